@@ -0,0 +1,464 @@
+use crate::base::{
+    BigInt, Data, Decimal, Domain, Double, Identifier, Integer, MAX_DECIMAL_PRECISION,
+};
+use crate::relation::MemTable;
+use crate::DBError;
+use std::cmp::Ordering;
+use std::error::Error;
+
+/// The aggregate functions which a SELECT may compute across the records it selects
+#[derive(Clone, Copy, PartialEq)]
+pub enum Function {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Function {
+    /// Returns the Function which the given (lowercase) word names, or None when it names none.
+    pub fn from(word: &str) -> Option<Self> {
+        match word {
+            "count" => Some(Function::Count),
+            "sum" => Some(Function::Sum),
+            "avg" => Some(Function::Avg),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            _ => None,
+        }
+    }
+
+    /// Returns the lowercase name of the Function.
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Count => "count",
+            Function::Sum => "sum",
+            Function::Avg => "avg",
+            Function::Min => "min",
+            Function::Max => "max",
+        }
+    }
+}
+
+/// An aggregate function in the projection of a SELECT and the attribute it is computed over,
+/// which is None for COUNT(*).
+pub struct Aggregate {
+    pub function: Function,
+    pub attribute: Option<Identifier>,
+}
+
+impl Aggregate {
+    /// Returns true for COUNT(*), which only needs the number of records selected.
+    pub fn counts_all(&self) -> bool {
+        self.attribute.is_none()
+    }
+
+    /// Returns the name the result of this Aggregate is listed under, such as sum_salary
+    /// for SUM(salary) or count for COUNT(*). This may be too long for an Identifier.
+    fn result_name(&self) -> String {
+        match self.attribute {
+            Some(ref attribute) => format!("{}_{}", self.function.name(), attribute.name()),
+            None => self.function.name().to_string(),
+        }
+    }
+}
+
+/// Attempts to create the Identifiers the results of aggregates are listed under, in the order
+/// they are given in. Names too long for an Identifier are cut short, and a name already given to
+/// an earlier result is cut short enough to end in the lowest number which makes it unique, such
+/// as count_2 for the second COUNT(*).
+///
+/// # Errors
+///
+/// Fails when a name is not a valid Identifier.
+fn result_names(aggregates: &[Aggregate]) -> Result<Vec<Identifier>, DBError> {
+    let mut names: Vec<Identifier> = Vec::with_capacity(aggregates.len());
+    for aggregate in aggregates {
+        let full_name = aggregate.result_name(); // ascii, like the Identifiers it is made of
+        let mut name = full_name[..full_name.len().min(19)].to_string();
+        let mut number = 2;
+        while names.iter().any(|taken| taken.name() == name) {
+            let suffix = format!("_{number}");
+            let kept = full_name.len().min(19 - suffix.len());
+            name = format!("{}{suffix}", &full_name[..kept]);
+            number += 1;
+        }
+        names.push(Identifier::from(&name)?);
+    }
+    Ok(names)
+}
+
+/// Attempts to compute each of the aggregates over the records of selection. Returns a MemTable
+/// with one record which holds their results in the order they are given. Null values are
+/// skipped, so COUNT(attribute) counts the records which have a value for it, and SUM, AVG,
+/// MIN, and MAX are Null when no record has one.
+///
+/// # Errors
+///
+/// Fails when an aggregate names an attribute which is not in selection, when SUM or AVG
+/// is asked for over an attribute which is not numeric, when a result is too large for its Domain,
+/// or when the name of a result is not a valid Identifier.
+pub fn aggregate(
+    aggregates: &[Aggregate],
+    selection: &MemTable,
+) -> Result<MemTable, Box<dyn Error>> {
+    let mut attributes = Vec::with_capacity(aggregates.len());
+    let mut record = Vec::with_capacity(aggregates.len());
+    for (aggregate, name) in aggregates.iter().zip(result_names(aggregates)?) {
+        let (data, domain) = match aggregate.attribute {
+            Some(ref attribute) => {
+                let attri_num = match selection
+                    .attributes
+                    .iter()
+                    .position(|(identifier, _)| identifier == attribute)
+                {
+                    Some(attri_num) => attri_num,
+                    None => Err(DBError::ConstraintError(
+                        "Could not find an attribute in the tables with the name given to an aggregate function.",
+                    ))?,
+                };
+                let values = selection
+                    .records
+                    .iter()
+                    .map(|record| &record[attri_num])
+                    .filter(|data| **data != Data::Null);
+                compute(
+                    aggregate.function,
+                    selection.attributes[attri_num].1,
+                    values,
+                )?
+            }
+            None => (count_to_data(selection.records.len())?, Domain::Integer),
+        };
+        attributes.push((name, domain));
+        record.push(data);
+    }
+    MemTable::build_from_records(vec![record], attributes)
+}
+
+/// Attempts to build the MemTable of results for aggregates which are all COUNT(*), given the
+/// number of records counted. This lets COUNT(*) skip reading the records when there is no
+/// Condition to check them against.
+///
+/// # Errors
+///
+/// Fails when count is too large for an Integer, or when the name of a result is not a valid Identifier.
+pub fn count_all(aggregates: &[Aggregate], count: usize) -> Result<MemTable, Box<dyn Error>> {
+    let mut attributes = Vec::with_capacity(aggregates.len());
+    let mut record = Vec::with_capacity(aggregates.len());
+    for name in result_names(aggregates)? {
+        attributes.push((name, Domain::Integer));
+        record.push(count_to_data(count)?);
+    }
+    MemTable::build_from_records(vec![record], attributes)
+}
+
+/// Attempts to compute function over the values of an attribute of the given Domain, which have
+/// had their Null values removed. Returns the result along with its Domain. COUNT is an Integer
+/// and AVG is a Double. SUM of Integers and BigInts is a BigInt, and SUM of Decimals is exact and
+/// keeps their scale. MIN and MAX have the Domain of the attribute.
+///
+/// # Errors
+///
+/// Fails when SUM or AVG is asked for over a Domain which is not numeric, or when the result is
+/// too large for its Domain.
+fn compute<'a>(
+    function: Function,
+    domain: Domain,
+    mut values: impl Iterator<Item = &'a Data>,
+) -> Result<(Data, Domain), Box<dyn Error>> {
+    if let Function::Sum | Function::Avg = function {
+        if !domain.is_numeric() {
+            Err(DBError::ConstraintError(
+                "SUM and AVG require an attribute of a numeric Domain.",
+            ))?
+        }
+    }
+
+    let result = match function {
+        Function::Count => (count_to_data(values.count())?, Domain::Integer),
+        Function::Min | Function::Max => {
+            let wanted = match function {
+                Function::Min => Ordering::Less,
+                _ => Ordering::Greater,
+            };
+            let extreme = values.fold(None, |extreme: Option<&Data>, data| match extreme {
+                Some(extreme) if data.cmp(extreme) != wanted => Some(extreme),
+                _ => Some(data),
+            });
+            (extreme.cloned().unwrap_or(Data::Null), domain)
+        }
+        Function::Sum => match domain {
+            Domain::Integer | Domain::BigInt => {
+                let sum = values.try_fold(None, |sum: Option<i64>, data| {
+                    sum.unwrap_or(0).checked_add(whole_number(data)).map(Some)
+                });
+                let sum = sum.ok_or(DBError::ConstraintError("SUM is too large for a BigInt."))?;
+                let sum = sum.map_or(Data::Null, |sum| Data::BigInt(BigInt::wrap(sum)));
+                (sum, Domain::BigInt)
+            }
+            Domain::Decimal(_, scale) => {
+                let sum = decimal_sum(values)?.map_or(Data::Null, Data::Decimal);
+                (sum, Domain::Decimal(MAX_DECIMAL_PRECISION, scale))
+            }
+            _ => {
+                let sum = values.fold(None, |sum: Option<f64>, data| {
+                    Some(sum.unwrap_or(0.0) + double(data))
+                });
+                let sum = match sum {
+                    Some(sum) => finite_double(sum, "SUM is too large for a Double.")?,
+                    None => Data::Null,
+                };
+                (sum, Domain::Double)
+            }
+        },
+        Function::Avg => {
+            let mut count = 0;
+            let sum = match domain {
+                Domain::Integer | Domain::BigInt => {
+                    // an i128 holds the sum of more i64s than there can be records
+                    let sum: i128 = values
+                        .inspect(|_| count += 1)
+                        .map(|data| whole_number(data) as i128)
+                        .sum();
+                    sum as f64
+                }
+                Domain::Decimal(..) => {
+                    decimal_sum(values.inspect(|_| count += 1))?.map_or(0.0, |sum| sum.to_f64())
+                }
+                _ => values.inspect(|_| count += 1).map(double).sum(),
+            };
+            let avg = match count {
+                0 => Data::Null,
+                count => finite_double(sum / count as f64, "AVG is too large for a Double.")?,
+            };
+            (avg, Domain::Double)
+        }
+    };
+    Ok(result)
+}
+
+/// Attempts to wrap count as an Integer, which is the Domain of COUNT.
+///
+/// # Errors
+///
+/// Fails when count is too large for an Integer.
+fn count_to_data(count: usize) -> Result<Data, DBError> {
+    match i32::try_from(count) {
+        Ok(count) => Ok(Data::Integer(Integer::wrap(count))),
+        Err(_) => Err(DBError::ConstraintError(
+            "COUNT is too large for an Integer.",
+        )),
+    }
+}
+
+/// Attempts to wrap value as a Double, which must be finite for Data::cmp to order it.
+///
+/// # Errors
+///
+/// Fails with the given message when value is infinite or NaN, such as when a sum overflows.
+fn finite_double(value: f64, message: &'static str) -> Result<Data, DBError> {
+    if !value.is_finite() {
+        return Err(DBError::ConstraintError(message));
+    }
+    Ok(Data::Double(Double::wrap(value)))
+}
+
+/// Attempts to add up Decimal values exactly. Returns None when there are no values.
+///
+/// # Errors
+///
+/// Fails when the sum has more digits than a Decimal can hold.
+fn decimal_sum<'a>(values: impl Iterator<Item = &'a Data>) -> Result<Option<Decimal>, DBError> {
+    let mut sum: Option<Decimal> = None;
+    for data in values {
+        let Data::Decimal(decimal) = data else {
+            unreachable!("an attribute only holds values of its own Domain");
+        };
+        let total = match sum {
+            Some(sum) => sum.checked_add(decimal),
+            None => Some(decimal.clone()),
+        };
+        sum = Some(total.ok_or(DBError::ConstraintError(
+            "SUM has more digits than a DECIMAL can hold.",
+        ))?);
+    }
+    Ok(sum)
+}
+
+/// Returns the value of an Integer or BigInt.
+///
+/// # Panics
+///
+/// Panics when given any other variant.
+fn whole_number(data: &Data) -> i64 {
+    match data {
+        Data::Integer(int) => *int.value() as i64,
+        Data::BigInt(int) => *int.value(),
+        _ => panic!("Only Integers and BigInts are whole numbers"),
+    }
+}
+
+/// Returns the value of a Double.
+///
+/// # Panics
+///
+/// Panics when given any other variant.
+fn double(data: &Data) -> f64 {
+    match data {
+        Data::Double(double) => *double.value(),
+        _ => panic!("Only Doubles are summed as floating point numbers"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate_of(function: Function, attribute: Option<&str>) -> Aggregate {
+        Aggregate {
+            function,
+            attribute: attribute.map(|name| Identifier::from(name).unwrap()),
+        }
+    }
+
+    fn int(value: i32) -> Data {
+        Data::Integer(Integer::wrap(value))
+    }
+
+    fn decimal(literal: &str) -> Data {
+        Data::Decimal(Decimal::from(literal).unwrap())
+    }
+
+    /// Returns what function computes over values, which are of the given Domain.
+    fn compute_over(function: Function, domain: Domain, values: &[Data]) -> (Data, Domain) {
+        compute(function, domain, values.iter()).unwrap()
+    }
+
+    #[test]
+    fn nulls_are_skipped() {
+        let attributes = vec![
+            (Identifier::from("id").unwrap(), Domain::Integer),
+            (Identifier::from("n").unwrap(), Domain::Integer),
+        ];
+        let records = vec![
+            vec![int(1), int(4)],
+            vec![int(2), Data::Null],
+            vec![int(3), int(8)],
+            vec![int(4), Data::Null],
+        ];
+        let selection = MemTable::build_from_records(records, attributes).unwrap();
+        let aggregates = [
+            aggregate_of(Function::Count, None),
+            aggregate_of(Function::Count, Some("n")),
+            aggregate_of(Function::Sum, Some("n")),
+            aggregate_of(Function::Avg, Some("n")),
+            aggregate_of(Function::Min, Some("n")),
+            aggregate_of(Function::Max, Some("n")),
+        ];
+        let result = aggregate(&aggregates, &selection).unwrap();
+        assert_eq!(
+            result.records,
+            vec![vec![
+                int(4),
+                int(2),
+                Data::BigInt(BigInt::wrap(12)),
+                Data::Double(Double::wrap(6.0)),
+                int(4),
+                int(8),
+            ]]
+        );
+    }
+
+    #[test]
+    fn empty_sets_have_null_results() {
+        for domain in [Domain::Integer, Domain::Double, Domain::Decimal(8, 2)] {
+            for function in [Function::Sum, Function::Avg, Function::Min, Function::Max] {
+                assert_eq!(compute_over(function, domain, &[]).0, Data::Null);
+            }
+            assert_eq!(compute_over(Function::Count, domain, &[]).0, int(0));
+        }
+        assert_eq!(
+            count_all(&[aggregate_of(Function::Count, None)], 0)
+                .unwrap()
+                .records,
+            [[int(0)]]
+        );
+    }
+
+    #[test]
+    fn sums_which_overflow_fail() {
+        let big = Data::BigInt(BigInt::wrap(i64::MAX));
+        let values = [big.clone(), Data::BigInt(BigInt::wrap(1))];
+        assert!(compute(Function::Sum, Domain::BigInt, values.iter()).is_err());
+        // AVG adds up in an i128, so it does not overflow where SUM does
+        let (avg, _) = compute_over(Function::Avg, Domain::BigInt, &[big.clone(), big]);
+        assert_eq!(avg, Data::Double(Double::wrap(i64::MAX as f64)));
+
+        let many = vec![int(i32::MAX); 3];
+        let (sum, domain) = compute_over(Function::Sum, Domain::Integer, &many);
+        assert_eq!(sum, Data::BigInt(BigInt::wrap(i32::MAX as i64 * 3)));
+        assert!(matches!(domain, Domain::BigInt));
+
+        let huge = [
+            Data::Double(Double::wrap(f64::MAX)),
+            Data::Double(Double::wrap(f64::MAX)),
+        ];
+        assert!(compute(Function::Sum, Domain::Double, huge.iter()).is_err());
+        assert!(compute(Function::Avg, Domain::Double, huge.iter()).is_err());
+        let widest = decimal(&"9".repeat(38));
+        let values = [widest, decimal("1")];
+        assert!(compute(Function::Sum, Domain::Decimal(38, 0), values.iter()).is_err());
+        assert!(count_to_data(i32::MAX as usize + 1).is_err());
+    }
+
+    #[test]
+    fn decimal_sums_keep_their_scale() {
+        let values = [decimal("1.10"), decimal("2.20"), decimal("-0.05")];
+        let (sum, domain) = compute_over(Function::Sum, Domain::Decimal(8, 2), &values);
+        assert_eq!(sum.to_string(), "3.25");
+        assert!(matches!(domain, Domain::Decimal(MAX_DECIMAL_PRECISION, 2)));
+        let values = [decimal("0.10"), decimal("0.20")];
+        let (sum, _) = compute_over(Function::Sum, Domain::Decimal(8, 2), &values);
+        assert_eq!(sum.to_string(), "0.30");
+        let (avg, _) = compute_over(Function::Avg, Domain::Decimal(8, 2), &values);
+        assert_eq!(avg, Data::Double(Double::wrap(0.15)));
+        assert!(compute(Function::Sum, Domain::Text, [].iter()).is_err());
+    }
+
+    #[test]
+    fn result_names_are_unique_identifiers() {
+        let names = |aggregates: &[Aggregate]| -> Vec<String> {
+            let names = result_names(aggregates).unwrap();
+            names.iter().map(|name| name.name().to_string()).collect()
+        };
+        let counts = [
+            aggregate_of(Function::Count, None),
+            aggregate_of(Function::Count, None),
+            aggregate_of(Function::Count, None),
+            aggregate_of(Function::Sum, Some("n")),
+            aggregate_of(Function::Sum, Some("n")),
+        ];
+        assert_eq!(
+            names(&counts),
+            ["count", "count_2", "count_3", "sum_n", "sum_n_2"]
+        );
+
+        // the longest Identifier has 19 characters
+        let long = [
+            aggregate_of(Function::Max, Some("abcdefghijklmnopqrs")),
+            aggregate_of(Function::Max, Some("abcdefghijklmnopqrs")),
+            aggregate_of(Function::Max, Some("abcdefghijklmnopxyz")),
+        ];
+        assert_eq!(
+            names(&long),
+            [
+                "max_abcdefghijklmno",
+                "max_abcdefghijklm_2",
+                "max_abcdefghijklm_3"
+            ]
+        );
+        assert!(names(&long).iter().all(|name| name.len() <= 19));
+    }
+}
@@ -0,0 +1,922 @@
+use crate::base::{Data, Key};
+use crate::wal::WalOp;
+use crate::DBError;
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Bound,
+};
+
+/// The size of every page in an index file. Page 0 holds the tree's header and every other page holds one Node.
+const PAGE_SIZE: usize = 4096;
+
+/// Written at the start of the header page so older index files can be told apart
+const MAGIC: &[u8; 8] = b"MDBBPT02";
+
+/// The number of bytes used by the header fields
+const HEADER_LEN: usize = 33;
+
+/// The most pages kept in memory before clean pages are dropped again
+const CACHE_PAGES: usize = 1024;
+
+/// Bytes used by a Node's kind and entry count
+const NODE_HEADER_LEN: usize = 3;
+
+/// The most bytes the values of one key may take once encoded. Any four entries fit in a page,
+/// so a Node which has outgrown its page by one entry always splits into two halves that fit.
+const MAX_KEY_LEN: usize = (PAGE_SIZE - NODE_HEADER_LEN - 8) / 4 - 16;
+
+/// The most bytes a Text value of a key may take. Encoding a Text adds at most six bytes,
+/// so every value then fits its u8 length prefix.
+const MAX_KEY_TEXT_LEN: usize = u8::MAX as usize - 6;
+
+/// A page number within the index file
+type PageNum = u64;
+
+/// A key along with the data (record number) stored with it. Entries are ordered by key,
+/// and then by data in trees that allow duplicate keys.
+type Entry = (Key, usize);
+
+/// A single page of the tree. Each value of a key is stored with a u8 length prefix followed by
+/// the bytes from Data::as_bytes, so no value may need more than u8::MAX bytes. The number of
+/// values in a key is the number of columns in the tree's header.
+#[derive(Debug, Clone)]
+enum Node {
+    /// Holds the entries themselves in order. next links every leaf to the one after it
+    /// so the entries can be walked in order.
+    Leaf {
+        entries: Vec<Entry>,
+        next: Option<PageNum>,
+    },
+    /// Holds one more child than separators. Every entry in children[i] is less than
+    /// separators[i] and every entry in children[i + 1] is greater than or equal to it.
+    Internal {
+        separators: Vec<Entry>,
+        children: Vec<PageNum>,
+    },
+}
+
+impl Node {
+    /// Returns the number of bytes this Node takes up once encoded.
+    fn encoded_len(&self) -> usize {
+        // the first u64, and then every entry
+        let (entries, per_entry) = self.entries();
+        NODE_HEADER_LEN
+            + 8
+            + entries
+                .iter()
+                .map(|(key, _)| key_len(key) + per_entry)
+                .sum::<usize>()
+    }
+
+    /// Returns the entries (or separators) of this Node along with the bytes each takes once
+    /// encoded on top of its key: a u64 for the data, and another for the child of an Internal Node.
+    fn entries(&self) -> (&Vec<Entry>, usize) {
+        match self {
+            Node::Leaf { entries, .. } => (entries, 8),
+            Node::Internal { separators, .. } => (separators, 16),
+        }
+    }
+
+    /// Returns the position to split this Node at so that the entries before it and the entries
+    /// from it on take about the same number of bytes. There is at least one entry on either side,
+    /// and an Internal Node keeps at least one separator on either side of the one that moves up.
+    fn split_point(&self) -> usize {
+        let (entries, per_entry) = self.entries();
+        let sizes: Vec<usize> = entries
+            .iter()
+            .map(|(key, _)| key_len(key) + per_entry)
+            .collect();
+        let half = sizes.iter().sum::<usize>() / 2;
+        let mut split = 0;
+        let mut size = 0;
+        while split < sizes.len() && size + sizes[split] / 2 < half {
+            size += sizes[split];
+            split += 1;
+        }
+        match self {
+            Node::Leaf { .. } => split.clamp(1, entries.len() - 1),
+            Node::Internal { .. } => split.clamp(1, entries.len() - 2),
+        }
+    }
+
+    /// Returns the page representation of this Node. The format is a one byte kind,
+    /// a u16 entry count, and then the entries. Leaves start with the page number of the
+    /// next leaf (0 for none) and then store (key, data) pairs. Internal nodes start with
+    /// their first child and then store (key, data, child) triples.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the Node does not fit in a page.
+    fn encode(&self) -> Result<Vec<u8>, DBError> {
+        let mut page = Vec::with_capacity(PAGE_SIZE);
+        let (kind, entries, first, children): (u8, &Vec<Entry>, u64, &[u64]) = match self {
+            Node::Leaf { entries, next } => (0, entries, next.unwrap_or(0), &[]),
+            Node::Internal {
+                separators,
+                children,
+            } => (1, separators, children[0], &children[1..]),
+        };
+        page.push(kind);
+        page.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        page.extend_from_slice(&first.to_be_bytes());
+        for (i, (key, data)) in entries.iter().enumerate() {
+            for value in key.values() {
+                let value_bytes = value.as_bytes();
+                page.push(value_bytes.len() as u8);
+                page.extend_from_slice(&value_bytes);
+            }
+            page.extend_from_slice(&(*data as u64).to_be_bytes());
+            if let Some(child) = children.get(i) {
+                page.extend_from_slice(&child.to_be_bytes());
+            }
+        }
+        if page.len() > PAGE_SIZE {
+            return Err(DBError::FileFormatError(
+                "Index node is too large to fit in its page.",
+            ));
+        }
+        page.resize(PAGE_SIZE, 0);
+        Ok(page)
+    }
+
+    /// Reads a Node back from a page written by encode for a tree whose keys hold key_len values.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the page is not a valid Node.
+    fn decode(page: &[u8], key_len: usize) -> Result<Self, Box<dyn Error>> {
+        let bad_page = || DBError::FileFormatError("Index page is not a valid tree node.");
+        let read_u64 = |at: usize| -> Result<u64, DBError> {
+            match page.get(at..at + 8) {
+                Some(bytes) => Ok(u64::from_be_bytes(bytes.try_into().unwrap())),
+                None => Err(bad_page()),
+            }
+        };
+
+        let kind = page[0];
+        let count = u16::from_be_bytes([page[1], page[2]]) as usize;
+        let first = read_u64(NODE_HEADER_LEN)?;
+
+        let mut entries = Vec::with_capacity(count);
+        let mut children = vec![first];
+        let mut index = NODE_HEADER_LEN + 8;
+        for _ in 0..count {
+            let mut values = Vec::with_capacity(key_len);
+            for _ in 0..key_len {
+                let value_len = *page.get(index).ok_or_else(bad_page)? as usize;
+                index += 1; // for the value_len just read
+                let value_bytes = page.get(index..index + value_len).ok_or_else(bad_page)?;
+                values.push(Data::from_bytes(value_bytes)?);
+                index += value_len; // for the value just read
+            }
+            entries.push((Key::new(values), read_u64(index)? as usize));
+            index += 8;
+            if kind == 1 {
+                children.push(read_u64(index)?);
+                index += 8;
+            }
+        }
+
+        match kind {
+            0 => Ok(Node::Leaf {
+                entries,
+                next: if first == 0 { None } else { Some(first) }, // page 0 is the header so never a leaf
+            }),
+            1 => Ok(Node::Internal {
+                separators: entries,
+                children,
+            }),
+            _ => Err(Box::new(bad_page())),
+        }
+    }
+}
+
+/// Checks that key can be stored in a tree, which is checked by a Table before any of its
+/// records or indexes change.
+///
+/// # Errors
+///
+/// Fails when a Text value of key takes more than MAX_KEY_TEXT_LEN bytes, or when its
+/// values take more than MAX_KEY_LEN bytes together once encoded.
+pub fn check_key_len(key: &Key) -> Result<(), DBError> {
+    let too_long_text = key.values().iter().any(|value| match value {
+        Data::Text(text) => text.content().len() > MAX_KEY_TEXT_LEN,
+        value => value.as_bytes().len() > u8::MAX as usize,
+    });
+    if too_long_text {
+        return Err(DBError::ConstraintError(
+            "A Text value of a key or index cannot take more than 249 bytes.",
+        ));
+    }
+    if key_len(key) > MAX_KEY_LEN {
+        return Err(DBError::ConstraintError(
+            "The values of a key or index cannot take more than 1005 bytes together.",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the number of bytes the values of key take once encoded, each with its length byte.
+fn key_len(key: &Key) -> usize {
+    key.values()
+        .iter()
+        .map(|value| value.as_bytes().len() + 1)
+        .sum()
+}
+
+/// A B+tree index kept in pages of a file. Pages are only read once they are needed
+/// and changes are kept in memory until take_ops hands them to the write-ahead log, so
+/// the index is updated in place alongside the records it points to.
+///
+/// A unique tree holds each key at most once. Other trees may hold a key many times as
+/// long as the data stored with it differs.
+///
+/// Removing a key never merges pages. Space freed in a leaf is reused by later inserts
+/// into that leaf.
+#[derive(Debug)]
+pub struct BPlusTree {
+    /// The file this tree belongs to. All ops are written against this path.
+    path: String,
+    /// The file pages are read from. Differs from path while a transaction has a shadow copy.
+    read_path: String,
+    unique: bool,
+    /// The attribute numbers of the indexed attributes in its table, in key order
+    columns: Vec<usize>,
+    root: PageNum,
+    page_count: u64,
+    /// Pages read so far along with any pages changed since the last take_ops
+    pages: RefCell<HashMap<PageNum, Node>>,
+    /// Pages that differ from the file
+    dirty: BTreeSet<PageNum>,
+    /// True when the header fields differ from the header in the file
+    header_dirty: bool,
+}
+
+impl BPlusTree {
+    /// Returns a new tree holding no keys for the attributes numbered columns.
+    /// Nothing is written until take_ops is called.
+    pub fn new(path: &str, unique: bool, columns: Vec<usize>) -> Self {
+        let mut pages = HashMap::new();
+        pages.insert(
+            1,
+            Node::Leaf {
+                entries: Vec::new(),
+                next: None,
+            },
+        );
+        BPlusTree {
+            path: path.to_string(),
+            read_path: path.to_string(),
+            unique,
+            columns,
+            root: 1,
+            page_count: 2,
+            pages: RefCell::new(pages),
+            dirty: BTreeSet::from([1]),
+            header_dirty: true,
+        }
+    }
+
+    /// Attempts to open the tree stored at path. Only the header is read here.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or does not start with a tree header.
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut tree = BPlusTree {
+            path: path.to_string(),
+            read_path: path.to_string(),
+            unique: false,
+            columns: Vec::new(),
+            root: 0,
+            page_count: 0,
+            pages: RefCell::new(HashMap::new()),
+            dirty: BTreeSet::new(),
+            header_dirty: false,
+        };
+        tree.read_header()?;
+        Ok(tree)
+    }
+
+    /// Attempts to open the tree stored at path like open. Returns None when there is no file at
+    /// path, or when the file is an index from before this format, which must be rebuilt.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or when it is a tree which cannot be opened.
+    pub fn open_if_current(path: &str) -> Result<Option<Self>, Box<dyn Error>> {
+        let mut magic = Vec::with_capacity(MAGIC.len());
+        match File::open(path) {
+            Ok(file) => file.take(MAGIC.len() as u64).read_to_end(&mut magic)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Box::new(err)),
+        };
+        if magic != MAGIC {
+            return Ok(None); // an index written before its pages were given this format
+        }
+        Ok(Some(BPlusTree::open(path)?))
+    }
+
+    /// Returns the path of the file this tree belongs to.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the attribute numbers of the attributes this tree indexes, in key order.
+    pub fn columns(&self) -> &[usize] {
+        &self.columns
+    }
+
+    /// Makes the tree read pages from read_path from now on. Used while a transaction keeps its
+    /// changes in a shadow copy of the file.
+    pub fn read_from(&mut self, read_path: String) {
+        self.read_path = read_path;
+    }
+
+    /// Reads the header fields back from the header page.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or does not start with a tree header.
+    fn read_header(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut header = [0; PAGE_SIZE];
+        File::open(&self.read_path)?.read_exact(&mut header)?;
+        if &header[0..8] != MAGIC {
+            return Err(Box::new(DBError::FileFormatError(
+                "Index file does not start with a B+tree header.",
+            )));
+        }
+        self.root = u64::from_be_bytes(header[8..16].try_into().unwrap());
+        self.page_count = u64::from_be_bytes(header[16..24].try_into().unwrap());
+        self.unique = header[24] == 1;
+        self.columns = vec![u64::from_be_bytes(header[25..33].try_into().unwrap()) as usize];
+
+        // any columns after the first follow their count
+        let extra_columns = header[HEADER_LEN] as usize;
+        for i in 0..extra_columns {
+            let at = HEADER_LEN + 1 + i * 8;
+            let column = u64::from_be_bytes(header[at..at + 8].try_into().unwrap());
+            self.columns.push(column as usize);
+        }
+        Ok(())
+    }
+
+    /// Compares two entries. Only keys are compared in a unique tree.
+    fn cmp_entries(&self, entry: &Entry, other: &Entry) -> Ordering {
+        match entry.0.cmp(&other.0) {
+            Ordering::Equal if !self.unique => entry.1.cmp(&other.1),
+            ordering => ordering,
+        }
+    }
+
+    /// Makes sure the Node at page is in memory, reading it from the file if needed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the page cannot be read or is not a valid Node.
+    fn load(&self, page: PageNum) -> Result<(), Box<dyn Error>> {
+        let mut pages = self.pages.borrow_mut();
+        if pages.contains_key(&page) {
+            return Ok(());
+        }
+
+        if pages.len() >= CACHE_PAGES {
+            // clean pages can always be read again so only changed pages must stay
+            pages.retain(|page, _| self.dirty.contains(page));
+        }
+
+        let mut file = File::open(&self.read_path)?;
+        file.seek(SeekFrom::Start(page * PAGE_SIZE as u64))?;
+        let mut buf = vec![0; PAGE_SIZE];
+        file.read_exact(&mut buf)?;
+        pages.insert(page, Node::decode(&buf, self.columns.len())?);
+        Ok(())
+    }
+
+    /// Returns a copy of the Node at page.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the page cannot be read.
+    fn node(&self, page: PageNum) -> Result<Node, Box<dyn Error>> {
+        self.load(page)?;
+        Ok(self.pages.borrow()[&page].clone())
+    }
+
+    /// Replaces the Node at page and marks it to be written by take_ops.
+    fn set_node(&mut self, page: PageNum, node: Node) {
+        self.pages.get_mut().insert(page, node);
+        self.dirty.insert(page);
+    }
+
+    /// Returns the number of a fresh page at the end of the file.
+    fn allocate(&mut self) -> PageNum {
+        self.page_count += 1;
+        self.header_dirty = true;
+        self.page_count - 1
+    }
+
+    /// Returns the position of the child that could hold entry in an Internal Node.
+    fn child_index(&self, separators: &[Entry], entry: &Entry) -> usize {
+        separators.partition_point(|sep| self.cmp_entries(sep, entry) != Ordering::Greater)
+    }
+
+    /// Returns the number of the leaf page that holds entry if it is anywhere in the tree.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    fn find_leaf(&self, entry: &Entry) -> Result<PageNum, Box<dyn Error>> {
+        let mut page = self.root;
+        loop {
+            self.load(page)?;
+            match &self.pages.borrow()[&page] {
+                Node::Leaf { .. } => return Ok(page),
+                Node::Internal {
+                    separators,
+                    children,
+                } => {
+                    page = children[self.child_index(separators, entry)];
+                }
+            }
+        }
+    }
+
+    /// Returns the data stored with key if the key is in the tree. In a tree that
+    /// allows duplicate keys, or when key is only a prefix of the tree's keys,
+    /// this is the data stored with the smallest matching key.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    pub fn find(&self, key: &Key) -> Result<Option<usize>, Box<dyn Error>> {
+        Ok(self.find_all(key)?.first().copied())
+    }
+
+    /// Returns the data stored with every copy of key in the tree, or with every key that
+    /// starts with key when it is only a prefix of the tree's keys, in key order.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    pub fn find_all(&self, key: &Key) -> Result<Vec<usize>, Box<dyn Error>> {
+        self.range(Bound::Included(key), Bound::Included(key))
+    }
+
+    /// Returns the data stored with every key between lower and upper, in key order.
+    /// Bounds may be prefixes of the tree's keys, in which case only that many values
+    /// of each key are compared. Only the leaves which hold keys in the range are read.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    pub fn range(
+        &self,
+        lower: Bound<&Key>,
+        upper: Bound<&Key>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let mut page = match lower {
+            // (key, 0) sorts before every other entry with this key
+            Bound::Included(key) | Bound::Excluded(key) => Some(self.find_leaf(&(key.clone(), 0))?),
+            Bound::Unbounded => Some(self.first_leaf()?),
+        };
+
+        let mut all_data = Vec::new();
+        while let Some(curr_page) = page {
+            let (entries, next) = match self.node(curr_page)? {
+                Node::Leaf { entries, next } => (entries, next),
+                Node::Internal { .. } => Err(DBError::FileFormatError(
+                    "Index leaf links to a non-leaf page.",
+                ))?,
+            };
+            let first = match lower {
+                Bound::Included(key) => entries
+                    .partition_point(|(entry_key, _)| entry_key.cmp_prefix(key) == Ordering::Less),
+                Bound::Excluded(key) => entries.partition_point(|(entry_key, _)| {
+                    entry_key.cmp_prefix(key) != Ordering::Greater
+                }),
+                Bound::Unbounded => 0,
+            };
+            for (entry_key, data) in &entries[first..] {
+                let past_upper = match upper {
+                    Bound::Included(key) => entry_key.cmp_prefix(key) == Ordering::Greater,
+                    Bound::Excluded(key) => entry_key.cmp_prefix(key) != Ordering::Less,
+                    Bound::Unbounded => false,
+                };
+                if past_upper {
+                    return Ok(all_data);
+                }
+                all_data.push(*data);
+            }
+            page = next; // the range may carry on into the next leaf
+        }
+        Ok(all_data)
+    }
+
+    /// Returns the number of the left most leaf page, which starts the chain of leaves.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    fn first_leaf(&self) -> Result<PageNum, Box<dyn Error>> {
+        let mut page = self.root;
+        loop {
+            match self.node(page)? {
+                Node::Leaf { .. } => return Ok(page),
+                Node::Internal { children, .. } => page = children[0],
+            }
+        }
+    }
+
+    /// Attempts to insert key with the given data. Nothing changes when the insert fails.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the key is already in a unique tree, when check_key_len finds it too long,
+    /// or when a page cannot be read.
+    pub fn insert(&mut self, key: Key, data: usize) -> Result<(), Box<dyn Error>> {
+        check_key_len(&key)?;
+
+        if let Some((separator, right)) = self.insert_into(self.root, (key, data))? {
+            // the root split so the tree grows by one level
+            let new_root = self.allocate();
+            self.set_node(
+                new_root,
+                Node::Internal {
+                    separators: vec![separator],
+                    children: vec![self.root, right],
+                },
+            );
+            self.root = new_root;
+            self.header_dirty = true;
+        }
+        Ok(())
+    }
+
+    /// Inserts entry into the subtree at page. Returns the separator and page of the new
+    /// right sibling when the Node at page had to be split.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the entry is already in the tree or when a page cannot be read.
+    fn insert_into(
+        &mut self,
+        page: PageNum,
+        entry: Entry,
+    ) -> Result<Option<(Entry, PageNum)>, Box<dyn Error>> {
+        let mut node = self.node(page)?;
+        match node {
+            Node::Leaf {
+                ref mut entries, ..
+            } => match entries.binary_search_by(|probe| self.cmp_entries(probe, &entry)) {
+                Ok(_) => Err(DBError::ConstraintError(
+                    "Cannot insert a key that already exists in the table.",
+                ))?,
+                Err(i) => entries.insert(i, entry),
+            },
+            Node::Internal {
+                ref mut separators,
+                ref mut children,
+            } => {
+                let i = self.child_index(separators, &entry);
+                match self.insert_into(children[i], entry)? {
+                    Some((separator, right)) => {
+                        separators.insert(i, separator);
+                        children.insert(i + 1, right);
+                    }
+                    None => return Ok(None), // nothing changed at this level
+                }
+            }
+        }
+
+        if node.encoded_len() <= PAGE_SIZE {
+            self.set_node(page, node);
+            return Ok(None);
+        }
+
+        // the node no longer fits in its page so move the upper half of its bytes to a new page
+        let split = node.split_point();
+        let right_page = self.allocate();
+        let (separator, right) = match node {
+            Node::Leaf {
+                ref mut entries,
+                ref mut next,
+            } => {
+                let right_entries = entries.split_off(split);
+                let separator = right_entries[0].clone();
+                let right = Node::Leaf {
+                    entries: right_entries,
+                    next: next.replace(right_page),
+                };
+                (separator, right)
+            }
+            Node::Internal {
+                ref mut separators,
+                ref mut children,
+            } => {
+                let right_separators = separators.split_off(split + 1);
+                let separator = separators.pop().unwrap(); // moves up instead of staying in either half
+                let right = Node::Internal {
+                    separators: right_separators,
+                    children: children.split_off(split + 1),
+                };
+                (separator, right)
+            }
+        };
+        self.set_node(page, node);
+        self.set_node(right_page, right);
+        Ok(Some((separator, right_page)))
+    }
+
+    /// Returns the data from the removed key when the key is found and removed.
+    /// Only meant for unique trees - use remove_entry when keys may repeat.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    pub fn remove(&mut self, key: &Key) -> Result<Option<usize>, Box<dyn Error>> {
+        self.remove_entry(key, 0)
+    }
+
+    /// Removes the copy of key stored with data. In a unique tree data is ignored and the
+    /// key is removed whatever it is stored with. Returns the data that was removed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    pub fn remove_entry(
+        &mut self,
+        key: &Key,
+        data: usize,
+    ) -> Result<Option<usize>, Box<dyn Error>> {
+        let entry = (key.clone(), data);
+        let page = self.find_leaf(&entry)?;
+        let mut node = self.node(page)?;
+        let removed = match node {
+            Node::Leaf {
+                ref mut entries, ..
+            } => match entries.binary_search_by(|probe| self.cmp_entries(probe, &entry)) {
+                Ok(i) => Some(entries.remove(i).1),
+                Err(_) => None,
+            },
+            Node::Internal { .. } => unreachable!("find_leaf only returns leaves"),
+        };
+        if removed.is_some() {
+            self.set_node(page, node);
+        }
+        Ok(removed)
+    }
+
+    /// Returns the data values of all keys in key order by walking the leaves.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a page cannot be read.
+    pub fn get_data(&self) -> Result<Vec<usize>, Box<dyn Error>> {
+        self.range(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Returns the ops which write every page changed since the last call, and forgets that
+    /// they were changed. The caller must apply the ops for the file to match the tree.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a changed page cannot be encoded, and then nothing is forgotten.
+    pub fn take_ops(&mut self) -> Result<Vec<WalOp>, DBError> {
+        let pages = self.pages.get_mut();
+        let mut page_ops = Vec::with_capacity(self.dirty.len());
+        for page in self.dirty.iter() {
+            page_ops.push(WalOp::Write {
+                path: self.path.clone(),
+                offset: page * PAGE_SIZE as u64,
+                bytes: pages[page].encode()?,
+            });
+        }
+        self.dirty.clear();
+
+        let mut ops = Vec::with_capacity(page_ops.len() + 1);
+        if self.header_dirty {
+            let mut header = Vec::with_capacity(PAGE_SIZE);
+            header.extend_from_slice(MAGIC);
+            header.extend_from_slice(&self.root.to_be_bytes());
+            header.extend_from_slice(&self.page_count.to_be_bytes());
+            header.push(self.unique as u8);
+            header.extend_from_slice(&(self.columns[0] as u64).to_be_bytes());
+            header.push(self.columns.len() as u8 - 1);
+            for column in &self.columns[1..] {
+                header.extend_from_slice(&(*column as u64).to_be_bytes());
+            }
+            header.resize(PAGE_SIZE, 0);
+            ops.push(WalOp::Write {
+                path: self.path.clone(),
+                offset: 0,
+                bytes: header,
+            });
+            self.header_dirty = false;
+        }
+        ops.append(&mut page_ops);
+        Ok(ops)
+    }
+
+    /// Forgets every page read so far, along with any changes, and reads the header back from
+    /// read_path. Used once the file has been changed underneath the tree.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the header cannot be read back from the file.
+    pub fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.get_mut().clear();
+        self.dirty.clear();
+        self.header_dirty = false;
+        self.read_header()
+    }
+
+    /// Throws away every change made since the last call to take_ops so the tree
+    /// matches its file again.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the header cannot be read back from the file.
+    pub fn discard_changes(&mut self) -> Result<(), Box<dyn Error>> {
+        let pages = self.pages.get_mut();
+        for page in std::mem::take(&mut self.dirty) {
+            pages.remove(&page);
+        }
+        if self.header_dirty {
+            self.header_dirty = false;
+            self.read_header()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Integer, Text};
+    use std::fs;
+
+    /// Returns the path of an index file in an empty directory kept for the named test.
+    fn test_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "mini_dbms_b_plus_tree_{}_{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        format!("{}/t.idx", dir.display())
+    }
+
+    fn remove_test_dir(path: &str) {
+        fs::remove_dir_all(std::path::Path::new(path).parent().unwrap()).unwrap();
+    }
+
+    fn int_key(value: i32) -> Key {
+        Key::new(vec![Data::Integer(Integer::wrap(value))])
+    }
+
+    /// Returns a key of a single Text value ending in value, as long as a Text in a key may be.
+    fn text_key(value: usize) -> Key {
+        Key::new(vec![Data::Text(
+            Text::from(&format!("{value:0>249}")).unwrap(),
+        )])
+    }
+
+    /// Writes every change made to tree to its file.
+    fn flush(tree: &mut BPlusTree) {
+        for op in tree.take_ops().unwrap() {
+            op.apply().unwrap();
+        }
+    }
+
+    #[test]
+    fn inserts_split_and_keep_key_order() {
+        let path = test_path("split");
+        let mut tree = BPlusTree::new(&path, true, vec![0]);
+        // insert out of order so that splits happen in the middle of nodes as well as the end
+        for i in 0..5000 {
+            let value = (i * 7919) % 5000;
+            tree.insert(int_key(value), value as usize).unwrap();
+        }
+        assert!(tree.page_count > 2);
+        assert_eq!(tree.get_data().unwrap(), (0..5000).collect::<Vec<_>>());
+        assert_eq!(tree.find(&int_key(4321)).unwrap(), Some(4321));
+        assert!(tree.insert(int_key(4321), 0).is_err());
+        remove_test_dir(&path);
+    }
+
+    #[test]
+    fn wide_keys_split_into_pages_that_fit() {
+        let path = test_path("wide");
+        let mut tree = BPlusTree::new(&path, false, vec![0, 1, 2, 3]);
+        let wide_key = |i: usize| {
+            let mut values = text_key(i).values().to_vec();
+            values.extend_from_slice(text_key(i).values());
+            values.extend_from_slice(text_key(i).values());
+            values.push(Data::Text(Text::from("a").unwrap()));
+            Key::new(values)
+        };
+        for i in (0..300).rev() {
+            tree.insert(wide_key(i), i).unwrap();
+        }
+        flush(&mut tree);
+        let mut tree = BPlusTree::open(&path).unwrap();
+        assert_eq!(tree.get_data().unwrap(), (0..300).collect::<Vec<_>>());
+
+        // a fourth wide value takes the key over MAX_KEY_LEN
+        let mut values = wide_key(0).values().to_vec();
+        values.push(values[0].clone());
+        assert!(tree.insert(Key::new(values), 0).is_err());
+        remove_test_dir(&path);
+    }
+
+    #[test]
+    fn removed_keys_are_gone() {
+        let path = test_path("remove");
+        let mut tree = BPlusTree::new(&path, false, vec![0]);
+        for i in 0..2000 {
+            tree.insert(text_key(i % 1000), i).unwrap();
+        }
+        for i in (0..1000).step_by(2) {
+            assert_eq!(
+                tree.remove_entry(&text_key(i), i + 1000).unwrap(),
+                Some(i + 1000)
+            );
+        }
+        assert_eq!(tree.remove_entry(&text_key(0), 1000).unwrap(), None);
+        assert_eq!(tree.find_all(&text_key(0)).unwrap(), vec![0]);
+        assert_eq!(tree.find_all(&text_key(1)).unwrap(), vec![1, 1001]);
+        assert_eq!(tree.get_data().unwrap().len(), 1500);
+        remove_test_dir(&path);
+    }
+
+    #[test]
+    fn range_scans_stop_at_their_bounds() {
+        let path = test_path("range");
+        let mut tree = BPlusTree::new(&path, true, vec![0]);
+        for i in 0..1000 {
+            tree.insert(int_key(i), i as usize).unwrap();
+        }
+        let lower = int_key(250);
+        let upper = int_key(750);
+        assert_eq!(
+            tree.range(Bound::Included(&lower), Bound::Excluded(&upper))
+                .unwrap(),
+            (250..750).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.range(Bound::Excluded(&lower), Bound::Included(&upper))
+                .unwrap(),
+            (251..=750).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.range(Bound::Unbounded, Bound::Excluded(&int_key(3)))
+                .unwrap(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            tree.range(Bound::Excluded(&int_key(997)), Bound::Unbounded)
+                .unwrap(),
+            vec![998, 999]
+        );
+        remove_test_dir(&path);
+    }
+
+    #[test]
+    fn reopened_trees_hold_the_same_keys() {
+        let path = test_path("reopen");
+        let mut tree = BPlusTree::new(&path, true, vec![2]);
+        for i in 0..3000 {
+            tree.insert(int_key(i), i as usize).unwrap();
+        }
+        flush(&mut tree);
+        for i in 0..1000 {
+            tree.remove(&int_key(i)).unwrap();
+        }
+        flush(&mut tree);
+
+        let tree = BPlusTree::open_if_current(&path).unwrap().unwrap();
+        assert!(tree.unique);
+        assert_eq!(tree.columns(), &[2]);
+        assert_eq!(tree.get_data().unwrap(), (1000..3000).collect::<Vec<_>>());
+        assert_eq!(tree.find(&int_key(2500)).unwrap(), Some(2500));
+        assert_eq!(tree.find(&int_key(500)).unwrap(), None);
+        remove_test_dir(&path);
+    }
+
+    #[test]
+    fn only_missing_and_older_files_are_not_opened() {
+        let path = test_path("legacy");
+        assert!(BPlusTree::open_if_current(&path).unwrap().is_none());
+        fs::write(&path, b"MDBBPT01").unwrap();
+        assert!(BPlusTree::open_if_current(&path).unwrap().is_none());
+        fs::write(&path, MAGIC).unwrap(); // the current format, but cut short
+        assert!(BPlusTree::open_if_current(&path).is_err());
+        remove_test_dir(&path);
+    }
+}
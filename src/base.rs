@@ -1,7 +1,7 @@
 use crate::DBError;
 use std::cmp::Ordering;
 use std::error::Error;
-use std::fs::File;
+use std::fmt::Display;
 use std::io::Write;
 
 /// The Identifier type is a wrapper type for attribute names in tables and database/table names.
@@ -18,8 +18,8 @@ impl Identifier {
     ///
     /// # Examples
     ///
-    /// ```
-    /// use dbms::base::Identifier;
+    /// ```ignore
+    /// use mini_dbms::base::Identifier;
     ///
     /// Identifier::from("name1234").unwrap();
     /// ```
@@ -42,7 +42,7 @@ impl Identifier {
             return Err(DBError::ParseError("Identifer cannot be an empty string."));
         }
 
-        if let Ok(_) = name.parse::<u32>() {
+        if name.parse::<u32>().is_ok() {
             return Err(DBError::ParseError("Identifer cannot be all numeric."));
         }
 
@@ -55,7 +55,10 @@ impl Identifier {
         match name {
             "create" | "database" | "select" | "use" | "describe" | "let" | "insert" | "update"
             | "delete" | "input" | "exit" | "rename" | "table" | "primary" | "key" | "where"
-            | "from" | "all" | "values" | "set" | "output" | "none" => Err(DBError::ParseError(
+            | "from" | "all" | "values" | "set" | "output" | "none" | "null" | "and" | "or"
+            | "is" | "not" | "default" | "check" | "true" | "false" | "order" | "by" | "asc"
+            | "desc" | "limit" | "offset" | "references" | "on" | "index" | "drop" | "begin"
+            | "commit" | "rollback" | "between" => Err(DBError::ParseError(
                 "Cannot set an Identifier to a command name or reserved keyword",
             )),
             _ => Ok(Identifier {
@@ -76,11 +79,11 @@ impl Identifier {
     /// # Errors
     ///
     /// Fails when cannot write to the file given.
-    pub fn write_to_file(&self, mut file: &File) -> Result<(), std::io::Error> {
+    pub fn write_to_file(&self, mut file: impl Write) -> Result<(), std::io::Error> {
         let mut buf_to_write: [u8; 19] = [b' '; 19]; // write spaces that can be trimmed
         self.name
             .as_bytes()
-            .into_iter()
+            .iter()
             .enumerate()
             .for_each(|(i, byte)| {
                 buf_to_write[i] = *byte;
@@ -90,54 +93,64 @@ impl Identifier {
 }
 
 /// A base datatype of the overall database. Wraps Strings ensuring
-/// that they have length less than 100 characters.
+/// that their length in bytes fits in a u32.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Text {
     content: String,
 }
 
 impl Text {
-    /// Reads an instance of Text back from a u8 slice.
+    /// Reads an instance of Text back from a u8 slice, trimming the spaces
+    /// it may have been padded with.
     ///
     /// # Errors
     ///
-    /// This function expects a byte array with length 100 or less.
-    /// It fails when
-    /// - The given slice contains more than 100 characters
-    /// - The slice fails to be read to a String
+    /// Fails when the slice fails to be read to a String.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        if bytes.len() > 100 {
-            // should never happen since we should only ever read from valid locations but...
-            return Err(Box::new(DBError::ParseError(
-                "Text should have been stored as 100 or fewer characters.",
-            )));
-        }
-        Ok(Text::from(&String::from_utf8(bytes.to_vec())?.trim())?)
+        Ok(Text::from(String::from_utf8(bytes.to_vec())?.trim())?)
     }
 
-    /// Returns the u8 byte array which represents this Text instance.
-    /// These are formatted to always be 100 characters long by appending spaces.
-    pub fn to_bytes(&self) -> [u8; 100] {
-        let mut buf: [u8; 100] = [b' '; 100]; // write spaces that can be trimmed
-        self.content
-            .as_bytes()
-            .into_iter()
-            .enumerate()
-            .for_each(|(i, byte)| {
-                // overwrite buf with characters from self.content
-                buf[i] = *byte;
-            });
-        buf
+    /// Reads an instance of Text back from the bytes a VarChar stores it as, which are
+    /// a u16 length followed by the content and then padding.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the length runs past the end of bytes or the content is not utf-8.
+    pub fn from_varchar_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+        let content = bytes.get(2..2 + len).ok_or(DBError::FileFormatError(
+            "VarChar is longer than its limit.",
+        ))?;
+        Ok(Text::from(std::str::from_utf8(content)?)?)
+    }
+
+    /// Returns the bytes a VarChar with the given limit stores this Text as. These are a u16
+    /// length followed by the content, padded with zeroes to always be limit + 2 bytes long.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the content is longer than limit bytes.
+    pub fn to_varchar_bytes(&self, limit: u16) -> Result<Vec<u8>, DBError> {
+        if self.content.len() > limit as usize {
+            return Err(DBError::ConstraintError(
+                "Text is longer than its VARCHAR limit.",
+            ));
+        }
+        let mut buf = Vec::with_capacity(limit as usize + 2);
+        buf.extend_from_slice(&(self.content.len() as u16).to_be_bytes());
+        buf.extend_from_slice(self.content.as_bytes());
+        buf.resize(limit as usize + 2, 0);
+        Ok(buf)
     }
 
     /// Copies a str reference into a Text instance.
-    /// Guarantees that the String has length less than or equal to 100.
+    /// Guarantees that the String has a length in bytes which fits in a u32.
     ///
     /// # Errors
-    /// - Fails when content has length greater than 100
+    /// - Fails when content has length greater than u32::MAX
     pub fn from(content: &str) -> Result<Self, DBError> {
-        if content.len() > 100 {
-            return Err(DBError::ParseError("Text longer than 100 characters."));
+        if content.len() > u32::MAX as usize {
+            return Err(DBError::ParseError("Text longer than 4294967295 bytes."));
         }
 
         Ok(Text {
@@ -145,10 +158,78 @@ impl Text {
         })
     }
 
+    /// Reads a Text from a string literal as it is written in a command, wrapped in double quotes.
+    /// Inside of the quotes, '""' and '\"' are a double quote, '\\' is a backslash, and '\n',
+    /// '\t', '\r', and '\0' are a newline, tab, carriage return, and null character.
+    ///
+    /// # Errors
+    ///
+    /// Fails when literal is not wrapped in double quotes, holds an unknown escape sequence or a
+    /// double quote which is not escaped, or is longer than Text::from allows.
+    pub fn from_literal(literal: &str) -> Result<Self, DBError> {
+        let inner = match literal
+            .strip_prefix('"')
+            .and_then(|literal| literal.strip_suffix('"'))
+        {
+            Some(inner) => inner,
+            None => {
+                return Err(DBError::ParseError(
+                    "String literal expected. Wrap literals in double quotes.",
+                ))
+            }
+        };
+
+        let mut content = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if chars.next() != Some('"') => {
+                    return Err(DBError::ParseError(
+                        "Double quotes in string literals must be written as \"\" or \\\".",
+                    ))
+                }
+                '\\' => content.push(match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    _ => {
+                        return Err(DBError::ParseError(
+                            "Unknown escape sequence in string literal.",
+                        ))
+                    }
+                }),
+                _ => content.push(c),
+            }
+        }
+        Text::from(&content)
+    }
+
     /// Returns a reference to the wrapped string in a Text instance
     pub fn content(&self) -> &str {
         &self.content
     }
+
+    /// Returns the content with backslashes, newlines, tabs, carriage returns, and null characters
+    /// written as the escapes from_literal reads, and any other control character as its
+    /// unicode escape, so that it prints on a single line.
+    pub fn escaped(&self) -> String {
+        let mut escaped = String::with_capacity(self.content.len());
+        for c in self.content.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\t' => escaped.push_str("\\t"),
+                '\r' => escaped.push_str("\\r"),
+                '\0' => escaped.push_str("\\0"),
+                c if c.is_control() => escaped.extend(c.escape_unicode()),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
 }
 
 /// A base datatype of the overall database. Wraps i32 values and
@@ -177,17 +258,6 @@ impl Integer {
         4
     }
 
-    /// Attempts to parse an i32 out of the str reference in value.
-    ///
-    /// # Errors
-    ///
-    /// Fails when value cannot be parsed into an i32
-    pub fn from(value: &str) -> Result<Self, Box<dyn Error>> {
-        Ok(Integer {
-            value: value.parse()?,
-        })
-    }
-
     /// Returns a reference to the wrapped i32 value.
     pub fn value(&self) -> &i32 {
         &self.value
@@ -199,10 +269,49 @@ impl Integer {
     }
 }
 
-/// A base datatype of the overall database. Wraps f64 values and
-/// provides some helpful shortcuts for parsing from string references
-/// and writing to file. Note that this type also rounds off precision.
-/// past 2 decimal places.
+/// A base datatype of the overall database. Wraps i64 values for
+/// numbers, such as ids, which outgrow an Integer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigInt {
+    value: i64,
+}
+
+impl BigInt {
+    /// Returns a wrapped i64 from its big-endian bytes.
+    pub fn from_bytes(bytes: &[u8; 8]) -> Self {
+        BigInt {
+            value: i64::from_be_bytes(*bytes),
+        }
+    }
+
+    /// Returns the byte representation of the wrapped i64.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.value.to_be_bytes()
+    }
+
+    /// Returns a constant value for use in file loading.
+    pub fn byte_len() -> usize {
+        8
+    }
+
+    /// Returns a reference to the wrapped i64 value.
+    pub fn value(&self) -> &i64 {
+        &self.value
+    }
+
+    /// Returns a BigInt that wraps the given i64 value.
+    pub fn wrap(value: i64) -> Self {
+        BigInt { value }
+    }
+
+    /// Returns a BigInt with the same value as the given Integer.
+    pub fn from_integer(int: &Integer) -> Self {
+        BigInt::wrap(int.value as i64)
+    }
+}
+
+/// The datatype that Float attributes were stored as before Double replaced it. Only read from
+/// files written by older versions, which rounded values to two decimal places.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Float {
     float: f64,
@@ -232,86 +341,656 @@ impl Float {
         Ok(Float { float })
     }
 
-    /// Converts the Float instance to its 5 byte representation.
-    /// This is 4 bytes for an i32 and 1 to store the decimal rounded to the hundredths.
-    pub fn to_bytes(&self) -> [u8; 5] {
-        let i = self.float as i32; // i32 portion
-        let f = ((self.float - i as f64) * 100.).round(); // round off excess precision
-        let f: u8 = if f < 0.0 { (-f + 100.0) as u8 } else { f as u8 }; // encode negativity
-        let i = i.to_be_bytes();
-        [i[0], i[1], i[2], i[3], f]
+    /// Returns a constant value for use in file loading.
+    pub fn byte_len() -> usize {
+        5
+    }
+
+    /// Returns a reference to the wrapped float value.
+    pub fn value(&self) -> &f64 {
+        &self.float
+    }
+}
+
+/// A base datatype of the overall database. Wraps finite f64 values, which are stored
+/// as their 8 IEEE 754 bytes so that no precision is lost.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Double {
+    value: f64,
+}
+
+impl Double {
+    /// Returns a Double from its big-endian IEEE 754 bytes.
+    pub fn from_bytes(bytes: &[u8; 8]) -> Self {
+        Double::wrap(f64::from_be_bytes(*bytes))
+    }
+
+    /// Returns the big-endian IEEE 754 bytes of the wrapped f64.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.value.to_be_bytes()
+    }
+
+    /// Returns a constant value for use in file loading.
+    pub fn byte_len() -> usize {
+        8
     }
 
-    /// Attempts to read a Float out of a string reference. Rounds
-    /// the internal value given to only contain two decimal places.
+    /// Attempts to parse a finite f64 out of the str reference in value.
     ///
     /// # Errors
     ///
-    /// Fails float &str given cannot be parsed as a float value.
-    pub fn from(float: &str) -> Result<Self, Box<dyn Error>> {
-        let mut float = float.parse::<f64>()?;
-        float = (float * 100.).round() / 100.; // remove extra precision
-        Ok(Float { float })
+    /// Fails when value cannot be parsed into an f64 or is infinite or NaN.
+    pub fn from(value: &str) -> Result<Self, Box<dyn Error>> {
+        let value = value.parse::<f64>()?;
+        if !value.is_finite() {
+            Err(DBError::ParseError("Double values must be finite."))?
+        }
+        Ok(Double::wrap(value))
+    }
+
+    /// Returns a reference to the wrapped f64 value.
+    pub fn value(&self) -> &f64 {
+        &self.value
+    }
+
+    /// Returns a Double that wraps the given f64 value. -0.0 is kept as 0.0 so that
+    /// equal values always have the same bytes.
+    pub fn wrap(value: f64) -> Self {
+        Double {
+            value: if value == 0.0 { 0.0 } else { value },
+        }
+    }
+}
+
+impl Display for Double {
+    /// Writes the shortest decimal that reads back as the same f64, always with a
+    /// decimal point or exponent so that it cannot be mistaken for an Integer.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.value)
+    }
+}
+
+/// The most digits a Decimal may have, which is as many as an i128 can always hold.
+pub const MAX_DECIMAL_PRECISION: u8 = 38;
+
+/// A base datatype of the overall database. Holds an exact decimal number as an i128
+/// count of units of 10^-scale, so 12.30 with a scale of 2 is 1230.
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    value: i128,
+    scale: u8,
+}
+
+impl Decimal {
+    /// Returns a Decimal with a scale of 0 which holds the given whole number exactly.
+    pub fn from_whole(value: i64) -> Self {
+        Decimal {
+            value: value as i128,
+            scale: 0,
+        }
+    }
+
+    /// Returns a Decimal with the given scale from the big-endian i128 it is stored as.
+    pub fn from_bytes(bytes: &[u8; 16], scale: u8) -> Self {
+        Decimal {
+            value: i128::from_be_bytes(*bytes),
+            scale,
+        }
+    }
+
+    /// Returns the byte representation of the wrapped i128. The scale is not included.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.value.to_be_bytes()
     }
 
     /// Returns a constant value for use in file loading.
     pub fn byte_len() -> usize {
-        5
+        16
     }
 
-    /// Returns a reference to the wrapped float value.
-    pub fn value(&self) -> &f64 {
-        &self.float
+    /// Attempts to read a Decimal out of a literal such as '-12.30', keeping every digit
+    /// given. The scale is the number of digits after the decimal point.
+    ///
+    /// # Errors
+    ///
+    /// Fails when decimal is not a number written with digits and an optional sign and
+    /// decimal point, or when it has more than MAX_DECIMAL_PRECISION digits.
+    pub fn from(decimal: &str) -> Result<Self, DBError> {
+        let (negative, digits) = match decimal.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, decimal.strip_prefix('+').unwrap_or(decimal)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.len() + fraction.len() == 0
+            || !whole
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return Err(DBError::ParseError("Invalid Decimal literal."));
+        }
+        let digits = format!("{whole}{fraction}");
+        if digits.trim_start_matches('0').len() > MAX_DECIMAL_PRECISION as usize
+            || fraction.len() > MAX_DECIMAL_PRECISION as usize
+        {
+            return Err(DBError::ParseError(
+                "Decimal literal has more than 38 digits.",
+            ));
+        }
+        let value: i128 = digits.trim_start_matches('0').parse().unwrap_or(0);
+        Ok(Decimal {
+            value: if negative { -value } else { value },
+            scale: fraction.len() as u8,
+        })
     }
 
-    /// Returns a formatted string that cuts off floating point precision artifacts by rounding.
-    pub fn to_string(&self) -> String {
-        let i = self.float as i32;
-        let f = ((self.float - i as f64) * 100.).round(); // round to two decimal places
-        if f < 0.0 {
-            // print negative correctly when integer portion is 0
-            let f = -f as u8;
-            if i == 0 {
-                format!("-{i}.{f}")
+    /// Returns the same number with scale digits after the decimal point, rounding half away
+    /// from zero when digits are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the number then needs more than precision digits.
+    pub fn rescale(&self, precision: u8, scale: u8) -> Result<Self, DBError> {
+        let too_large = DBError::ConstraintError("Value is too large for its DECIMAL precision.");
+        let value = if scale >= self.scale {
+            let factor = 10i128.pow((scale - self.scale) as u32);
+            self.value.checked_mul(factor).ok_or(too_large)?
+        } else {
+            let factor = 10i128.pow((self.scale - scale) as u32);
+            let (quotient, remainder) = (self.value / factor, self.value % factor);
+            if remainder.abs() * 2 >= factor {
+                quotient + self.value.signum()
             } else {
-                format!("{i}.{f}")
+                quotient
             }
+        };
+        if precision < MAX_DECIMAL_PRECISION && value.abs() >= 10i128.pow(precision as u32) {
+            return Err(DBError::ConstraintError(
+                "Value is too large for its DECIMAL precision.",
+            ));
+        }
+        Ok(Decimal { value, scale })
+    }
+
+    /// Returns the exact sum of the two Decimals, with the larger of their scales, or None
+    /// when it has more than MAX_DECIMAL_PRECISION digits.
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let scale = self.scale.max(other.scale);
+        let aligned = |decimal: &Decimal| {
+            let factor = 10i128.pow((scale - decimal.scale) as u32);
+            decimal.value.checked_mul(factor)
+        };
+        let value = aligned(self)?.checked_add(aligned(other)?)?;
+        if value.unsigned_abs() >= 10u128.pow(MAX_DECIMAL_PRECISION as u32) {
+            return None;
+        }
+        Some(Decimal { value, scale })
+    }
+
+    /// Returns the nearest f64 to this Decimal.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+
+    /// Returns an Ordering based on the numbers the two Decimals stand for, whatever their scales.
+    pub fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        let aligned = |decimal: &Decimal| {
+            let factor = 10i128.pow((scale - decimal.scale) as u32);
+            decimal.value.checked_mul(factor)
+        };
+        match (aligned(self), aligned(other)) {
+            (Some(value), Some(other_value)) => value.cmp(&other_value),
+            // only the number further from zero can overflow
+            (None, _) if self.value < 0 => Ordering::Less,
+            (None, _) => Ordering::Greater,
+            (_, None) if other.value < 0 => Ordering::Greater,
+            (_, None) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialEq for Decimal {
+    /// Decimals are equal when they stand for the same number, so 1.5 equals 1.50.
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Display for Decimal {
+    /// Writes the Decimal with exactly scale digits after the decimal point.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.value < 0 { "-" } else { "" };
+        let digits = format!(
+            "{:0>width$}",
+            self.value.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (whole, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        if fraction.is_empty() {
+            write!(f, "{sign}{whole}")
         } else {
-            let f = f as u8;
-            format!("{i}.{f}")
+            write!(f, "{sign}{whole}.{fraction}")
         }
     }
+}
 
-    /// Returns an Integer reference that wraps the given i32 value.
-    pub fn wrap(float: f64) -> Self {
-        let float = (float * 100.).round() / 100.; // remove extra precision
-        Float { float }
+/// A base datatype of the overall database. Wraps bool values and
+/// provides some helpful shortcuts for parsing from string references
+#[derive(Clone, Debug, PartialEq)]
+pub struct Boolean {
+    value: bool,
+}
+
+impl Boolean {
+    /// Reads a Boolean back from its one byte representation.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the byte is neither 0 nor 1.
+    pub fn from_bytes(bytes: &[u8; 1]) -> Result<Self, DBError> {
+        match bytes[0] {
+            0 => Ok(Boolean { value: false }),
+            1 => Ok(Boolean { value: true }),
+            _ => Err(DBError::FileFormatError("Boolean in incorrect format.")),
+        }
+    }
+
+    /// Returns the byte representation of the wrapped bool, 1 for true and 0 for false.
+    pub fn to_bytes(&self) -> [u8; 1] {
+        [self.value as u8]
+    }
+
+    /// Returns a constant value for use in file loading.
+    pub fn byte_len() -> usize {
+        1
+    }
+
+    /// Attempts to read a Boolean out of the literals 'true' and 'false'.
+    ///
+    /// # Errors
+    ///
+    /// Fails when value is neither 'true' nor 'false'.
+    pub fn from(value: &str) -> Result<Self, DBError> {
+        match value {
+            "true" => Ok(Boolean { value: true }),
+            "false" => Ok(Boolean { value: false }),
+            _ => Err(DBError::ParseError(
+                "Boolean literal must be TRUE or FALSE.",
+            )),
+        }
+    }
+
+    /// Returns a reference to the wrapped bool value.
+    pub fn value(&self) -> &bool {
+        &self.value
+    }
+
+    /// Returns a Boolean that wraps the given bool value.
+    pub fn wrap(value: bool) -> Self {
+        Boolean { value }
     }
 }
 
+impl Display for Boolean {
+    /// Writes TRUE or FALSE, the same way the literals are written in commands.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.value {
+            write!(f, "TRUE")
+        } else {
+            write!(f, "FALSE")
+        }
+    }
+}
+
+/// The number of microseconds in a day, for converting between Dates and Timestamps.
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// A base datatype of the overall database. Wraps the number of days since 1970-01-01
+/// and provides shortcuts for reading and writing ISO-8601 dates such as 2024-02-29.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Date {
+    days: i32,
+}
+
+impl Date {
+    /// Returns a Date from the big-endian i32 count of days it is stored as.
+    pub fn from_bytes(bytes: &[u8; 4]) -> Self {
+        Date {
+            days: i32::from_be_bytes(*bytes),
+        }
+    }
+
+    /// Returns the byte representation of the wrapped count of days.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        self.days.to_be_bytes()
+    }
+
+    /// Returns a constant value for use in file loading.
+    pub fn byte_len() -> usize {
+        4
+    }
+
+    /// Attempts to read a Date out of an ISO-8601 date in the form YYYY-MM-DD.
+    ///
+    /// # Errors
+    ///
+    /// Fails when date is not in that form or names a day which does not exist.
+    pub fn from(date: &str) -> Result<Self, DBError> {
+        let invalid =
+            || DBError::ParseError("Date literal must be a real day in the form YYYY-MM-DD.");
+        let mut parts = date.split('-');
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day), None)
+                if year.len() == 4 && month.len() == 2 && day.len() == 2 =>
+            {
+                (year, month, day)
+            }
+            _ => return Err(invalid()),
+        };
+        let parse = |part: &str| -> Result<i64, DBError> {
+            if part.bytes().all(|b| b.is_ascii_digit()) {
+                Ok(part.parse().unwrap())
+            } else {
+                Err(invalid())
+            }
+        };
+        let (year, month, day) = (parse(year)?, parse(month)?, parse(day)?);
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(invalid());
+        }
+        Ok(Date {
+            days: days_from_civil(year, month, day) as i32,
+        })
+    }
+
+    /// Returns a reference to the wrapped count of days since 1970-01-01.
+    pub fn value(&self) -> &i32 {
+        &self.days
+    }
+
+    /// Returns a Date that is the given number of days after 1970-01-01.
+    pub fn wrap(days: i32) -> Self {
+        Date { days }
+    }
+}
+
+impl Display for Date {
+    /// Writes the Date in the ISO-8601 form YYYY-MM-DD.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = civil_from_days(self.days as i64);
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// A base datatype of the overall database. Wraps the number of microseconds since
+/// 1970-01-01T00:00:00 and provides shortcuts for reading and writing ISO-8601 timestamps
+/// such as 2024-02-29T13:45:00.25. No time zone is stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timestamp {
+    micros: i64,
+}
+
+impl Timestamp {
+    /// Returns a Timestamp from the big-endian i64 count of microseconds it is stored as.
+    pub fn from_bytes(bytes: &[u8; 8]) -> Self {
+        Timestamp {
+            micros: i64::from_be_bytes(*bytes),
+        }
+    }
+
+    /// Returns the byte representation of the wrapped count of microseconds.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.micros.to_be_bytes()
+    }
+
+    /// Returns a constant value for use in file loading.
+    pub fn byte_len() -> usize {
+        8
+    }
+
+    /// Attempts to read a Timestamp out of an ISO-8601 timestamp in the form
+    /// YYYY-MM-DDTHH:MM:SS with up to six digits of fractional seconds. A space may stand
+    /// in for the T, and a date on its own is read as midnight of that day.
+    ///
+    /// # Errors
+    ///
+    /// Fails when timestamp is not in that form or names a time which does not exist.
+    pub fn from(timestamp: &str) -> Result<Self, DBError> {
+        let invalid = || {
+            DBError::ParseError(
+                "Timestamp literal must be a real time in the form YYYY-MM-DDTHH:MM:SS.",
+            )
+        };
+        let (date, time) = match timestamp.split_once(['t', 'T', ' ']) {
+            Some(tuple) => tuple,
+            None => (timestamp, "00:00:00"),
+        };
+        let date = Date::from(date).map_err(|_| invalid())?;
+
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let mut parts = time.split(':');
+        let mut micros = 0;
+        for (limit, unit) in [(24, 3_600_000_000), (60, 60_000_000), (60, 1_000_000)] {
+            let part = match parts.next() {
+                Some(part) if part.len() == 2 && part.bytes().all(|b| b.is_ascii_digit()) => {
+                    part.parse::<i64>().unwrap()
+                }
+                _ => return Err(invalid()),
+            };
+            if part >= limit {
+                return Err(invalid());
+            }
+            micros += part * unit;
+        }
+        if parts.next().is_some()
+            || fraction.len() > 6
+            || !fraction.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if !fraction.is_empty() {
+            micros += format!("{fraction:0<6}").parse::<i64>().unwrap();
+        }
+
+        Ok(Timestamp {
+            micros: Timestamp::from_date(&date).micros + micros,
+        })
+    }
+
+    /// Returns the Timestamp at midnight at the start of date.
+    pub fn from_date(date: &Date) -> Self {
+        Timestamp {
+            micros: date.days as i64 * MICROS_PER_DAY,
+        }
+    }
+
+    /// Returns a reference to the wrapped count of microseconds since 1970-01-01T00:00:00.
+    pub fn value(&self) -> &i64 {
+        &self.micros
+    }
+}
+
+impl Display for Timestamp {
+    /// Writes the Timestamp in the ISO-8601 form YYYY-MM-DDTHH:MM:SS, followed by
+    /// fractional seconds only when there are any.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days = self.micros.div_euclid(MICROS_PER_DAY);
+        let micros = self.micros.rem_euclid(MICROS_PER_DAY);
+        let (hour, minute) = (micros / 3_600_000_000, micros / 60_000_000 % 60);
+        let (second, fraction) = (micros / 1_000_000 % 60, micros % 1_000_000);
+        write!(
+            f,
+            "{}T{hour:02}:{minute:02}:{second:02}",
+            Date::wrap(days as i32)
+        )?;
+        if fraction != 0 {
+            let fraction = format!("{fraction:06}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of days in the given month of the given year.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days from 1970-01-01 to the given day of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // count years from March so that the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the (year, month, day) that is the given number of days after 1970-01-01.
+/// The inverse of days_from_civil.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = (month_from_march + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 /// Contains a variant for each datatypes. Provides
 /// methods for parsing strings and some other helpful actions
 #[derive(Copy, Clone, PartialEq)]
 pub enum Domain {
     Integer,
+    BigInt,
+    /// Text of any length, which is stored out of line in the table's heap
     Text,
-    Float,
+    /// Text of up to the given number of bytes, which is stored in the record
+    VarChar(u16),
+    Boolean,
+    Date,
+    Timestamp,
+    Double,
+    /// The most digits a value may have (its precision) and how many of them follow
+    /// the decimal point (its scale)
+    Decimal(u8, u8),
 }
 
 impl Domain {
     /// Attempt to read a domain out of a descriptor string reference.
-    /// Only recognizes lowercase values.
+    /// Only recognizes lowercase values. Float is another name for Double,
+    /// 'varchar(limit)' must give its limit, and 'decimal(precision, scale)' may leave off
+    /// the scale, which is then 0, or both, which gives decimal(18, 0).
     ///
     /// # Errors
     ///
-    /// Fails when descriptor does not reference one of the three
-    /// Domain variants.
+    /// Fails when descriptor does not reference one of the Domain variants or gives a
+    /// VARCHAR limit or DECIMAL precision or scale that is out of range.
     pub fn from(descriptor: &str) -> Result<Self, DBError> {
         match descriptor {
             "text" => Ok(Domain::Text),
             "integer" => Ok(Domain::Integer),
-            "float" => Ok(Domain::Float),
-            _ => Err(DBError::ParseError("Invalid Domain type.")),
+            "bigint" => Ok(Domain::BigInt),
+            "double" | "float" => Ok(Domain::Double),
+            "boolean" => Ok(Domain::Boolean),
+            "date" => Ok(Domain::Date),
+            "timestamp" => Ok(Domain::Timestamp),
+            "decimal" => Ok(Domain::Decimal(18, 0)),
+            _ => {
+                let params = |name: &str| {
+                    descriptor
+                        .strip_prefix(name)
+                        .map(|params| params.trim_start())
+                        .and_then(|params| params.strip_prefix('('))
+                        .and_then(|params| params.strip_suffix(')'))
+                };
+                if let Some(limit) = params("varchar") {
+                    return match limit.trim().parse::<u16>() {
+                        Ok(limit) if limit > 0 => Ok(Domain::VarChar(limit)),
+                        _ => Err(DBError::ParseError("VARCHAR limit must be 1 to 65535.")),
+                    };
+                }
+                let params =
+                    params("decimal").ok_or(DBError::ParseError("Invalid Domain type."))?;
+                let (precision, scale) = params.split_once(',').unwrap_or((params, "0"));
+                match (precision.trim().parse::<u8>(), scale.trim().parse::<u8>()) {
+                    (Ok(precision), Ok(scale))
+                        if (1..=MAX_DECIMAL_PRECISION).contains(&precision)
+                            && scale <= precision =>
+                    {
+                        Ok(Domain::Decimal(precision, scale))
+                    }
+                    _ => Err(DBError::ParseError(
+                        "DECIMAL precision must be 1 to 38 and its scale no more than its precision.",
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Reads a Domain back from the bytes written by write_to_file.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the bytes do not stand for a Domain.
+    pub fn from_bytes(bytes: &[u8; 3]) -> Result<Self, DBError> {
+        match bytes[0] {
+            0 => Ok(Domain::Integer),
+            1 => Ok(Domain::Text),
+            3 => Ok(Domain::Boolean),
+            4 => Ok(Domain::Date),
+            5 => Ok(Domain::Timestamp),
+            6 => Ok(Domain::Double),
+            7 => Ok(Domain::Decimal(bytes[1], bytes[2])),
+            8 => Ok(Domain::BigInt),
+            9 => Ok(Domain::VarChar(u16::from_be_bytes([bytes[1], bytes[2]]))),
+            _ => Err(DBError::FileFormatError("Incorrect Domain type read.")),
+        }
+    }
+
+    /// Returns true if values of the two Domains can be compared with each other. Any two
+    /// numeric Domains can be compared, whatever their precisions and scales, and a Text
+    /// or VarChar with any other Text or VarChar.
+    pub fn is_comparable_with(&self, other: &Domain) -> bool {
+        match (self, other) {
+            (Domain::Text | Domain::VarChar(_), Domain::Text | Domain::VarChar(_)) => true,
+            (domain, other) if domain.is_numeric() && other.is_numeric() => true,
+            _ => self == other,
+        }
+    }
+
+    /// Returns true for the Domains which hold numbers: Integer, BigInt, Double, and Decimal.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Domain::Integer | Domain::BigInt | Domain::Double | Domain::Decimal(..)
+        )
+    }
+
+    /// Returns the byte which stands for the Domain variant in files. 2 was used by Float.
+    pub fn byte(&self) -> u8 {
+        match self {
+            Domain::Integer => 0,
+            Domain::Text => 1,
+            Domain::Boolean => 3,
+            Domain::Date => 4,
+            Domain::Timestamp => 5,
+            Domain::Double => 6,
+            Domain::Decimal(..) => 7,
+            Domain::BigInt => 8,
+            Domain::VarChar(_) => 9,
         }
     }
 
@@ -319,39 +998,73 @@ impl Domain {
     /// the byte size of the datatype for the Domain variant given
     pub fn size_in_bytes(&self) -> u32 {
         match self {
-            Domain::Float => Float::byte_len() as u32, // i32 + u8 fraction
             Domain::Integer => Integer::byte_len() as u32, // i32
-            Domain::Text => 100, // Text has no byte_len() function but always stores itself as 100 bytes
+            Domain::BigInt => BigInt::byte_len() as u32,   // i64
+            Domain::Text => 12, // the u64 offset and u32 length of the value in the heap
+            Domain::VarChar(limit) => *limit as u32 + 2, // u16 length and up to limit bytes
+            Domain::Boolean => Boolean::byte_len() as u32, // 0 or 1
+            Domain::Date => Date::byte_len() as u32, // i32 days
+            Domain::Timestamp => Timestamp::byte_len() as u32, // i64 microseconds
+            Domain::Double => Double::byte_len() as u32, // f64
+            Domain::Decimal(..) => Decimal::byte_len() as u32, // i128 units of the scale
         }
     }
 
-    /// Takes a file and writes the integer which represents the Domain variant in self
-    pub fn write_to_file(&self, mut file: &File) -> Result<(), std::io::Error> {
-        file.write_all(&[*self as u8])
+    /// Takes a file and writes the byte which represents the Domain variant in self
+    /// followed by two bytes of parameters. These are the precision and scale of a Decimal,
+    /// the u16 limit of a VarChar, and 0 for other variants.
+    pub fn write_to_file(&self, mut file: impl Write) -> Result<(), std::io::Error> {
+        let params = match self {
+            Domain::Decimal(precision, scale) => [*precision, *scale],
+            Domain::VarChar(limit) => limit.to_be_bytes(),
+            _ => [0, 0],
+        };
+        file.write_all(&[self.byte(), params[0], params[1]])
     }
+}
 
-    /// Returns a string representation for a given Domain variant.
+impl Display for Domain {
+    /// Writes a string representation for a given Domain variant.
     /// Note that this string cannot be directly read back by Domain::from()
     /// since it includes captialized letters. Must use String.to_lowercase() first.
-    pub fn to_string(&self) -> &'static str {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Domain::Float => "Float",
-            Domain::Integer => "Integer",
-            Domain::Text => "Text",
+            Domain::Integer => write!(f, "Integer"),
+            Domain::BigInt => write!(f, "BigInt"),
+            Domain::Text => write!(f, "Text"),
+            Domain::VarChar(limit) => write!(f, "VarChar({limit})"),
+            Domain::Boolean => write!(f, "Boolean"),
+            Domain::Date => write!(f, "Date"),
+            Domain::Timestamp => write!(f, "Timestamp"),
+            Domain::Double => write!(f, "Double"),
+            Domain::Decimal(precision, scale) => write!(f, "Decimal({precision}, {scale})"),
         }
     }
 }
 
-/// A wrapper for Integer, Text, and Float.
+/// A wrapper for Integer, BigInt, Text, Boolean, Date, Timestamp, Double, and Decimal.
 /// Contains same variants as Domain, except that all
-/// variants contain a payload of the datatype represented
+/// variants contain a payload of the datatype represented.
+/// Null stands for a missing value of any Domain.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Data {
     Integer(Integer),
+    BigInt(BigInt),
     Text(Text),
-    Float(Float),
+    Boolean(Boolean),
+    Date(Date),
+    Timestamp(Timestamp),
+    Double(Double),
+    Decimal(Decimal),
+    Null,
 }
 
+/// The first byte Data::as_bytes gives for the variants which cannot be told apart by their
+/// length alone. No Text can start with it since 0xFF never starts a UTF-8 character, but
+/// Integers and Floats can, so it only marks values longer than Float::byte_len().
+/// It is followed by the byte which the Domain of the value is written as.
+const TAGGED: u8 = 0xFF;
+
 impl Data {
     /// Attempts to read a Data variant from a slice of bytes.
     ///
@@ -359,7 +1072,48 @@ impl Data {
     ///
     /// Fails when bytes cannot be parsed into any Data variant.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() > Float::byte_len() && bytes[0] == TAGGED {
+            let value = &bytes[2..];
+            let wrong_len =
+                || DBError::FileFormatError("Value has the wrong length for its Domain.");
+            return match Domain::from_bytes(&[bytes[1], 0, 0])? {
+                Domain::Date => Ok(Data::Date(Date::from_bytes(
+                    value.try_into().map_err(|_| wrong_len())?,
+                ))),
+                Domain::Timestamp => Ok(Data::Timestamp(Timestamp::from_bytes(
+                    value.try_into().map_err(|_| wrong_len())?,
+                ))),
+                Domain::Double => Ok(Data::Double(Double::from_bytes(
+                    value.try_into().map_err(|_| wrong_len())?,
+                ))),
+                Domain::BigInt => Ok(Data::BigInt(BigInt::from_bytes(
+                    value.try_into().map_err(|_| wrong_len())?,
+                ))),
+                Domain::Text => match value.get(4..) {
+                    Some(content) => Ok(Data::Text(Text::from(std::str::from_utf8(content)?)?)),
+                    None => Err(Box::new(wrong_len())),
+                },
+                Domain::Decimal(..) => match value {
+                    [scale, value @ ..] => Ok(Data::Decimal(Decimal::from_bytes(
+                        value.try_into().map_err(|_| wrong_len())?,
+                        *scale,
+                    ))),
+                    [] => Err(Box::new(wrong_len())),
+                },
+                _ => Err(Box::new(DBError::FileFormatError(
+                    "Incorrect Domain type read.",
+                ))),
+            };
+        }
+
         match bytes.len() {
+            0 => Ok(Data::Null),
+            1 => {
+                // Boolean::byte_len()
+                Ok(Data::Boolean(Boolean::from_bytes(
+                    bytes.try_into().unwrap(),
+                )?))
+            }
             4 => {
                 // Integer::byte_len()
                 Ok(Data::Integer(Integer::from_bytes(
@@ -367,8 +1121,9 @@ impl Data {
                 )))
             }
             5 => {
-                // Float::byte_len()
-                Ok(Data::Float(Float::from_bytes(bytes.try_into().unwrap())?))
+                // Float::byte_len(), for values written before Double replaced Float
+                let float = Float::from_bytes(bytes.try_into().unwrap())?;
+                Ok(Data::Double(Double::wrap(*float.value())))
             }
             _ => Ok(Data::Text(Text::from_bytes(bytes)?)),
         }
@@ -376,57 +1131,409 @@ impl Data {
 
     /// Gets a byte representation for the given Data variant that can be read back by
     /// Data::from_bytes(). This differs from simply calling .to_bytes() on the wrapped
-    /// datatype since Text values are returned with dynamic size. The Text.to_bytes() method
-    /// provides static size storage while this provides dynamic.
+    /// datatype since Text values are returned with dynamic size. Text.to_varchar_bytes()
+    /// provides static size storage while this provides dynamic. BigInts, Dates, Timestamps, Doubles,
+    /// and Decimals are written after TAGGED and their Domain byte, and Decimals then their scale.
+    /// Text which starts or ends with whitespace is written the same way, followed by its length.
     pub fn as_bytes(&self) -> Vec<u8> {
         match self {
+            Data::Text(text) if text.content.trim() != text.content => {
+                // the spaces Text is padded with would be trimmed off with its own whitespace
+                let len = (text.content.len() as u32).to_be_bytes();
+                [
+                    &[TAGGED, Domain::Text.byte()],
+                    &len[..],
+                    text.content.as_bytes(),
+                ]
+                .concat()
+            }
             Data::Text(text) => {
                 let mut text_vec = text.content.as_bytes().to_vec();
-                while text_vec.len() < 5 {
+                while text_vec.len() <= Float::byte_len() {
                     text_vec.push(b' '); // to differentiate this from int or float values... these will be trimmed later
                 }
                 text_vec
             }
-            Data::Float(float) => float.to_bytes().to_vec(),
             Data::Integer(int) => int.to_bytes().to_vec(),
+            Data::BigInt(int) => [&[TAGGED, Domain::BigInt.byte()], &int.to_bytes()[..]].concat(),
+            Data::Boolean(boolean) => boolean.to_bytes().to_vec(),
+            Data::Date(date) => [&[TAGGED, Domain::Date.byte()], &date.to_bytes()[..]].concat(),
+            Data::Timestamp(timestamp) => [
+                &[TAGGED, Domain::Timestamp.byte()],
+                &timestamp.to_bytes()[..],
+            ]
+            .concat(),
+            Data::Double(double) => {
+                [&[TAGGED, Domain::Double.byte()], &double.to_bytes()[..]].concat()
+            }
+            Data::Decimal(decimal) => [
+                &[TAGGED, Domain::Decimal(0, 0).byte(), decimal.scale],
+                &decimal.to_bytes()[..],
+            ]
+            .concat(),
+            Data::Null => Vec::new(),
         }
     }
 
     /// Returns an Ordering based on the comparison of the payload of
-    /// two given Data variants.
+    /// two given Data variants. Null is Ordering::Equal to Null and Ordering::Less
+    /// than every other value so that it can be kept in indexes. Integers, BigInts, and
+    /// Decimals compare exactly by the numbers they stand for, whichever variants they are,
+    /// and are rounded to the nearest Double when compared with one.
     ///
     /// # Panics
     ///
     /// Panics when given incompatible/unequal variants.
     pub fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
-            (Data::Float(f1), Data::Float(f2)) => f1
-                .float
-                .partial_cmp(&f2.float)
+            (Data::Double(d1), Data::Double(d2)) => d1
+                .value
+                .partial_cmp(&d2.value)
                 .expect("No Nan or other odd float values allowed"),
+            (Data::Decimal(d1), Data::Decimal(d2)) => d1.cmp(d2),
             (Data::Integer(i1), Data::Integer(i2)) => i1.value.cmp(&i2.value),
+            (Data::BigInt(i1), Data::BigInt(i2)) => i1.value.cmp(&i2.value),
             (Data::Text(t1), Data::Text(t2)) => t1.content.cmp(&t2.content),
-            _ => {
-                panic!("Can't compare Data variants that are incompatible");
+            (Data::Boolean(b1), Data::Boolean(b2)) => b1.value.cmp(&b2.value),
+            (Data::Date(d1), Data::Date(d2)) => d1.days.cmp(&d2.days),
+            (Data::Timestamp(t1), Data::Timestamp(t2)) => t1.micros.cmp(&t2.micros),
+            (Data::Null, Data::Null) => Ordering::Equal,
+            (Data::Null, _) => Ordering::Less,
+            (_, Data::Null) => Ordering::Greater,
+            // other numbers are exact unless compared with a Double, which they are rounded to
+            (Data::Double(double), number) => double
+                .value
+                .partial_cmp(&number.exact_number().to_f64())
+                .expect("No Nan or other odd float values allowed"),
+            (number, Data::Double(double)) => number
+                .exact_number()
+                .to_f64()
+                .partial_cmp(&double.value)
+                .expect("No Nan or other odd float values allowed"),
+            (number1, number2) => number1.exact_number().cmp(&number2.exact_number()),
+        }
+    }
+
+    /// Returns the number an Integer, BigInt, or Decimal stands for as a Decimal.
+    ///
+    /// # Panics
+    ///
+    /// Panics when given any other variant, as those cannot be compared with numbers.
+    fn exact_number(&self) -> Decimal {
+        match self {
+            Data::Integer(int) => Decimal::from_whole(int.value as i64),
+            Data::BigInt(int) => Decimal::from_whole(int.value),
+            Data::Decimal(decimal) => decimal.clone(),
+            _ => panic!("Can't compare Data variants that are incompatible"),
+        }
+    }
+
+    /// Returns the value as an attribute of the given Domain stores it, so that a number
+    /// may be set to an attribute of a numeric Domain which holds every value of its own.
+    /// Values which are not numbers are returned as they are.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the Domain cannot hold the number, such as a Double for a Decimal or a
+    /// BigInt for an Integer, or when it has too many digits for a Decimal's precision.
+    pub fn promote_to(self, domain: Domain) -> Result<Data, DBError> {
+        match (self, domain) {
+            (Data::Integer(int), Domain::BigInt) => Ok(Data::BigInt(BigInt::from_integer(&int))),
+            (number @ (Data::Integer(_) | Data::BigInt(_) | Data::Decimal(_)), Domain::Double) => {
+                Ok(Data::Double(Double::wrap(number.exact_number().to_f64())))
+            }
+            (
+                number @ (Data::Integer(_) | Data::BigInt(_) | Data::Decimal(_)),
+                Domain::Decimal(precision, scale),
+            ) => Ok(Data::Decimal(
+                number.exact_number().rescale(precision, scale)?,
+            )),
+            (data @ Data::Integer(_), Domain::Integer)
+            | (data @ Data::BigInt(_), Domain::BigInt)
+            | (data @ Data::Double(_), Domain::Double) => Ok(data),
+            (Data::Integer(_) | Data::BigInt(_) | Data::Double(_) | Data::Decimal(_), domain)
+                if domain.is_numeric() =>
+            {
+                Err(DBError::ConstraintError(
+                    "Number cannot be held by the Domain of its attribute.",
+                ))
             }
+            (data, _) => Ok(data),
         }
     }
 
-    /// Returns the length of the string representation of the given Data variant.
-    pub fn string_len(&self) -> usize {
+    /// Returns the Data as it is shown in a cell of a printed table, which is its string
+    /// representation with the characters of a Text escaped to keep it on one line.
+    pub fn to_cell_string(&self) -> String {
         match self {
-            Data::Float(float) => float.to_string().len(),
-            Data::Integer(int) => int.value.to_string().len(),
-            Data::Text(text) => text.content.len(),
+            Data::Text(text) => text.escaped(),
+            data => data.to_string(),
         }
     }
+}
+
+/// The value a record has for an index, made up of the values of one or more of its attributes.
+/// Keys compare one value at a time, and a key sorts before every longer key that it is a
+/// prefix of, so all keys which share a prefix sit next to each other in an index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Key {
+    values: Vec<Data>,
+}
+
+impl Key {
+    /// Creates a Key out of the given values, in order.
+    pub fn new(values: Vec<Data>) -> Self {
+        Key { values }
+    }
 
-    /// Returns the string representation of the given Data variant.
-    pub fn to_string(&self) -> String {
+    /// Creates the Key that record has for the attributes numbered attri_nums.
+    ///
+    /// # Panics
+    ///
+    /// Panics when an attribute number is out of the record's bounds.
+    pub fn from_record(record: &[Data], attri_nums: &[usize]) -> Self {
+        Key {
+            values: attri_nums
+                .iter()
+                .map(|attri_num| record[*attri_num].clone())
+                .collect(),
+        }
+    }
+
+    /// Returns a reference to the values that make up this Key.
+    pub fn values(&self) -> &[Data] {
+        &self.values
+    }
+
+    /// Returns an Ordering based on comparing the values of both keys in order.
+    /// When one key is a prefix of the other, the shorter key is Ordering::Less.
+    ///
+    /// # Panics
+    ///
+    /// Panics when two values in the same position are incompatible Data variants.
+    pub fn cmp(&self, other: &Self) -> Ordering {
+        for (value, other_value) in self.values.iter().zip(other.values.iter()) {
+            match value.cmp(other_value) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            }
+        }
+        self.values.len().cmp(&other.values.len())
+    }
+
+    /// Returns an Ordering based on comparing only as many values of this Key as prefix holds,
+    /// so a Key is Ordering::Equal to each of its prefixes.
+    ///
+    /// # Panics
+    ///
+    /// Panics when two values in the same position are incompatible Data variants.
+    pub fn cmp_prefix(&self, prefix: &Self) -> Ordering {
+        for (value, prefix_value) in self.values.iter().zip(prefix.values.iter()) {
+            match value.cmp(prefix_value) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl From<Data> for Key {
+    /// Creates a Key made of a single value.
+    fn from(data: Data) -> Self {
+        Key { values: vec![data] }
+    }
+}
+
+impl Display for Data {
+    /// Writes the string representation of the given Data variant.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Data::Float(float) => float.to_string(),
-            Data::Integer(int) => int.value.to_string(),
-            Data::Text(text) => text.content.to_string(),
+            Data::Double(double) => write!(f, "{double}"),
+            Data::Decimal(decimal) => write!(f, "{decimal}"),
+            Data::Integer(int) => write!(f, "{}", int.value),
+            Data::BigInt(int) => write!(f, "{}", int.value),
+            Data::Text(text) => write!(f, "{}", text.content),
+            Data::Boolean(boolean) => write!(f, "{boolean}"),
+            Data::Date(date) => write!(f, "{date}"),
+            Data::Timestamp(timestamp) => write!(f, "{timestamp}"),
+            Data::Null => write!(f, "NULL"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(literal: &str) -> Decimal {
+        Decimal::from(literal).unwrap()
+    }
+
+    #[test]
+    fn civil_days_count_from_1970() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(1971, 1, 1), 365);
+        assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1600, 1, 1), -135_140);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+
+        // every day follows the one before it, across leap days and the 1970 boundary
+        let mut prev = civil_from_days(-200_000);
+        for days in -199_999..200_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+            let (prev_year, prev_month, prev_day) = prev;
+            if day == 1 {
+                assert_eq!(prev_day, days_in_month(prev_year, prev_month));
+                assert!(month == prev_month % 12 + 1);
+                assert_eq!(year, if month == 1 { prev_year + 1 } else { prev_year });
+            } else {
+                assert_eq!((year, month, day), (prev_year, prev_month, prev_day + 1));
+            }
+            prev = (year, month, day);
+        }
+    }
+
+    #[test]
+    fn dates_must_be_real_days() {
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+
+        assert_eq!(Date::from("1970-01-01").unwrap(), Date::wrap(0));
+        assert_eq!(Date::from("1969-12-31").unwrap(), Date::wrap(-1));
+        for date in [
+            "2024-02-29",
+            "2000-02-29",
+            "1969-12-31",
+            "0001-01-01",
+            "9999-12-31",
+        ] {
+            assert_eq!(Date::from(date).unwrap().to_string(), date);
+        }
+        for date in [
+            "2023-02-29",
+            "1900-02-29",
+            "2023-04-31",
+            "2023-13-01",
+            "2023-00-10",
+            "2023-01-00",
+            "2023-1-01",
+            "23-01-01",
+            "2023-01-01-01",
+            "2023/01/01",
+            "2023-0a-01",
+        ] {
+            assert!(Date::from(date).is_err(), "{date}");
+        }
+    }
+
+    #[test]
+    fn timestamps_parse_and_compare() {
+        let timestamp = |literal: &str| Timestamp::from(literal).unwrap();
+        assert_eq!(*timestamp("1970-01-01T00:00:00").value(), 0);
+        assert_eq!(*timestamp("1970-01-01").value(), 0);
+        assert_eq!(*timestamp("1969-12-31T23:59:59.5").value(), -500_000);
+        assert_eq!(
+            timestamp("2024-02-29 13:45:00.25"),
+            timestamp("2024-02-29T13:45:00.250000")
+        );
+        assert_eq!(
+            Timestamp::from_date(&Date::from("2024-02-29").unwrap()),
+            timestamp("2024-02-29")
+        );
+        for literal in [
+            "2024-02-29T13:45:00.25",
+            "1969-12-31T23:59:59.5",
+            "1970-01-01T00:00:00",
+            "1960-06-15T08:30:00.000001",
+        ] {
+            assert_eq!(timestamp(literal).to_string(), literal);
+        }
+        for literal in [
+            "2023-02-29T00:00:00",
+            "2024-01-01T24:00:00",
+            "2024-01-01T12:60:00",
+            "2024-01-01T12:00:60",
+            "2024-01-01T12:00",
+            "2024-01-01T12:00:00:00",
+            "2024-01-01T12:00:00.1234567",
+            "2024-01-01T1:00:00",
+            "2024-01-01T12:00:00.5x",
+        ] {
+            assert!(Timestamp::from(literal).is_err(), "{literal}");
+        }
+
+        let data = |literal: &str| Data::Timestamp(timestamp(literal));
+        assert_eq!(
+            data("1969-12-31T23:59:59").cmp(&data("1970-01-01T00:00:00")),
+            Ordering::Less
+        );
+        assert_eq!(
+            data("2024-02-29T13:45:00.000001").cmp(&data("2024-02-29T13:45:00")),
+            Ordering::Greater
+        );
+        let date = |literal: &str| Data::Date(Date::from(literal).unwrap());
+        assert_eq!(date("1969-12-31").cmp(&date("1970-01-01")), Ordering::Less);
+        assert_eq!(
+            date("2024-03-01").cmp(&date("2024-02-29")),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn decimals_rescale_with_rounding() {
+        assert_eq!(decimal("1.25").rescale(5, 1).unwrap().to_string(), "1.3");
+        assert_eq!(decimal("-1.25").rescale(5, 1).unwrap().to_string(), "-1.3");
+        assert_eq!(decimal("1.24").rescale(5, 1).unwrap().to_string(), "1.2");
+        assert_eq!(decimal("0.5").rescale(5, 0).unwrap().to_string(), "1");
+        assert_eq!(decimal("12.3").rescale(4, 2).unwrap().to_string(), "12.30");
+        assert_eq!(decimal("-0.05").rescale(4, 2).unwrap().to_string(), "-0.05");
+
+        // 12.30 needs four digits, and 99.995 rounds up to 100.00
+        assert!(decimal("12.3").rescale(3, 2).is_err());
+        assert!(decimal("99.995").rescale(4, 2).is_err());
+        assert_eq!(
+            decimal("99.994").rescale(4, 2).unwrap().to_string(),
+            "99.99"
+        );
+
+        // scaling up 38 digits overflows the i128 rather than wrapping
+        let widest = "9".repeat(38);
+        assert!(decimal(&widest).rescale(MAX_DECIMAL_PRECISION, 2).is_err());
+        assert!(Decimal::from(&"9".repeat(39)).is_err());
+        assert!(Decimal::from(&format!("0.{}", "1".repeat(39))).is_err());
+        for literal in ["", "-", ".", "1.2.3", "1e5", "--1", "12a"] {
+            assert!(Decimal::from(literal).is_err(), "{literal}");
+        }
+    }
+
+    #[test]
+    fn decimals_compare_across_scales() {
+        assert_eq!(decimal("1.5"), decimal("1.50"));
+        assert_eq!(decimal("1.5").cmp(&decimal("1.51")), Ordering::Less);
+        assert_eq!(decimal("-1.5").cmp(&decimal("-1.51")), Ordering::Greater);
+        assert_eq!(decimal("-0.1").cmp(&decimal("0.01")), Ordering::Less);
+        assert_eq!(decimal("100").cmp(&decimal("99.999")), Ordering::Greater);
+
+        // the wider number cannot be aligned to the other's scale without overflowing
+        let widest = decimal(&"9".repeat(38));
+        let small = decimal(&format!("0.{}1", "0".repeat(36)));
+        assert_eq!(widest.cmp(&small), Ordering::Greater);
+        assert_eq!(small.cmp(&widest), Ordering::Less);
+        let negative = decimal(&format!("-{}", "9".repeat(38)));
+        assert_eq!(negative.cmp(&small), Ordering::Less);
+        assert_eq!(small.cmp(&negative), Ordering::Greater);
+
+        let int = Data::Integer(Integer::wrap(2));
+        assert_eq!(int.cmp(&Data::Decimal(decimal("1.99"))), Ordering::Greater);
+        assert_eq!(int.cmp(&Data::Decimal(decimal("2.000"))), Ordering::Equal);
+        let big = Data::BigInt(BigInt::wrap(-3));
+        assert_eq!(big.cmp(&Data::Decimal(decimal("-2.5"))), Ordering::Less);
+    }
+}
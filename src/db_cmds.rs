@@ -1,6 +1,8 @@
-use crate::base::{Data, Domain, Float, Identifier, Integer, Text};
-use crate::relation::Table;
-use crate::{binary_search_tree::BST, logic::Condition, CmdIterator, DBError, Database};
+use crate::base::{Data, Domain, Identifier};
+use crate::parser::{parse_statement, Select, Statement};
+use crate::relation::{ColumnConstraints, Savepoint, Table};
+use crate::wal::Wal;
+use crate::{logic::Condition, CmdIterator, DBError, Database};
 use std::{
     error::Error,
     fs::{File, OpenOptions},
@@ -10,10 +12,13 @@ use std::{
 /// Contains procedures outside of the main commands which are needed for one or more commands
 mod helpers;
 
+/// Contains the checks and actions which keep REFERENCES constraints between tables intact
+mod references;
+
 // helper's functions will only and all be used in this module
 use helpers::*;
 
-/// Attempts to parse and run the command given by reading the first keyword and delegating.
+/// Attempts to parse the command given into a Statement and run it by delegating.
 /// Returns a result that either contains String outputs passed from the command run or an
 /// Err contianing info about why the given command failed.
 ///
@@ -22,60 +27,124 @@ use helpers::*;
 /// Fails anytime the command given is invalid or when the command requires filesystem access
 /// but does not have it.
 pub fn run_cmd(cmd: &str, db: &mut Database) -> Result<Vec<String>, Box<dyn Error>> {
-    let cmd = cmd.to_lowercase(); // lowercase everything so remove case-sensitivity here
+    if cmd.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let statement = parse_statement(cmd)?;
+
+    // changes to the schema or the current database cannot be undone by ROLLBACK
+    if db.in_transaction && statement.changes_schema() {
+        return Err(Box::new(DBError::ParseError(
+            "Cannot create, drop, or delete tables/indexes or switch databases inside a transaction.",
+        )));
+    }
 
-    let (directive, cmd) = match cmd.split_once(' ') {
-        Some((directive, cmd)) => (directive, cmd),
-        None => (&cmd[..], ""),
+    // inside a transaction, a statement which fails partway must not leave some of its changes
+    // to be committed
+    let savepoints = match statement {
+        Statement::Insert { .. } | Statement::Update { .. } | Statement::Delete { .. }
+            if db.in_transaction =>
+        {
+            Some(savepoint(db))
+        }
+        _ => None,
     };
+    let result = run_statement(statement, db);
+    if let (Err(_), Some(savepoints)) = (&result, savepoints) {
+        rollback_to(db, savepoints)?;
+    }
+    result
+}
 
-    match directive.trim() {
-        "create" => {
-            run_create(cmd, db)?;
+/// Attempts to run a Statement by delegating to the function for its command. Returns a result
+/// that either contains String outputs passed from the command run or an Err containing info about
+/// why the command failed.
+///
+/// # Errors
+///
+/// Fails anytime the Statement cannot be run or when it requires filesystem access but does not have it.
+fn run_statement(statement: Statement, db: &mut Database) -> Result<Vec<String>, Box<dyn Error>> {
+    match statement {
+        Statement::CreateDatabase(name) => {
+            create_database(name)?;
+            eprintln!("\tCREATE Success!");
             Ok(vec![])
         }
-        "use" => {
-            run_use(cmd, db)?;
+        Statement::CreateTable {
+            name,
+            columns,
+            primary_key,
+        } => {
+            create_table(db, name, columns, primary_key)?;
+            eprintln!("\tCREATE Success!");
             Ok(vec![])
         }
-        "describe" => run_describe(cmd, db), // only the commands which return output can be returned directly.
-        "select" => run_select(cmd, db),
-        "let" => {
-            run_let(cmd, db)?;
+        Statement::CreateIndex {
+            name,
+            table,
+            attribute,
+        } => {
+            create_index(db, name, table, attribute)?;
+            eprintln!("\tCREATE Success!");
             Ok(vec![])
         }
-        "insert" => {
-            run_insert(cmd, db)?;
+        Statement::DropIndex(name) => {
+            run_drop(name, db)?;
             Ok(vec![])
         }
-        "update" => {
-            run_update(cmd, db)?;
+        Statement::Use(name) => {
+            run_use(name, db)?;
             Ok(vec![])
         }
-        "delete" => {
-            run_delete(cmd, db)?;
+        // only the commands which return output can be returned directly.
+        Statement::Describe(table_name) => run_describe(table_name, db),
+        Statement::Select(select) => run_select(select, db),
+        Statement::Let { table, key, select } => {
+            run_let(table, key, select, db)?;
             Ok(vec![])
         }
-        "input" => {
-            run_input(cmd, db)?;
+        Statement::Insert { table, values } => {
+            run_insert(table, values, db)?;
             Ok(vec![])
         }
-        "rename" => {
-            run_rename(cmd, db)?;
+        Statement::Update {
+            table,
+            new_values,
+            condition,
+        } => {
+            run_update(table, new_values, condition, db)?;
             Ok(vec![])
         }
-        "exit" => {
-            if !cmd.trim().is_empty() {
-                eprintln!("\tEXIT command does not take arguments.");
-            }
-            run_exit(db)?;
+        Statement::Delete { table, condition } => {
+            run_delete(table, condition, db)?;
             Ok(vec![])
         }
-        "" => return Ok(vec![]),
-        _ => {
-            return Err(Box::new(DBError::ParseError(
-                "Failed to read command directive.",
-            )))
+        Statement::Input {
+            file_name,
+            output_name,
+        } => {
+            run_input(&file_name, output_name.as_deref(), db)?;
+            Ok(vec![])
+        }
+        Statement::Rename { table, attributes } => {
+            run_rename(table, attributes, db)?;
+            Ok(vec![])
+        }
+        Statement::Begin => {
+            run_begin(db)?;
+            Ok(vec![])
+        }
+        Statement::Commit => {
+            run_commit(db)?;
+            Ok(vec![])
+        }
+        Statement::Rollback => {
+            run_rollback(db)?;
+            Ok(vec![])
+        }
+        Statement::Exit => {
+            run_exit(db)?;
+            Ok(vec![])
         }
     }
 }
@@ -87,9 +156,9 @@ pub fn run_cmd(cmd: &str, db: &mut Database) -> Result<Vec<String>, Box<dyn Erro
 ///
 /// Fails when the SELECT command string given cannot be parsed or when file system
 /// access fails.
-fn run_select(cmd: &str, db: &mut Database) -> Result<Vec<String>, Box<dyn Error>> {
+fn run_select(select: Select, db: &mut Database) -> Result<Vec<String>, Box<dyn Error>> {
     // select processing is offloaded in a helper function since LET command also uses it
-    let return_vec = select_from_tables(cmd, db)?.to_string_vec();
+    let return_vec = select_from_tables(select, db)?.to_string_vec();
     eprintln!("\tSELECT Success!");
     Ok(return_vec)
 }
@@ -101,20 +170,13 @@ fn run_select(cmd: &str, db: &mut Database) -> Result<Vec<String>, Box<dyn Error
 ///
 /// Fails when cannot parse command or not enough attributes given in the list.
 /// Also fails when the file cannot be written to.
-fn run_rename(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    // split the table_name and the attribute_name
-    let cmd = cmd.trim_start();
-    let (table_name, attribute_names) = match cmd.split_once(' ') {
-        Some((table_name, attribute_names)) => (table_name.trim(), attribute_names.trim()),
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "RENAME command requires attribute names",
-            )))
-        }
-    };
-
+fn run_rename(
+    table_name: Identifier,
+    new_attributes: Vec<Identifier>,
+    db: &mut Database,
+) -> Result<(), Box<dyn Error>> {
     // get handle on the Table to rename
-    let table = match db.table_map.get_mut(table_name) {
+    let table = match db.table_map.get_mut(table_name.name()) {
         Some(table) => table,
         None => {
             return Err(Box::new(DBError::ParseError(
@@ -123,13 +185,6 @@ fn run_rename(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
         }
     };
 
-    // read attributes out of the given list
-    let mut new_attributes: Vec<Identifier> = Vec::new();
-    for attribute_name in iterate_list(attribute_names)? {
-        // iterate list is a helper function for parenthesis surrounded lists
-        new_attributes.push(Identifier::from(attribute_name.trim())?);
-    }
-
     // ensure correct number of attributes
     if table.attributes().len() != new_attributes.len() {
         return Err(Box::new(DBError::ConstraintError(
@@ -150,89 +205,70 @@ fn run_rename(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 ///
 /// Fails when cannot parse command or cannot set new key given.
 /// Also fails when the file cannot be written to or the database path is not set.
-fn run_let(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
+fn run_let(
+    new_table_name: Identifier,
+    key_attri: Option<Identifier>,
+    select: Select,
+    db: &mut Database,
+) -> Result<(), Box<dyn Error>> {
     // check db.path since table creation requires a valid path
-    if db.path == "" {
+    if db.path.is_empty() {
         return Err(Box::new(DBError::ParseError(
             "Database path not set. Run the USE command before table creation.",
         )));
     }
 
-    // get new_table_name out of the cmd
-    let (new_table_name, cmd) = match cmd.split_once(" key ") {
-        Some((new_table_name, cmd)) => (new_table_name.trim(), cmd.trim()),
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "LET command requires KEY clause.",
-            )))
-        }
-    };
-
     // ensure the table name is unique
-    if db.table_map.get(new_table_name).is_some() {
+    let new_table_name = new_table_name.name();
+    if db.table_map.contains_key(new_table_name) {
         return Err(Box::new(DBError::ParseError(
             "Table with name given already exists.",
         )));
     }
 
-    // remove the select keyword since this is what run_cmd ordinarily does and the helper function expects
-    let (key_attri, cmd) = match cmd.split_once(" select ") {
-        Some((key_attri, cmd)) => (key_attri.trim(), cmd.trim()),
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "LET command requires SELECT clause.",
-            )))
-        }
-    };
-
     // call the helper function which returns a MemTable
-    let selected_table = select_from_tables(cmd, db)?;
+    let selected_table = select_from_tables(select, db)?;
 
     // copy out the projected attributes for new table
     let attribute_list: Vec<(Identifier, Domain)> = selected_table
         .get_projected_attribute_list() // required since projection is implemented with no immediate deletions
         .into_iter()
-        .map(|tuple| tuple.clone())
+        .cloned()
         .collect();
 
     // look for the new primary key in the projected attributes
     let mut primary_key = None;
     for (i, attribute) in attribute_list.iter().enumerate() {
-        if key_attri == attribute.0.name() {
+        if key_attri.as_ref() == Some(&attribute.0) {
             primary_key = Some(i);
         }
     }
 
-    // allow for none to be specified to express the selection having no key
-    if primary_key.is_none() && key_attri != "none" {
+    // KEY NONE gives the selection no key
+    if primary_key.is_none() && key_attri.is_some() {
         return Err(Box::new(DBError::ParseError(
             "KEY given must be one of the selected attributes.",
         )));
     }
 
-    // create the table - this creates the new table file and the bst file
-    let mut table = Table::build(new_table_name, attribute_list, primary_key, &db.path)?;
+    // create the table - this creates the new table file and the index file
+    let primary_key = primary_key.into_iter().collect();
+    let constraints = vec![ColumnConstraints::default(); attribute_list.len()];
+    let mut table = Table::build(
+        new_table_name,
+        attribute_list,
+        constraints,
+        primary_key,
+        &db.path,
+    )?;
 
     // write projected records to new table
-    for rec_num in 0..selected_table.records.len() {
-        match table.write_record(selected_table.get_projected_record(rec_num)) {
-            Ok(()) => (),
-            Err(err) => {
-                table.clean_up()?; // delete the table if the key specfied has duplicates
-                return Err(err);
-            }
-        };
-    }
-
-    // Table.write_record updates record count in memory but not in the file
-    table.write_record_count()?;
-
-    if let Some(ref bst) = table.bst {
-        // balance the bst plus write it to file
-        let mut bst_path = table.file_path.clone();
-        bst_path.replace_range(table.file_path.len() - 3.., "index");
-        bst.write_to_file(&bst_path)?;
-        table.bst = Some(BST::read_from_file(&bst_path)?);
+    let records = (0..selected_table.records.len())
+        .map(|rec_num| selected_table.get_projected_record(rec_num))
+        .collect();
+    if let Err(err) = table.write_records(records) {
+        table.clean_up()?; // delete the table if the key specfied has duplicates
+        return Err(err);
     }
 
     // insert the new table into the database map
@@ -248,44 +284,25 @@ fn run_let(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 /// # Errors
 ///
 /// Fails when cannot parse command or when the file cannot be written to.
-fn run_update(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    // split out table_name
-    let (table_name, cmd) = match cmd.split_once(" set ") {
-        Some((table_name, cmd)) => (table_name.trim(), cmd.trim()),
-        None => return Err(DBError::ParseError("UPDATE directive requires SET clause."))?,
-    };
-
-    // split out new_values list
-    let (new_values, condition) = match cmd.split_once(" where") {
-        Some((new_values, condition)) => {
-            if condition.starts_with(' ') || condition.starts_with('(') {
-                (new_values.trim(), condition.trim())
-            } else {
-                Err(DBError::ParseError(
-                    "Could not parse clause after SET clause in UPDATE.",
-                ))?
-            }
-        },
-        None => (cmd, ""), // empty condition = always true
-    };
-
+fn run_update(
+    table_name: Identifier,
+    new_values: Vec<(Identifier, Data)>,
+    cond: Condition,
+    db: &mut Database,
+) -> Result<(), Box<dyn Error>> {
     // get a handle to the table to update
-    let table = match db.table_map.get_mut(table_name) {
+    let table_name = table_name.name();
+    let table = match db.table_map.get(table_name) {
         Some(table) => table,
-        None => {
-            Err(DBError::ParseError(
-                "Could not find a table with that name to update.",
-            ))?
-        }
+        None => Err(DBError::ParseError(
+            "Could not find a table with that name to update.",
+        ))?,
     };
 
-    // create a Condition based on the WHERE clause
-    let cond = Condition::parse(condition)?;
-    // read out the new attribute values
-    let new_values = parse_new_attr_values(&table, new_values)?;
-    // update the table with the new values - Condition calls Table's update functions after
-    // connecting its attribute names to the table's attribute list
-    cond.update(table, new_values)?;
+    // convert the new attribute values to the Domains of their attributes
+    let new_values = convert_new_attr_values(table, new_values)?;
+    // update the table with the new values along with any records which reference the ones changed
+    references::update(db, table_name, cond, new_values)?;
     eprintln!("\tUPDATE Success!");
     Ok(())
 }
@@ -296,20 +313,16 @@ fn run_update(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 /// # Errors
 ///
 /// Fails when cannot parse command or when the file cannot be written to.
-fn run_delete(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
+fn run_delete(
+    table_name: Identifier,
+    condition: Option<Condition>,
+    db: &mut Database,
+) -> Result<(), Box<dyn Error>> {
     // if a condition is given the table will be culled from, else delete the whole table
     // uses one of two helper functions for each case
-    match cmd.split_once(" where") {
-        Some((table_name, condition)) => {
-            if condition.starts_with(' ') || condition.starts_with('(') {
-                delete_tuples(db, table_name.trim(), condition.trim())?
-            } else {
-                Err(DBError::ParseError(
-                    "Could not parse clause after table name in DELETE.",
-                ))?
-            }
-        },
-        None => delete_table(db, cmd.trim())?,
+    match condition {
+        Some(condition) => delete_tuples(db, table_name.name(), condition)?,
+        None => delete_table(db, table_name.name())?,
     }
     eprintln!("\tDELETE Success!");
     Ok(())
@@ -322,14 +335,11 @@ fn run_delete(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 ///
 /// Fails when cannot parse command or when any command in the file fails.
 /// Also fails when the file cannot be read or when output file cannot be written to.
-fn run_input(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    // check whether or not there is an output file specified.
-    // specifying 'output\s;' will be read as no file specified.
-    let (file_name, output_name) = match cmd.split_once(" output ") {
-        Some((file_name, output_name)) => (file_name.trim(), output_name.trim()),
-        None => (cmd.trim(), ""),
-    };
-
+fn run_input(
+    file_name: &str,
+    output_name: Option<&str>,
+    db: &mut Database,
+) -> Result<(), Box<dyn Error>> {
     // open the input file
     let mut input = match File::open(file_name) {
         Ok(file) => file,
@@ -339,16 +349,15 @@ fn run_input(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
     };
 
     // open or create output file
-    let mut output_file = if output_name.is_empty() {
-        None
-    } else {
-        Some(
+    let mut output_file = match output_name {
+        Some(output_name) => Some(
             OpenOptions::new()
                 .create(true)
                 .write(true)
                 .truncate(true)
                 .open(output_name)?,
-        )
+        ),
+        None => None,
     };
 
     // read input file to string
@@ -356,13 +365,26 @@ fn run_input(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
     input.read_to_string(&mut input_string)?;
 
     // iterate through all cmds in file... bubble any errors up to main loop
-    for cmd in CmdIterator::over(&input_string) {
-        let output = run_cmd(cmd.trim_start(), db)?;
+    let mut cmds = CmdIterator::over(&input_string);
+    while let Some(cmd) = cmds.next() {
+        let output = match run_cmd(&cmd, db) {
+            Ok(output) => output,
+            Err(mut err) => {
+                // say where in the file the failing command is
+                cmds.locate(&mut err, Some(file_name));
+                let (line, _) = cmds.line_col(cmd.len() - cmd.trim_start().len());
+                eprintln!(
+                    "\tINPUT stopped at statement {} on line {line} of {file_name}.",
+                    cmds.statement_count()
+                );
+                return Err(err);
+            }
+        };
         if let Some(ref mut file) = output_file {
             // if output file specified, write to file
             let to_file = output
                 .iter()
-                .map(|out| [out.as_bytes(), &[b'\n']].concat())
+                .map(|out| [out.as_bytes(), b"\n"].concat())
                 .collect::<Vec<Vec<u8>>>()
                 .concat();
             file.write_all(&to_file)?;
@@ -383,8 +405,7 @@ fn run_input(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 /// # Errors
 ///
 /// Fails when cannot parse command or when the files in the database fail to read correctly.
-fn run_use(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    let db_name = Identifier::from(cmd.trim())?; // Once this succeeds we know the database name could be valid
+fn run_use(db_name: Identifier, db: &mut Database) -> Result<(), Box<dyn Error>> {
     *db = Database::build("./".to_owned() + db_name.name() + "/")?; // reads all .dat files and any mathing .index files
     eprintln!("\tUSE Success!");
     Ok(())
@@ -397,19 +418,14 @@ fn run_use(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 ///
 /// Fails when cannot parse command or cannot insert a key given.
 /// Also fails when the file cannot be written to.
-fn run_insert(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    // split out table_name
-    let (table_name, cmd) = match cmd.split_once(" values") {
-        Some((table_name, cmd)) => (table_name.trim(), cmd.trim()),
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "Invalid arguments for INSERT",
-            )))
-        }
-    };
-
+fn run_insert(
+    table_name: Identifier,
+    values: Vec<Option<Data>>,
+    db: &mut Database,
+) -> Result<(), Box<dyn Error>> {
     // get a handle to the Table we want to insert into
-    let table = match db.table_map.get_mut(table_name) {
+    let table_name = table_name.name();
+    let table = match db.table_map.get(table_name) {
         Some(table) => table,
         None => {
             return Err(Box::new(DBError::ParseError(
@@ -418,45 +434,34 @@ fn run_insert(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
         }
     };
 
-    // iterate the new values out of the remaining cmd string
-    let values: Vec<Result<Data, Box<dyn Error>>> = iterate_list(cmd)?
-        .enumerate() // get the numbers for each to match in Table
-        .map(|(index, value)| -> Result<Data, Box<dyn Error>> {
-            let value = value.trim(); // trim the data
-            let domain = match table.attributes().get(index) {
-                Some(tuple) => tuple.1,
-                None => {
-                    return Err(Box::new(DBError::ParseError(
-                        "Too many attributes in value list to INSERT",
-                    )));
-                }
-            }; // check domain -- this yields an error if values list is too long for table
-            match domain {
-                // try to parse the datatype expected for each attribute
-                Domain::Integer => Ok(Data::Integer(Integer::from(value)?)),
-                Domain::Float => Ok(Data::Float(Float::from(value)?)),
-                Domain::Text => {
-                    if value.starts_with('"') && value.ends_with('"') && value.len() > 1 {
-                        // unwrap the double quotes before feeding it to Text
-                        Ok(Data::Text(Text::from(&value[1..value.len() - 1].trim())?))
-                    // all text values are trimmed before stored
-                    } else {
-                        return Err(Box::new(DBError::ParseError(
-                            "String literal expected. Wrap literals in double quotes.",
-                        )));
-                    }
-                }
-            }
-        })
-        .collect();
-    let mut record: Vec<Data> = Vec::with_capacity(values.len());
-    for value in values.into_iter() {
-        record.push(value?); // unwrap the Results that were not possible in the closure.
-                             // this is only neccessary since the '?' op cannot be used at the end of each map closure call
+    if values.len() > table.attributes().len() {
+        return Err(Box::new(DBError::ParseError(
+            "Too many attributes in value list to INSERT",
+        )));
     }
 
+    // check the domain of each value, where DEFAULT is None
+    let mut record: Vec<Data> = Vec::with_capacity(table.attributes().len());
+    for (index, value) in values.into_iter().enumerate() {
+        record.push(match value {
+            Some(value) => value_for_domain(value, table.attributes()[index].1)?,
+            None => table.default_value(index),
+        });
+    }
+    // attributes left off of the end of the list get their default values
+    for attri_num in record.len()..table.attributes().len() {
+        record.push(table.default_value(attri_num));
+    }
+
+    // every value which REFERENCES another table must be a key in it
+    references::check_references(db, table_name, record.iter().enumerate())?;
+
     // once the record is verified and created in memory, try to write it to the table
-    table.write_single_record(record)?;
+    let table = db
+        .table_map
+        .get_mut(table_name)
+        .expect("table was found above");
+    table.write_record(record)?;
     eprintln!("\tINSERT Success!");
     Ok(())
 }
@@ -467,17 +472,147 @@ fn run_insert(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
 /// # Errors
 ///
 /// Fails when the files cannot be written to.
-pub fn run_exit(db: &Database) -> Result<(), Box<dyn Error>> {
+pub fn run_exit(db: &mut Database) -> Result<(), Box<dyn Error>> {
+    if db.in_transaction {
+        eprintln!("\tRolling back uncommitted transaction");
+        rollback(db)?;
+    }
     eprintln!("\tSaving Database state");
-    // store the bst and record count for all tables
-    for (_, table) in db.table_map.iter() {
-        table.write_record_count()?;
-        table.write_bst()?;
+    // every change to the records and indexes has already gone through the log and been applied
+    if !db.path.is_empty() {
+        Wal::open(&db.path).checkpoint()?;
     }
     eprintln!("\tPROGRAM END");
     std::process::exit(0);
 }
 
+/// Attempts to run the BEGIN command. Returns a result indicating either
+/// a success or a parsing Error.
+///
+/// # Errors
+///
+/// Fails when no database is in use, or when a transaction is already open.
+fn run_begin(db: &mut Database) -> Result<(), Box<dyn Error>> {
+    if db.path.is_empty() {
+        Err(DBError::ParseError(
+            "Database path not set. Run the USE command before BEGIN.",
+        ))?
+    }
+    if db.in_transaction {
+        Err(DBError::ParseError("A transaction is already open."))?
+    }
+
+    begin(db);
+    eprintln!("\tBEGIN Success!");
+    Ok(())
+}
+
+/// Starts a transaction on every table in the database.
+fn begin(db: &mut Database) {
+    for table in db.table_map.values_mut() {
+        table.begin_transaction();
+    }
+    db.in_transaction = true;
+}
+
+/// Returns a Savepoint for each table in the open transaction, so that the changes made by
+/// a statement can be thrown away if it fails.
+fn savepoint(db: &Database) -> Vec<(String, Savepoint)> {
+    db.table_map
+        .iter()
+        .map(|(name, table)| (name.clone(), table.savepoint()))
+        .collect()
+}
+
+/// Throws away every change made in the open transaction since savepoints were taken,
+/// leaving the changes made before them to be committed or rolled back.
+///
+/// # Errors
+///
+/// Fails when a table cannot be returned to its savepoint.
+fn rollback_to(
+    db: &mut Database,
+    savepoints: Vec<(String, Savepoint)>,
+) -> Result<(), Box<dyn Error>> {
+    for (name, savepoint) in savepoints {
+        if let Some(table) = db.table_map.get_mut(&name) {
+            table.rollback_to(savepoint)?;
+        }
+    }
+    Ok(())
+}
+
+/// Attempts to run the COMMIT command. Every change made since BEGIN is written to the
+/// write-ahead log as one entry, so either all of them survive a crash or none do.
+///
+/// # Errors
+///
+/// Fails when no transaction is open, or when the files cannot be written to.
+fn run_commit(db: &mut Database) -> Result<(), Box<dyn Error>> {
+    if !db.in_transaction {
+        Err(DBError::ParseError("No transaction is open to COMMIT."))?
+    }
+
+    commit(db)?;
+    eprintln!("\tCOMMIT Success!");
+    Ok(())
+}
+
+/// Writes every change made since BEGIN to the write-ahead log as one entry, applies them
+/// to the files, and ends the transaction.
+///
+/// # Errors
+///
+/// Fails when the files cannot be written to.
+fn commit(db: &mut Database) -> Result<(), Box<dyn Error>> {
+    let mut ops = Vec::new();
+    for table in db.table_map.values_mut() {
+        ops.append(&mut table.take_pending());
+    }
+    Wal::open(&db.path).commit(&ops)?;
+
+    for table in db.table_map.values_mut() {
+        table.end_transaction()?;
+    }
+    db.in_transaction = false;
+    Ok(())
+}
+
+/// Attempts to run the ROLLBACK command. Returns a result indicating either
+/// a success or a parsing/file Error.
+///
+/// # Errors
+///
+/// Fails when no transaction is open, or when the tables cannot be reloaded.
+fn run_rollback(db: &mut Database) -> Result<(), Box<dyn Error>> {
+    if !db.in_transaction {
+        Err(DBError::ParseError("No transaction is open to ROLLBACK."))?
+    }
+
+    rollback(db)?;
+    eprintln!("\tROLLBACK Success!");
+    Ok(())
+}
+
+/// Throws away every change made since BEGIN. The table files were never touched, so only the
+/// shadow copies need to be removed and the tables (with their indexes) reloaded from disk.
+///
+/// # Errors
+///
+/// Fails when the shadow copies cannot be removed or the tables cannot be reloaded.
+fn rollback(db: &mut Database) -> Result<(), Box<dyn Error>> {
+    for (name, table) in db.table_map.iter_mut() {
+        let changed = table.has_pending();
+        table.end_transaction()?;
+        if changed {
+            // the in-memory record count and index pages still hold the uncommitted changes
+            *table = Table::read_from_file(name, &db.path)?;
+        }
+    }
+    db.in_transaction = false;
+    Ok(())
+}
+
 /// Attempts to parse and run the DESCRIBE command. Returns a result containing the successful
 /// description of the desired table(s) or a parsing error. This function could easily only return DBError, but
 /// it is easier to match the other cmd functions so that run_cmd has a certain standard
@@ -485,18 +620,14 @@ pub fn run_exit(db: &Database) -> Result<(), Box<dyn Error>> {
 /// # Errors
 ///
 /// Fails when cannot parse command.
-fn run_describe(cmd: &str, db: &Database) -> Result<Vec<String>, Box<dyn Error>> {
-    let table_name = cmd.trim();
-    if table_name.is_empty() {
-        return Err(Box::new(DBError::ParseError(
-            "DESCRIBE requires at least one argument.",
-        )));
-    }
-
+fn run_describe(
+    table_name: Option<Identifier>,
+    db: &Database,
+) -> Result<Vec<String>, Box<dyn Error>> {
     let mut output = Vec::new();
-    if table_name != "all" {
+    if let Some(table_name) = table_name {
         // if you only wanted one table print that one out
-        let table = match db.table_map.get(table_name) {
+        let table = match db.table_map.get(table_name.name()) {
             Some(table) => table,
             None => {
                 return Err(Box::new(DBError::ParseError(
@@ -504,7 +635,7 @@ fn run_describe(cmd: &str, db: &Database) -> Result<Vec<String>, Box<dyn Error>>
                 )))
             }
         };
-        output.push(format!("{}", table_name.to_uppercase()));
+        output.push(table_name.name().to_uppercase());
         output.append(&mut table.attributes_to_string_vec());
         output.push(String::from(""));
         return Ok(output);
@@ -512,7 +643,7 @@ fn run_describe(cmd: &str, db: &Database) -> Result<Vec<String>, Box<dyn Error>>
 
     // otherwise default to print out all the tables
     for (table_name, table) in db.table_map.iter() {
-        output.push(format!("{}", table_name.to_uppercase()));
+        output.push(table_name.to_uppercase().to_string());
         output.append(&mut table.attributes_to_string_vec());
         output.push(String::from(""));
     }
@@ -521,33 +652,261 @@ fn run_describe(cmd: &str, db: &Database) -> Result<Vec<String>, Box<dyn Error>>
     Ok(output)
 }
 
-/// Attempts to parse and run the CREATE command. Returns a result indicating either
+/// Attempts to parse and run the DROP INDEX command. Returns a result indicating either
 /// a success or a parsing/file Error.
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command or when the file cannot be written to.
-fn run_create(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    let cmd = cmd.trim_start();
-    // split out first word given in command
-    let (cmd_0, cmd) = match cmd.split_once(' ') {
-        Some(tuple) => tuple,
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "CREATE command requires arguments.",
-            )))
+/// Fails when cannot parse command, when no index has the given name,
+/// or when the index file cannot be removed.
+fn run_drop(index_name: Identifier, db: &mut Database) -> Result<(), Box<dyn Error>> {
+    let index_name = index_name.name();
+
+    for table in db.table_map.values_mut() {
+        if table.drop_index(index_name)? {
+            eprintln!("\tDROP Success!");
+            return Ok(());
         }
-    };
+    }
+    Err(DBError::ParseError(
+        "Could not find an index with that name to drop.",
+    ))?
+}
 
-    // call one of two helpers which handle creation of a database or a table
-    if cmd_0 == "database" {
-        create_database(cmd)?;
-    } else if cmd_0 == "table" {
-        create_table(cmd, db)?;
-    } else {
-        Err(DBError::ParseError("Syntax error after directive CREATE."))?
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Integer, Text};
+    use std::fs;
+
+    /// Returns a Database in an empty directory kept for the named test.
+    fn test_db(name: &str) -> Database {
+        let dir =
+            std::env::temp_dir().join(format!("mini_dbms_db_cmds_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Database::build(format!("{}/", dir.display())).unwrap()
     }
 
-    eprintln!("\tCREATE Success!");
-    Ok(())
+    /// Returns the Database at the same path as db, read back from its files.
+    fn reopen(db: &Database) -> Database {
+        Database::build(db.path.clone()).unwrap()
+    }
+
+    fn remove_db(db: Database) {
+        fs::remove_dir_all(&db.path).unwrap();
+    }
+
+    /// Runs each command in cmds, panicking if one fails.
+    fn run(db: &mut Database, cmds: &str) {
+        for cmd in CmdIterator::over(cmds) {
+            if let Err(err) = run_cmd(&cmd, db) {
+                panic!("{cmd} failed: {err}");
+            }
+        }
+    }
+
+    /// Returns the projected records the SELECT command cmd selects.
+    fn select(db: &mut Database, cmd: &str) -> Vec<Vec<Data>> {
+        let Statement::Select(select) = parse_statement(cmd).unwrap() else {
+            panic!("{cmd} is not a SELECT");
+        };
+        let selection = select_from_tables(select, db).unwrap();
+        (0..selection.records.len())
+            .map(|rec_num| selection.get_projected_record(rec_num))
+            .collect()
+    }
+
+    fn int(value: i32) -> Data {
+        Data::Integer(Integer::wrap(value))
+    }
+
+    fn text(value: &str) -> Data {
+        Data::Text(Text::from(value).unwrap())
+    }
+
+    #[test]
+    fn rolled_back_inserts_are_not_visible() {
+        let mut db = test_db("rollback");
+        run(
+            &mut db,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT); INSERT t VALUES (1, \"kept\");",
+        );
+        run(&mut db, "BEGIN; INSERT t VALUES (2, \"dropped\");");
+        assert_eq!(select(&mut db, "SELECT id FROM t").len(), 2);
+        run(&mut db, "ROLLBACK;");
+
+        let expected = vec![vec![int(1), text("kept")]];
+        assert_eq!(select(&mut db, "SELECT all FROM t"), expected);
+        assert!(select(&mut db, "SELECT all FROM t WHERE id = 2").is_empty());
+        let mut db = reopen(&db);
+        assert_eq!(select(&mut db, "SELECT all FROM t"), expected);
+        remove_db(db);
+    }
+
+    #[test]
+    fn commits_change_every_table() {
+        let mut db = test_db("commit");
+        run(
+            &mut db,
+            "CREATE TABLE a (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE b (id INTEGER PRIMARY KEY, n INTEGER);
+            INSERT a VALUES (1, \"one\");",
+        );
+        run(
+            &mut db,
+            "BEGIN;
+            UPDATE a SET name = \"uno\" WHERE id = 1;
+            INSERT a VALUES (2, \"two\");
+            INSERT b VALUES (5, 50);
+            COMMIT;",
+        );
+        assert!(db.table_map.values().all(|table| !table.has_pending()));
+
+        let mut db = reopen(&db);
+        assert_eq!(
+            select(&mut db, "SELECT all FROM a ORDER BY id"),
+            vec![vec![int(1), text("uno")], vec![int(2), text("two")]]
+        );
+        assert_eq!(
+            select(&mut db, "SELECT all FROM b"),
+            vec![vec![int(5), int(50)]]
+        );
+        assert_eq!(
+            select(&mut db, "SELECT n FROM b WHERE id = 5"),
+            vec![vec![int(50)]]
+        );
+        remove_db(db);
+    }
+
+    #[test]
+    fn failed_statements_roll_back_to_their_savepoint() {
+        let mut db = test_db("savepoint");
+        run(
+            &mut db,
+            "CREATE TABLE parent (id INTEGER PRIMARY KEY);
+            CREATE TABLE child (id INTEGER PRIMARY KEY,
+                parent INTEGER CHECK (parent < 100) REFERENCES parent(id) ON UPDATE CASCADE);
+            INSERT parent VALUES (1); INSERT child VALUES (10, 1);",
+        );
+        run(
+            &mut db,
+            "BEGIN; INSERT parent VALUES (2); INSERT child VALUES (20, 2);",
+        );
+        // the parent may be changed before the cascade to the child breaks its CHECK
+        let update = run_cmd("UPDATE parent SET id = 200 WHERE id = 1;", &mut db);
+        assert!(update.is_err());
+        assert!(run_cmd("INSERT parent VALUES (2);", &mut db).is_err()); // a duplicate key
+        run(&mut db, "INSERT parent VALUES (3); COMMIT;");
+
+        let mut db = reopen(&db);
+        let parents = vec![vec![int(1)], vec![int(2)], vec![int(3)]];
+        assert_eq!(
+            select(&mut db, "SELECT id FROM parent ORDER BY id"),
+            parents
+        );
+        assert!(select(&mut db, "SELECT id FROM parent WHERE id = 200").is_empty());
+        assert_eq!(
+            select(&mut db, "SELECT all FROM child ORDER BY id"),
+            vec![vec![int(10), int(1)], vec![int(20), int(2)]]
+        );
+        assert_eq!(
+            select(&mut db, "SELECT id FROM child WHERE parent = 1"),
+            vec![vec![int(10)]]
+        );
+        remove_db(db);
+    }
+
+    #[test]
+    fn opening_a_database_removes_leftover_shadow_copies() {
+        let mut db = test_db("shadows");
+        run(
+            &mut db,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT t VALUES (1);",
+        );
+        let shadow = format!("{}t.dat.txn", db.path);
+        fs::write(&shadow, b"an uncommitted copy").unwrap();
+
+        let mut db = reopen(&db);
+        assert!(!std::path::Path::new(&shadow).exists());
+        assert_eq!(select(&mut db, "SELECT all FROM t"), vec![vec![int(1)]]);
+        remove_db(db);
+    }
+
+    #[test]
+    fn updates_compact_the_heap() {
+        let mut db = test_db("update_heap");
+        run(
+            &mut db,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT);
+            INSERT t VALUES (1, \"first\"); INSERT t VALUES (2, \"second\");",
+        );
+        for round in 0..20 {
+            let cmd = format!("UPDATE t SET name = \"name number {round}\" WHERE id = 1;");
+            run(&mut db, &cmd);
+        }
+
+        let heap_len = fs::metadata(format!("{}t.heap", db.path)).unwrap().len();
+        let used_len = ("name number 19".len() + "second".len()) as u64;
+        assert!(heap_len <= used_len * 2, "the heap kept {heap_len} bytes");
+        let mut db = reopen(&db);
+        assert_eq!(
+            select(&mut db, "SELECT all FROM t ORDER BY id"),
+            vec![
+                vec![int(1), text("name number 19")],
+                vec![int(2), text("second")]
+            ]
+        );
+        remove_db(db);
+    }
+
+    #[test]
+    fn dropped_indexes_lose_their_files() {
+        let mut db = test_db("drop_index");
+        run(
+            &mut db,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, n INTEGER);
+            INSERT t VALUES (1, 10); CREATE INDEX by_n ON t (n);",
+        );
+        let index_path = format!("{}t.by_n.idx", db.path);
+        assert!(std::path::Path::new(&index_path).exists());
+        run(&mut db, "DROP INDEX by_n;");
+
+        assert!(!std::path::Path::new(&index_path).exists());
+        assert!(run_cmd("DROP INDEX by_n;", &mut db).is_err());
+        let mut db = reopen(&db);
+        assert!(db.table_map["t"].secondary_index_on(1).is_none());
+        assert_eq!(
+            select(&mut db, "SELECT id FROM t WHERE n = 10"),
+            vec![vec![int(1)]]
+        );
+        remove_db(db);
+    }
+
+    #[test]
+    fn composite_keys_are_unique_only_as_a_whole() {
+        let mut db = test_db("composite_key");
+        run(
+            &mut db,
+            "CREATE TABLE t (a INTEGER, b INTEGER, v TEXT, PRIMARY KEY (a, b));
+            INSERT t VALUES (1, 1, \"one one\"); INSERT t VALUES (1, 2, \"one two\");
+            INSERT t VALUES (2, 1, \"two one\"); INSERT t VALUES (2, 2, \"two two\");",
+        );
+        assert!(run_cmd("INSERT t VALUES (1, 2, \"again\");", &mut db).is_err());
+        assert!(run_cmd("UPDATE t SET b = 1 WHERE v = \"one two\";", &mut db).is_err());
+        run(&mut db, "INSERT t VALUES (1, 3, \"one three\");"); // shares a prefix with (1, 1)
+
+        let prefix = select(&mut db, "SELECT v FROM t WHERE a = 1");
+        let ones = ["one one", "one two", "one three"].map(|v| vec![text(v)]);
+        assert_eq!(prefix, ones);
+        let prefix = select(&mut db, "SELECT v FROM t WHERE a = 2 ORDER BY v");
+        assert_eq!(prefix, ["two one", "two two"].map(|v| vec![text(v)]));
+        assert!(select(&mut db, "SELECT v FROM t WHERE a = 3").is_empty());
+        let full = select(&mut db, "SELECT v FROM t WHERE a = 2 AND b = 1");
+        assert_eq!(full, vec![vec![text("two one")]]);
+
+        let mut db = reopen(&db);
+        assert_eq!(select(&mut db, "SELECT v FROM t WHERE a = 1"), ones);
+        remove_db(db);
+    }
 }
@@ -1,59 +1,56 @@
-use crate::base::{Data, Domain, Identifier};
-use crate::logic::{Condition, Constraint, Operand, RelOp};
+use crate::aggregate::{self, Aggregate};
+use crate::base::{Data, Date, Domain, Identifier, Timestamp};
+use crate::logic::Condition;
+use crate::parser::{ColumnDef, Projection, Select};
 use crate::relation::{MemTable, Table};
 use crate::{DBError, Database};
 use std::error::Error;
 use std::{collections::HashMap, fs, path};
 
-/// Attempts to split a parenthesis surrounded list. Returns a result containing either the iterator
-/// over that split or a parsing Error
+use super::references;
+
+/// Attempts to convert a constant, as the parser read it, to a value of the given domain.
+/// Data::Null fits every domain. Dates and Timestamps may be written with or without double quotes,
+/// and numbers are promoted to the numeric domain of the attribute when it can hold them.
 ///
 /// # Errors
 ///
-/// Fails when list is not in the format '(item, item, item)'
-pub fn iterate_list<'a>(list: &'a str) -> Result<std::str::Split<'a, char>, Box<dyn Error>> {
-    let (check, list) = match list.split_once('(') {
-        Some(tuple) => tuple,
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "Lists must be wrapped in parenthesis.",
-            )))
-        }
+/// Fails when data is not a constant of the domain.
+pub fn value_for_domain(data: Data, domain: Domain) -> Result<Data, Box<dyn Error>> {
+    let data = match (data, domain) {
+        (Data::Text(text), Domain::Date) => Data::Date(Date::from(text.content())?),
+        (Data::Text(text), Domain::Timestamp) => Data::Timestamp(Timestamp::from(text.content())?),
+        (Data::Date(date), Domain::Timestamp) => Data::Timestamp(Timestamp::from_date(&date)),
+        (data, domain) => data.promote_to(domain)?,
     };
-
-    if check.trim() != "" {
-        return Err(Box::new(DBError::ParseError(
-            "Cannot parse list that does not start with '('",
-        )));
-    }
-
-    let (list, check) = match list.split_once(')') {
-        Some(tuple) => tuple,
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "Lists must be wrapped in parenthesis.",
-            )))
-        }
-    };
-
-    if check.trim() != "" {
-        return Err(Box::new(DBError::ParseError(
-            "Cannot parse list that does not end with ')'",
-        )));
+    match (&data, domain) {
+        (Data::Text(text), Domain::VarChar(limit)) if text.content().len() > limit as usize => Err(
+            DBError::ParseError("Text is longer than its VARCHAR limit."),
+        )?,
+        (Data::Null, _) | (Data::Text(_), Domain::Text | Domain::VarChar(_)) => Ok(data),
+        (_, Domain::Text | Domain::VarChar(_)) => Err(DBError::ParseError(
+            "String literal expected. Wrap literals in double quotes.",
+        ))?,
+        (Data::Integer(_), Domain::Integer)
+        | (Data::BigInt(_), Domain::BigInt)
+        | (Data::Double(_), Domain::Double)
+        | (Data::Decimal(_), Domain::Decimal(..))
+        | (Data::Boolean(_), Domain::Boolean)
+        | (Data::Date(_), Domain::Date)
+        | (Data::Timestamp(_), Domain::Timestamp) => Ok(data),
+        _ => Err(DBError::ParseError(
+            "Value given does not match the Domain of its attribute.",
+        ))?,
     }
-
-    return Ok(list.split(','));
 }
 
-/// Attempts to parse and run the CREATE DATABASE sub-command. Returns a result indicating either
-/// a success or a parsing/file Error
+/// Attempts to run the CREATE DATABASE sub-command. Returns a result indicating either
+/// a success or a file Error
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command or when the filesystem cannot be written to.
-pub fn create_database(cmd: &str) -> Result<(), Box<dyn Error>> {
-    let db_name = Identifier::from(cmd.trim())?;
-
+/// Fails when the filesystem cannot be written to.
+pub fn create_database(db_name: Identifier) -> Result<(), Box<dyn Error>> {
     let db_path = "./".to_owned() + db_name.name();
 
     if !path::Path::new(&db_path).is_dir() {
@@ -65,111 +62,195 @@ pub fn create_database(cmd: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-/// Attempts to parse and run the CREATE TABLE sub-command. Returns a result indicating either
-/// a success or a parsing/file Error
+/// Attempts to run the CREATE TABLE sub-command. Returns a result indicating either
+/// a success or a constraint/file Error
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command or when the file cannot be created/written to.
-pub fn create_table(cmd: &str, db: &mut Database) -> Result<(), Box<dyn Error>> {
-    if db.path == "" {
+/// Fails when the definitions do not make a valid table or when the file cannot be created/written to.
+pub fn create_table(
+    db: &mut Database,
+    table_name: Identifier,
+    columns: Vec<ColumnDef>,
+    key_names: Option<Vec<Identifier>>,
+) -> Result<(), Box<dyn Error>> {
+    if db.path.is_empty() {
         return Err(Box::new(DBError::ParseError(
             "Database path not set. Run the USE command before table creation.",
         )));
     }
 
-    let cmd = cmd.trim_start();
-    let (table_name, cmd) = 'block: {
-        for (i, c) in cmd.char_indices() {
-            if !c.is_ascii_alphanumeric() && c != '_' {
-                break 'block (&cmd[..i], &cmd[i..]);
-            }
-        }
-
-        return Err(Box::new(DBError::ParseError(
-            "Not enough arguments for CREATE TABLE.",
-        )))
-    };
-
-    if db.table_map.get(table_name).is_some() {
+    let table_name = table_name.name();
+    if db.table_map.contains_key(table_name) {
         return Err(Box::new(DBError::ParseError(
             "Table with name given already exists.",
         )));
     }
 
-    let mut attribute_list: Vec<(Identifier, Domain)> = Vec::new();
+    let mut attribute_list: Vec<(Identifier, Domain)> = Vec::with_capacity(columns.len());
+    let mut constraints_list = Vec::with_capacity(columns.len());
+    let mut primary_key = Vec::new(); // the attribute numbers of the key
+    let mut referenced_keys = Vec::new(); // the attribute numbers and keys named by REFERENCES
 
-    let mut attribute_iter = iterate_list(cmd)?;
-
-    let first_attribute = match attribute_iter.next() {
-        Some(s) => s.trim(),
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "Attribute list must not be empty.",
-            )))
+    for (attri_num, mut column) in columns.into_iter().enumerate() {
+        if column.primary_key {
+            if key_names.is_some() || !primary_key.is_empty() {
+                return Err(Box::new(DBError::ParseError(
+                    "A table can only have one PRIMARY KEY.",
+                )));
+            }
+            primary_key.push(attri_num);
         }
-    };
-
-    let mut first_attri_iter = first_attribute.split_whitespace();
-
-    attribute_list.push((
-        Identifier::from(first_attri_iter.next().unwrap_or(""))?,
-        Domain::from(first_attri_iter.next().unwrap_or(""))?,
-    ));
-
-    let primary_key = match (first_attri_iter.next(), first_attri_iter.next()) {
-        (None, None) => None,
-        (Some("primary"), Some("key")) => Some(0),
-        _ => {
-            return Err(Box::new(DBError::ParseError(
-                "Did not recognize third argument in attribute definition.",
-            )))
+        if let Some(default) = column.constraints.default {
+            column.constraints.default = Some(value_for_domain(default, column.domain)?);
         }
-    };
+        if let Some(parent_key) = column.referenced_key {
+            referenced_keys.push((attri_num, parent_key));
+        }
+        attribute_list.push((column.name, column.domain));
+        constraints_list.push(column.constraints);
+    }
 
-    for attribute in attribute_iter {
-        attribute_list.push(match attribute.trim().split_once(' ') {
-            Some((name, domain)) => (Identifier::from(name)?, Domain::from(domain)?),
+    if attribute_list.is_empty() {
+        return Err(Box::new(DBError::ParseError(
+            "Attribute list must not be empty.",
+        )));
+    }
+
+    // a key made of several attributes is ordered by them in the order they are listed
+    for key_name in key_names.unwrap_or_default() {
+        match attribute_list
+            .iter()
+            .position(|(identifier, _)| *identifier == key_name)
+        {
+            Some(key_num) => primary_key.push(key_num),
             None => {
                 return Err(Box::new(DBError::ParseError(
-                    "Did not find a Domain for an Attribute in the list.",
+                    "PRIMARY KEY names an attribute which is not in the table.",
                 )))
             }
-        });
+        };
+    }
+
+    // a REFERENCES constraint must name the primary key of an existing table, or of this one,
+    // and that key must be a single attribute of the same Domain
+    for (attri_num, parent_key) in referenced_keys {
+        let parent_name = match constraints_list[attri_num].references {
+            Some(ref reference) => reference.table.name(),
+            None => unreachable!("referenced_keys only holds attributes with REFERENCES"),
+        };
+        let parent_key_attribute = if parent_name == table_name {
+            match primary_key[..] {
+                [key_num] => Some(&attribute_list[key_num]),
+                _ => None,
+            }
+        } else {
+            let parent = match db.table_map.get(parent_name) {
+                Some(parent) => parent,
+                None => {
+                    return Err(Box::new(DBError::ParseError(
+                        "REFERENCES names a table which does not exist.",
+                    )))
+                }
+            };
+            match parent.key_attri_nums[..] {
+                [key_num] => Some(&parent.attributes()[key_num]),
+                _ => None,
+            }
+        };
+        let key_domain =
+            match parent_key_attribute {
+                Some((key, domain)) if *key == parent_key => *domain,
+                _ => return Err(Box::new(DBError::ConstraintError(
+                    "REFERENCES must name a PRIMARY KEY of one attribute in the referenced table.",
+                ))),
+            };
+        if key_domain != attribute_list[attri_num].1 {
+            return Err(Box::new(DBError::ConstraintError(
+                "REFERENCES must name an attribute of the same Domain.",
+            )));
+        }
     }
 
     // setup table struct to use its builtin formatting
     db.table_map.insert(
         table_name.to_string(),
-        Table::build(table_name, attribute_list, primary_key, &db.path)?,
+        Table::build(
+            table_name,
+            attribute_list,
+            constraints_list,
+            primary_key,
+            &db.path,
+        )?,
     );
 
     Ok(())
 }
 
-/// Attempts to parse and run the DELETE .. WHERE sub-command. Returns a result indicating either
-/// a success or a parsing/file Error
+/// Attempts to run the CREATE INDEX sub-command, 'name ON table(attribute)'. Returns a
+/// result indicating either a success or a constraint/file Error. Index names are unique across the
+/// database so that DROP INDEX can find an index by its name alone.
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command or when the filesystem cannot be written to.
-pub fn delete_tuples(db: &mut Database, table: &str, cond: &str) -> Result<(), Box<dyn Error>> {
-    let cond = Condition::parse(cond)?;
-    match db.table_map.get_mut(table) {
-        Some(table) => cond.delete(table),
+/// Fails when an index with the same name exists already, when the table is not found,
+/// or when the index file cannot be written to.
+pub fn create_index(
+    db: &mut Database,
+    index_name: Identifier,
+    table_name: Identifier,
+    attribute: Identifier,
+) -> Result<(), Box<dyn Error>> {
+    if db
+        .table_map
+        .values()
+        .any(|table| table.has_secondary_index(index_name.name()))
+    {
+        return Err(Box::new(DBError::ConstraintError(
+            "An index with that name exists already in the database.",
+        )));
+    }
+
+    match db.table_map.get_mut(table_name.name()) {
+        Some(table) => table.create_index(index_name, attribute.name()),
         None => Err(DBError::ParseError(
-            "Could not find a table with that name to delete from.",
+            "Could not find a table with that name to index.",
         ))?,
     }
 }
 
-/// Attempts to parse and run the DELETE table; sub-command. Returns a result indicating either
-/// a success or a parsing/file Error
+/// Attempts to run the DELETE .. WHERE sub-command. Returns a result indicating either
+/// a success or a file Error
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command or when the filesystem cannot be written to.
+/// Fails when the table is not found or when the filesystem cannot be written to.
+pub fn delete_tuples(
+    db: &mut Database,
+    table: &str,
+    cond: Condition,
+) -> Result<(), Box<dyn Error>> {
+    if !db.table_map.contains_key(table) {
+        Err(DBError::ParseError(
+            "Could not find a table with that name to delete from.",
+        ))?
+    }
+    // records which reference the deleted ones are handled by their ON DELETE actions
+    references::delete(db, table, cond)
+}
+
+/// Attempts to run the DELETE table; sub-command. Returns a result indicating either
+/// a success or a file Error
+///
+/// # Errors
+///
+/// Fails when the table is not found or referenced, or when the filesystem cannot be written to.
 pub fn delete_table(db: &mut Database, table: &str) -> Result<(), Box<dyn Error>> {
+    if references::is_referenced(db, table) {
+        Err(DBError::ConstraintError(
+            "Cannot delete a table which another table references.",
+        ))?
+    }
     match db.table_map.remove(table) {
         Some(table) => {
             table.clean_up()?;
@@ -181,109 +262,56 @@ pub fn delete_table(db: &mut Database, table: &str) -> Result<(), Box<dyn Error>
     }
 }
 
-/// Attempts to parse new attribute values for the UPDATE command. Returns a result containing either
-/// the successful parsing or a parsing/constraint error
+/// Attempts to convert the new attribute values of the UPDATE command to the Domains of their attributes.
+/// Returns a result containing either the converted values or a parsing/constraint error
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command/attributes or when two attributes given have the same Identifier
-pub fn parse_new_attr_values(
+/// Fails when an attribute is not in the table, when a value does not fit its attribute's Domain,
+/// or when two attributes given have the same Identifier
+pub fn convert_new_attr_values(
     table: &Table,
-    mut new_values: &str,
+    new_values: Vec<(Identifier, Data)>,
 ) -> Result<Vec<(Identifier, Data)>, Box<dyn Error>> {
-    let tables = vec![table];
-    let mut new_value_equalities = Vec::new();
-    let mut constraint;
-    loop {
-        new_values = new_values.trim_start();
-        (constraint, new_values) = Constraint::parse_split(new_values)?;
-        if constraint.rel_op == RelOp::Equals {
-            constraint.convert_with(&tables)?;
-            new_value_equalities.push(constraint);
-        } else {
-            Err(DBError::ParseError(
-                "Found incorrect operator in UPDATE SET clause.",
-            ))?
-        }
-        if !new_values.is_empty() {
-            new_values = &new_values[1..]; // remove a comma if there is one
-        } else {
-            break;
-        }
-    }
-
-    let mut new_values = Vec::with_capacity(new_value_equalities.len());
+    let mut converted = Vec::with_capacity(new_values.len());
     let mut attributes_used = HashMap::new();
 
-    for equality in new_value_equalities {
-        let id = match equality.left_op {
-            Operand::Attribute((_, j)) => {
+    for (identifier, data) in new_values {
+        let (id, domain) = match table
+            .attributes()
+            .iter()
+            .position(|(attribute, _)| *attribute == identifier)
+        {
+            Some(j) => {
                 if attributes_used.insert(j, 0).is_some() {
                     Err(DBError::ParseError(
                         "Cannot set an attribute twice in an UPDATE SET clause.",
                     ))?
                 }
-                table.attributes()[j].0.clone()
+                table.attributes()[j].clone()
             }
-            _ => Err(DBError::ParseError(
-                "Expected Attribute name in left operator for UPDATE SET clause.",
-            ))?,
-        };
-
-        let data = match equality.right_op {
-            Operand::Value(data) => data,
-            _ => Err(DBError::ParseError(
-                "Expected Data value in right operator for UPDATE SET clause.",
+            None => Err(DBError::ParseError(
+                "Could not find an attribute with that name in the table to UPDATE.",
             ))?,
         };
 
-        new_values.push((id, data));
+        // a number is stored the way its attribute's numeric Domain stores numbers
+        converted.push((id, value_for_domain(data, domain)?));
     }
-    Ok(new_values)
+    Ok(converted)
 }
 
-/// Attempts to parse and run the SELECT command. Returns a result containing the successfully selected
-/// MemTable or a parsing/file error.
+/// Attempts to run a SELECT. Returns a result containing the successfully selected
+/// MemTable or a parsing/file error. Aggregate functions give a MemTable of one record.
 ///
 /// # Errors
 ///
-/// Fails when cannot parse command or when the filesystem cannot be read from.
-pub fn select_from_tables(cmd: &str, db: &mut Database) -> Result<MemTable, Box<dyn Error>> {
-    let (attri_name_list, cmd) = match cmd.split_once(" from ") {
-        Some(tuple) => tuple,
-        None => {
-            return Err(Box::new(DBError::ParseError(
-                "SELECT command requires FROM clause.",
-            )))
-        }
-    };
-
-    let (table_name_list, condition) = match cmd.split_once(" where") {
-        Some((table_name_list, condition)) => {
-            if condition.starts_with(' ') || condition.starts_with('(') {
-                (table_name_list, condition.trim())
-            } else {
-                return Err(Box::new(DBError::ParseError(
-                    "Could not parse clause after FROM clause in SELECT.",
-                )))
-            }
-        }
-        None => (cmd, ""), // "" here since no condition is always true
-    };
-
-    let select_attributes: Vec<&str> = attri_name_list
-        .split(",")
-        .map(|attri| -> &str { attri.trim() })
-        .collect();
-
-    let table_name_list: Vec<&str> = table_name_list
-        .split(",")
-        .map(|table| -> &str { table.trim() })
-        .collect();
-
-    let mut tables = Vec::with_capacity(table_name_list.len());
-    for table_name in table_name_list {
-        match db.table_map.get(table_name) {
+/// Fails when a table or attribute is not found, when an aggregate function cannot be computed,
+/// or when the filesystem cannot be read from.
+pub fn select_from_tables(select: Select, db: &mut Database) -> Result<MemTable, Box<dyn Error>> {
+    let mut tables = Vec::with_capacity(select.tables.len());
+    for table_name in select.tables.iter() {
+        match db.table_map.get(table_name.name()) {
             Some(table) => tables.push(table),
             None => Err(DBError::ParseError(
                 "Could not find one of the tables to SELECT from.",
@@ -291,13 +319,42 @@ pub fn select_from_tables(cmd: &str, db: &mut Database) -> Result<MemTable, Box<
         }
     }
 
-    let cond = Condition::parse(condition)?;
+    let aggregates = match select.projection {
+        Projection::Aggregates(aggregates) => aggregates,
+        projection => {
+            let mut select_table =
+                select
+                    .condition
+                    .select(tables, &select.order_by, select.limit.as_ref())?;
+            if let Projection::Attributes(attributes) = projection {
+                select_table.project(attributes.iter().map(|attri| attri.name()).collect())?;
+            }
+            return Ok(select_table);
+        }
+    };
 
-    let mut select_table = cond.select(tables)?;
+    let mut select_table =
+        if select.condition.is_empty() && aggregates.iter().all(Aggregate::counts_all) {
+            // every record of the cartesian product is counted, so there is no need to read them
+            let count = tables
+                .iter()
+                .try_fold(1usize, |count, table| {
+                    count.checked_mul(table.record_count())
+                })
+                .ok_or(DBError::ConstraintError(
+                    "COUNT is too large for an Integer.",
+                ))?;
+            aggregate::count_all(&aggregates, count)?
+        } else {
+            aggregate::aggregate(&aggregates, &select.condition.select(tables, &[], None)?)?
+        };
 
-    if select_attributes.len() != 1 || select_attributes[0] != "all" {
-        select_table.project(select_attributes)?;
+    // the one record of results is only kept by a LIMIT which does not skip it
+    if select
+        .limit
+        .is_some_and(|limit| limit.count == 0 || limit.offset > 0)
+    {
+        select_table.records.clear();
     }
-
     Ok(select_table)
 }
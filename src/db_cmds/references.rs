@@ -0,0 +1,392 @@
+use super::{begin, commit, rollback};
+use crate::base::{Data, Identifier, Key};
+use crate::logic::Condition;
+use crate::relation::{MemTable, RecordUpdate, RefAction, Reference, Table};
+use crate::{DBError, Database};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+
+/// The records a statement deletes and updates in each table, including the ones it reaches
+/// by following the REFERENCES constraints of the tables which reference the records it changes.
+/// Everything is planned before anything is written so that a RESTRICT stops the whole statement.
+#[derive(Default)]
+struct Plan {
+    deletes: HashMap<String, BTreeSet<usize>>,
+    updates: HashMap<String, BTreeMap<usize, Vec<(Identifier, Data)>>>,
+}
+
+impl Plan {
+    /// Plans to delete the records at record_nums in the table named table_name, along with
+    /// the ON DELETE action of every record that references one of them.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a referencing record RESTRICTs the delete, when a referencing attribute
+    /// cannot be SET NULL, or when a table cannot be read.
+    fn delete(
+        &mut self,
+        db: &Database,
+        table_name: &str,
+        record_nums: Vec<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        let deletes = self.deletes.entry(table_name.to_string()).or_default();
+        // only follow the records which were not already planned, so that cycles end
+        let record_nums: Vec<usize> = record_nums
+            .into_iter()
+            .filter(|record_num| deletes.insert(*record_num))
+            .collect();
+
+        let table = get_table(db, table_name)?;
+        let key_num = match table.key_attri_nums[..] {
+            [key_num] => key_num,
+            _ => return Ok(()), // nothing can reference a table without a key of one attribute
+        };
+        let mut keys = Vec::with_capacity(record_nums.len());
+        for record_num in record_nums {
+            keys.push(table.read_record(record_num)?.swap_remove(key_num));
+        }
+
+        for (child_name, attri_num, reference) in referencing(db, table_name) {
+            let child_nums = self.referencing_records(db, child_name, attri_num, &keys)?;
+            if child_nums.is_empty() {
+                continue;
+            }
+            match reference.on_delete {
+                RefAction::Restrict => Err(DBError::ConstraintError(
+                    "Cannot delete a record which another table references.",
+                ))?,
+                RefAction::Cascade => self.delete(db, child_name, child_nums)?,
+                RefAction::SetNull => {
+                    self.set(db, child_name, child_nums, attri_num, Data::Null)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Plans each update in updates to the table named table_name, along with the ON UPDATE
+    /// action of every record that references a record whose key it changes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a referencing record RESTRICTs the update, when a referencing attribute
+    /// cannot be SET NULL, or when a table cannot be read.
+    fn update(
+        &mut self,
+        db: &Database,
+        table_name: &str,
+        updates: Vec<RecordUpdate>,
+    ) -> Result<(), Box<dyn Error>> {
+        let table = get_table(db, table_name)?;
+        let key_num = match table.key_attri_nums[..] {
+            [key_num] => Some(key_num),
+            _ => None,
+        };
+
+        let planned_updates = self.updates.entry(table_name.to_string()).or_default();
+        let mut key_changes = Vec::new(); // the old and new values of each key that is changed
+        for (record_num, new_values) in updates {
+            let planned = planned_updates.entry(record_num).or_default();
+            for (id, data) in new_values {
+                // only follow the values which were not already planned, so that cycles end
+                match planned.iter_mut().find(|(planned_id, _)| planned_id == &id) {
+                    Some((_, planned_data)) if *planned_data == data => continue,
+                    Some((_, planned_data)) => *planned_data = data.clone(),
+                    None => planned.push((id.clone(), data.clone())),
+                }
+
+                if let Some(key_num) = key_num {
+                    if table.attributes()[key_num].0 == id {
+                        let prev_key = table.read_record(record_num)?.swap_remove(key_num);
+                        if prev_key != data {
+                            key_changes.push((prev_key, data));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (prev_key, key) in key_changes {
+            for (child_name, attri_num, reference) in referencing(db, table_name) {
+                let child_nums = self.referencing_records(
+                    db,
+                    child_name,
+                    attri_num,
+                    std::slice::from_ref(&prev_key),
+                )?;
+                if child_nums.is_empty() {
+                    continue;
+                }
+                match reference.on_update {
+                    RefAction::Restrict => Err(DBError::ConstraintError(
+                        "Cannot change the key of a record which another table references.",
+                    ))?,
+                    RefAction::Cascade => {
+                        self.set(db, child_name, child_nums, attri_num, key.clone())?
+                    }
+                    RefAction::SetNull => {
+                        self.set(db, child_name, child_nums, attri_num, Data::Null)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Plans to set the attribute at attri_num to data in each record at record_nums of the
+    /// table named table_name.
+    ///
+    /// # Errors
+    ///
+    /// Fails when data is Data::Null for an attribute which is NOT NULL or when planning the update fails.
+    fn set(
+        &mut self,
+        db: &Database,
+        table_name: &str,
+        record_nums: Vec<usize>,
+        attri_num: usize,
+        data: Data,
+    ) -> Result<(), Box<dyn Error>> {
+        let table = get_table(db, table_name)?;
+        if data == Data::Null && table.constraints()[attri_num].not_null {
+            Err(DBError::ConstraintError(
+                "Cannot SET NULL an attribute which is NOT NULL.",
+            ))?
+        }
+        let id = table.attributes()[attri_num].0.clone();
+        let updates = record_nums
+            .into_iter()
+            .map(|record_num| (record_num, vec![(id.clone(), data.clone())]))
+            .collect();
+        self.update(db, table_name, updates)
+    }
+
+    /// Returns the record numbers of the records in the table named table_name whose attribute at
+    /// attri_num holds one of keys, leaving out any that are already planned to be deleted.
+    /// Uses a secondary index on the attribute if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the table or its index cannot be read.
+    fn referencing_records(
+        &self,
+        db: &Database,
+        table_name: &str,
+        attri_num: usize,
+        keys: &[Data],
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let table = get_table(db, table_name)?;
+        let mut record_nums = match table.secondary_index_on(attri_num) {
+            Some(index) => {
+                let mut record_nums = Vec::new();
+                for key in keys {
+                    record_nums.append(&mut index.find_all(&Key::from(key.clone()))?);
+                }
+                record_nums
+            }
+            None => MemTable::build(table)?
+                .records
+                .iter()
+                .enumerate()
+                .filter(|(_, record)| keys.contains(&record[attri_num]))
+                .map(|(record_num, _)| record_num)
+                .collect(),
+        };
+
+        if let Some(deletes) = self.deletes.get(table_name) {
+            record_nums.retain(|record_num| !deletes.contains(record_num));
+        }
+        Ok(record_nums)
+    }
+
+    /// Applies the planned updates and then the planned deletes to each table. When more than
+    /// one table or statement is involved outside of a transaction, they are all done inside of
+    /// one so that they go through the write-ahead log as a single entry.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a table rejects its changes or when the files cannot be written to.
+    /// Nothing is changed in that case. Inside of a transaction which was already open,
+    /// run_cmd throws away the changes made before the failure.
+    fn apply(self, db: &mut Database) -> Result<(), Box<dyn Error>> {
+        let change_count = self.updates.values().filter(|u| !u.is_empty()).count()
+            + self.deletes.values().filter(|d| !d.is_empty()).count();
+        let implicit_transaction = !db.in_transaction && change_count > 1;
+        if implicit_transaction {
+            begin(db);
+        }
+
+        let result = self.apply_changes(db);
+
+        if implicit_transaction {
+            match result {
+                Ok(()) => commit(db)?,
+                Err(_) => rollback(db)?,
+            }
+        }
+        result
+    }
+
+    /// Applies the planned updates and then the planned deletes to each table, since
+    /// deleting records moves others to new record numbers.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a table rejects its changes or when the files cannot be written to.
+    fn apply_changes(self, db: &mut Database) -> Result<(), Box<dyn Error>> {
+        let Plan { deletes, updates } = self;
+        for (table_name, updates) in updates {
+            let deletes = deletes.get(&table_name);
+            let updates: Vec<RecordUpdate> = updates
+                .into_iter()
+                .filter(|(record_num, _)| !deletes.is_some_and(|d| d.contains(record_num)))
+                .collect();
+            if !updates.is_empty() {
+                get_table_mut(db, &table_name)?.update_all(updates)?;
+            }
+        }
+        for (table_name, deletes) in deletes {
+            get_table_mut(db, &table_name)?.delete_all(deletes.into_iter().collect())?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the table named table_name.
+///
+/// # Errors
+///
+/// Fails when there is no table with that name.
+fn get_table<'a>(db: &'a Database, table_name: &str) -> Result<&'a Table, DBError> {
+    db.table_map
+        .get(table_name)
+        .ok_or(DBError::ParseError("No table found with the given name."))
+}
+
+/// Returns the table named table_name as mutable.
+///
+/// # Errors
+///
+/// Fails when there is no table with that name.
+fn get_table_mut<'a>(db: &'a mut Database, table_name: &str) -> Result<&'a mut Table, DBError> {
+    db.table_map
+        .get_mut(table_name)
+        .ok_or(DBError::ParseError("No table found with the given name."))
+}
+
+/// Returns the name of each table with an attribute that REFERENCES the table named table_name,
+/// along with the number of that attribute and its constraint.
+fn referencing<'a>(
+    db: &'a Database,
+    table_name: &'a str,
+) -> impl Iterator<Item = (&'a str, usize, &'a Reference)> {
+    db.table_map.iter().flat_map(move |(child_name, child)| {
+        child
+            .constraints()
+            .iter()
+            .enumerate()
+            .filter_map(
+                move |(attri_num, constraints)| match constraints.references {
+                    Some(ref reference) if reference.table.name() == table_name => {
+                        Some((&child_name[..], attri_num, reference))
+                    }
+                    _ => None,
+                },
+            )
+    })
+}
+
+/// Returns true if a table other than the one named table_name REFERENCES it.
+pub fn is_referenced(db: &Database, table_name: &str) -> bool {
+    referencing(db, table_name).any(|(child_name, _, _)| child_name != table_name)
+}
+
+/// Checks that each value given for an attribute of the table named table_name is either
+/// Data::Null or the key of a record in the table that the attribute REFERENCES, if any.
+/// Values are given with the numbers of their attributes.
+///
+/// # Errors
+///
+/// Fails when a value is not the key of any record in the referenced table or when an index cannot be read.
+pub fn check_references<'a>(
+    db: &Database,
+    table_name: &str,
+    values: impl Iterator<Item = (usize, &'a Data)>,
+) -> Result<(), Box<dyn Error>> {
+    let table = get_table(db, table_name)?;
+    for (attri_num, data) in values {
+        let reference = match table.constraints()[attri_num].references {
+            Some(ref reference) => reference,
+            None => continue,
+        };
+        if *data == Data::Null {
+            continue;
+        }
+
+        let parent_index = db
+            .table_map
+            .get(reference.table.name())
+            .and_then(|parent| parent.index.as_ref());
+        let found = match parent_index {
+            Some(index) => index.find(&Key::from(data.clone()))?.is_some(),
+            None => false,
+        };
+        if !found {
+            Err(DBError::ConstraintError(
+                "Cannot reference a key which is not in the referenced table.",
+            ))?
+        }
+    }
+    Ok(())
+}
+
+/// Updates every record in the table named table_name that matches cond with new_values,
+/// following the ON UPDATE action of each record that references a record whose key changes.
+///
+/// # Errors
+///
+/// Fails when the new values break a constraint, including a REFERENCES constraint, when a
+/// referencing record RESTRICTs the update, or when the files cannot be read from or written to.
+pub fn update(
+    db: &mut Database,
+    table_name: &str,
+    cond: Condition,
+    new_values: Vec<(Identifier, Data)>,
+) -> Result<(), Box<dyn Error>> {
+    let table = get_table(db, table_name)?;
+    let record_nums = cond.filter(table)?;
+    if record_nums.is_empty() {
+        return Ok(());
+    }
+
+    let attri_values = new_values.iter().filter_map(|(id, data)| {
+        let attri_num = table
+            .attributes()
+            .iter()
+            .position(|(attribute, _)| attribute == id)?;
+        Some((attri_num, data))
+    });
+    check_references(db, table_name, attri_values)?;
+
+    let mut plan = Plan::default();
+    let updates = record_nums
+        .into_iter()
+        .map(|record_num| (record_num, new_values.clone()))
+        .collect();
+    plan.update(db, table_name, updates)?;
+    plan.apply(db)
+}
+
+/// Deletes every record in the table named table_name that matches cond, following the
+/// ON DELETE action of each record that references one of them.
+///
+/// # Errors
+///
+/// Fails when a referencing record RESTRICTs the delete, when a referencing attribute cannot
+/// be SET NULL, or when the files cannot be read from or written to.
+pub fn delete(db: &mut Database, table_name: &str, cond: Condition) -> Result<(), Box<dyn Error>> {
+    let record_nums = cond.filter(get_table(db, table_name)?)?;
+    let mut plan = Plan::default();
+    plan.delete(db, table_name, record_nums)?;
+    plan.apply(db)
+}
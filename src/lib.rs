@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::Display;
 use std::fs;
+use wal::Wal;
 
 // public exports for the only commands neccessary to run all commands
 pub use db_cmds::run_cmd;
@@ -10,16 +11,26 @@ pub use db_cmds::run_exit;
 // bring all error types into the same scope
 pub use binary_search_tree::BSTInsertErr;
 
+/// Contains the aggregate functions, such as COUNT and SUM, which SELECT computes across records
+mod aggregate;
+/// Contains the paged B+tree that stores each table's indexes on disk
+mod b_plus_tree;
 /// Contains base types which help with data standardization
 mod base;
-/// Contains a Binary Search Tree that uses Data as a key and usize as a data payload
+/// Contains a Binary Search Tree that uses Key as a key and usize as a data payload
 mod binary_search_tree;
 /// Contains functions for all semantic database commands
 mod db_cmds;
 /// Contains code to aid with selecting/updating/deleting from tables
 mod logic;
+/// Contains the lexer and recursive-descent parser which read commands into Statements
+mod parser;
 /// Contains Table and MemTable structs which abstract over interactions with database tables/relations
 mod relation;
+/// Contains the in-memory and external merge sorts that ORDER BY uses
+mod sort;
+/// Contains the write-ahead log that every change to a table file goes through
+mod wal;
 
 /// A master reference to the current database the program is working with
 pub struct Database {
@@ -27,6 +38,14 @@ pub struct Database {
     path: String,
     /// Loaded from all .dat files in the db directory
     table_map: HashMap<String, Table>,
+    /// True between BEGIN and COMMIT/ROLLBACK
+    in_transaction: bool,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Database {
@@ -35,11 +54,14 @@ impl Database {
         Database {
             path: String::new(),
             table_map: HashMap::new(),
+            in_transaction: false,
         }
     }
 
     /// Creates a new Database with the given path and attempts to load
-    /// all .dat files in as tables.
+    /// all .dat files in as tables. Any writes left in the database's
+    /// write-ahead log by a crash are replayed first and any uncommitted
+    /// transaction is thrown away.
     ///
     /// # Errors
     ///
@@ -49,6 +71,10 @@ impl Database {
     pub fn build(path: String) -> Result<Self, Box<dyn Error>> {
         let mut table_map = HashMap::new();
 
+        // finish any writes that were interrupted before looking at the table files
+        let wal = Wal::open(&path);
+        let recovered = wal.replay()?;
+
         let db_files = match fs::read_dir(&path) {
             Ok(read_dir) => read_dir,
             Err(_) => {
@@ -70,6 +96,13 @@ impl Database {
                 .rsplit_once(".")
                 .expect("File name should have a dot separated identifier.");
 
+            // shadow copies left behind by a transaction that never committed
+            // and runs left behind by an external merge sort that never finished
+            if let (_, "txn" | "sort") = file_name_split {
+                fs::remove_file(file.path())?;
+                continue;
+            }
+
             // if file is a table file
             if let (table_name, "dat") = file_name_split {
                 table_map.insert(
@@ -79,7 +112,16 @@ impl Database {
             }
         }
 
-        Ok(Database { path, table_map })
+        if recovered {
+            // the replayed records and index pages are on disk now
+            wal.checkpoint()?;
+        }
+
+        Ok(Database {
+            path,
+            table_map,
+            in_transaction: false,
+        })
     }
 }
 
@@ -88,6 +130,8 @@ impl Database {
 pub enum DBError {
     /// Most common DBError variant since this is a blanket for all invalid syntax
     ParseError(&'static str),
+    /// A ParseError which knows the place in its command where it was found
+    SyntaxError(Box<SyntaxError>),
     /// Constraint errors most commonly come from mismatching domains in Conditions
     ConstraintError(&'static str),
     /// Rarely used since most file related errors are treated as full-stop issues
@@ -97,8 +141,9 @@ pub enum DBError {
 impl Display for DBError {
     /// Adds some default prefixes to DBErrors
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
+        match self {
             DBError::ParseError(s) => write!(f, "Failed to parse a line: {s}"),
+            DBError::SyntaxError(err) => write!(f, "{err}"),
             DBError::ConstraintError(s) => write!(f, "Invalid value given for a type: {s}"),
             DBError::FileFormatError(s) => write!(f, "Incorrect file format: {s}"),
         }
@@ -108,12 +153,94 @@ impl Display for DBError {
 // semantic extension of Display Trait
 impl Error for DBError {}
 
+/// A ParseError along with the token of its command which could not be parsed. Once the text
+/// the command came from is known, the statement number, line, and column are filled in as well.
+#[derive(Debug)]
+pub struct SyntaxError {
+    message: &'static str,
+    /// The byte offset in the command of the unexpected token, or of the end of the command
+    start: usize,
+    /// The unexpected token as it was written, or None when the command ended too early
+    found: Option<String>,
+    location: Option<Location>,
+}
+
+/// Where a SyntaxError was found in an INPUT file or in the lines typed into the program.
+#[derive(Debug)]
+struct Location {
+    /// The name of the INPUT file, or None for typed lines
+    file_name: Option<String>,
+    statement: usize,
+    line: usize,
+    column: usize,
+    /// The text of the line the error was found on
+    line_text: String,
+    /// The number of chars to underline in line_text, starting from column
+    width: usize,
+}
+
+impl SyntaxError {
+    /// Creates a SyntaxError for the token found at the byte offset start of a command,
+    /// where found is None when the command ended too early.
+    pub fn new(message: &'static str, start: usize, found: Option<String>) -> Self {
+        SyntaxError {
+            message,
+            start,
+            found,
+            location: None,
+        }
+    }
+
+    /// Returns the line the error was found on and a line which underlines the unexpected
+    /// token with carets, or None when the text the command came from is not known.
+    pub fn excerpt(&self) -> Option<(String, String)> {
+        let location = self.location.as_ref()?;
+        // copy tabs so that the carets line up with the token however tabs are shown
+        let mut carets: String = location
+            .line_text
+            .chars()
+            .take(location.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        carets.push_str(&"^".repeat(location.width));
+        Some((location.line_text.clone(), carets))
+    }
+}
+
+impl Display for SyntaxError {
+    /// Gives the message along with where the error was found and the unexpected token
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            Some(ref location) => {
+                write!(
+                    f,
+                    "Failed to parse statement {} at line {}, column {}",
+                    location.statement, location.line, location.column
+                )?;
+                if let Some(ref file_name) = location.file_name {
+                    write!(f, " of {file_name}")?;
+                }
+            }
+            None => write!(f, "Failed to parse a line")?,
+        }
+        match self.found {
+            Some(ref found) => write!(f, ": {} Found '{found}'.", self.message),
+            None => write!(f, ": {} Found the end of the command.", self.message),
+        }
+    }
+}
+
 /// An Iterator that returns semicolon terminated statements in a &str.
 /// Allows for comments that start with '#' and end with '\n'.
 pub struct CmdIterator<'a> {
     pos: usize,
     text: &'a str,
     cmd: String,
+    /// The number of statements returned so far
+    count: usize,
+    /// The byte offsets in the last statement returned paired with the byte offsets in text
+    /// that they were copied from, one pair for each piece of text between comments
+    segments: Vec<(usize, usize)>,
 }
 
 impl<'a> CmdIterator<'a> {
@@ -126,6 +253,80 @@ impl<'a> CmdIterator<'a> {
             pos: 0,
             text,
             cmd: String::from(""),
+            count: 0,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Returns the number of statements returned so far, which is the number of the last one.
+    pub fn statement_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the line and the column (in chars) in the text, both counted from 1, of the
+    /// byte offset cmd_offset in the last statement returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mini_dbms::CmdIterator;
+    /// let mut cmd_iter = CmdIterator::over("one;\n# comment\n  two\n three;");
+    /// cmd_iter.next();
+    /// let two = cmd_iter.next().unwrap();
+    /// assert_eq!(cmd_iter.line_col(two.find("three").unwrap()), (4, 2));
+    /// assert_eq!(cmd_iter.statement_count(), 2);
+    /// ```
+    pub fn line_col(&self, cmd_offset: usize) -> (usize, usize) {
+        let offset = self.text_offset(cmd_offset);
+        let line_start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = self.text[..line_start].matches('\n').count() + 1;
+        (line, self.text[line_start..offset].chars().count() + 1)
+    }
+
+    /// Fills in where the last statement returned is in the text when err is a SyntaxError
+    /// found in it which does not know yet. file_name is the name of the file the text was read
+    /// from, if it was read from one.
+    pub fn locate(&self, err: &mut Box<dyn Error>, file_name: Option<&str>) {
+        let syntax_error = match err.downcast_mut::<DBError>() {
+            Some(DBError::SyntaxError(syntax_error)) if syntax_error.location.is_none() => {
+                syntax_error
+            }
+            _ => return,
+        };
+        let (line, column) = self.line_col(syntax_error.start);
+        let offset = self.text_offset(syntax_error.start);
+        let line_start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_text = self.text[line_start..]
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        // underline the token, or the semicolon when the statement ended too early
+        let width = match syntax_error.found {
+            Some(ref found) => found.lines().next().unwrap_or_default().chars().count(),
+            None => 1,
+        };
+        syntax_error.location = Some(Location {
+            file_name: file_name.map(String::from),
+            statement: self.count,
+            line,
+            column,
+            line_text,
+            width: width.max(1),
+        });
+    }
+
+    /// Returns the byte offset in the text that cmd_offset in the last statement returned was
+    /// copied from.
+    fn text_offset(&self, cmd_offset: usize) -> usize {
+        match self
+            .segments
+            .iter()
+            .rev()
+            .find(|(segment_start, _)| *segment_start <= cmd_offset)
+        {
+            Some((segment_start, text_start)) => text_start + cmd_offset - segment_start,
+            None => self.pos,
         }
     }
 }
@@ -135,28 +336,39 @@ impl<'a> Iterator for CmdIterator<'a> {
 
     /// Returns the next semicolon terminated String found in self.text.
     /// Iterator ignores comments starting with '#' and ending with '\n'.
-    /// Iterator also ignores semicolon terminators inside a double quoted sub-string.
+    /// Iterator also ignores semicolon terminators inside a double quoted sub-string,
+    /// which a backslash escaped double quote does not end.
     /// Does not trim whitespace from returned Strings.
     ///
     /// # Examples
     ///
     /// ```
-    /// use dbms::CmdIterator;
-    /// let mut cmd_iter = CmdIterator::over("one \" ; \"; two # comment \n; three;");
+    /// use mini_dbms::CmdIterator;
+    /// let mut cmd_iter = CmdIterator::over("one \" ; \"; two # comment \n; three \" \\\" ; \";");
     /// assert_eq!(cmd_iter.next(), Some(String::from("one \" ; \"")));
-    /// assert_eq!(cmd_iter.next(), Some(String::from(" two \n")));
-    /// assert_eq!(cmd_iter.next(), Some(String::from(" three")));
+    /// assert_eq!(cmd_iter.next(), Some(String::from(" two  ")));
+    /// assert_eq!(cmd_iter.next(), Some(String::from(" three \" \\\" ; \"")));
     /// assert_eq!(cmd_iter.next(), None);
     /// ```
     fn next(&mut self) -> Option<Self::Item> {
         let mut double_quotes = false; // is true when waiting for an end double quote
+        let mut escaped = false; // is true when the last char was a backslash in double quotes
         let mut comment = false; // is true when waiting for the end of a comment
         let range = &self.text[self.pos..];
-        let mut since_last_push = 0; // number of chars traveled in self.text since last push to self.cmd
+        let mut since_last_push = 0; // number of bytes traveled in self.text since last push to self.cmd
+        if self.cmd.is_empty() {
+            self.segments.clear();
+        }
         for c in range.chars() {
+            // skip an escaped char
+            if escaped {
+                escaped = false;
+            } else if c == '\\' && double_quotes && !comment {
+                escaped = true;
             // start a comment
-            if c == '#' && !double_quotes && !comment {
+            } else if c == '#' && !double_quotes && !comment {
                 comment = true;
+                self.segments.push((self.cmd.len(), self.pos));
                 self.cmd
                     .push_str(&self.text[self.pos..self.pos + since_last_push]);
             // end a comment
@@ -169,18 +381,90 @@ impl<'a> Iterator for CmdIterator<'a> {
                 double_quotes = !double_quotes;
             // end a command
             } else if c == ';' && !double_quotes && !comment {
+                self.segments.push((self.cmd.len(), self.pos));
                 self.cmd.push_str(
                     &self.text[self.pos..self.pos + since_last_push] // replacements are necessary for parsing ease without Regex
-                        .replace('\r', " ") // for windows -- this and next line can be combined if guaranteed on windows
-                        .replace('\n', " "), // for mac and windows
+                        .replace(['\r', '\n'], " "), // for mac and windows
                 );
                 self.pos += since_last_push + 1; // +1 to ignore the semicolon
+                self.count += 1;
                 let cmd = std::mem::take(&mut self.cmd);
                 return Some(cmd);
             }
-            since_last_push += 1; // not equivalent to a count of the loop since is set to 0 sometimes
+            since_last_push += c.len_utf8(); // not equivalent to a count of the loop since is set to 0 sometimes
         }
 
         None // no more chars in self.text past self.pos
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses each statement in text, returning the error of the first which fails after
+    /// it is located in text.
+    fn first_error(text: &str) -> Box<dyn Error> {
+        let mut cmds = CmdIterator::over(text);
+        while let Some(cmd) = cmds.next() {
+            if let Err(err) = parser::parse_statement(&cmd) {
+                let mut err: Box<dyn Error> = Box::new(err);
+                cmds.locate(&mut err, Some("cmds.txt"));
+                return err;
+            }
+        }
+        panic!("every statement parsed");
+    }
+
+    /// Returns the SyntaxError in err along with where it was located.
+    fn located<'a>(err: &'a (dyn Error + 'static)) -> (&'a SyntaxError, &'a Location) {
+        match err.downcast_ref::<DBError>() {
+            Some(DBError::SyntaxError(syntax_error)) => {
+                (syntax_error, syntax_error.location.as_ref().unwrap())
+            }
+            _ => panic!("{err} is not a SyntaxError"),
+        }
+    }
+
+    #[test]
+    fn syntax_errors_point_into_input_files() {
+        let text = "CREATE TABLE t (id INTEGER PRIMARY KEY);\n\
+            # a comment; which does not end a statement\n\
+            SELECT id\n\
+            \tFROM t # and one which ends a line\n\
+            \tWHERE id = = 3;\n\
+            SELECT id FROM t;\n";
+        let err = first_error(text);
+        let (syntax_error, location) = located(err.as_ref());
+        assert_eq!(location.file_name.as_deref(), Some("cmds.txt"));
+        assert_eq!(location.statement, 2);
+        assert_eq!((location.line, location.column), (5, 13));
+        assert_eq!(location.line_text, "\tWHERE id = = 3;");
+        assert_eq!(location.width, 1);
+        assert_eq!(syntax_error.found.as_deref(), Some("="));
+        let (line, carets) = syntax_error.excerpt().unwrap();
+        assert_eq!(line, location.line_text);
+        assert_eq!(carets, format!("\t{}^", " ".repeat(11)));
+        assert!(err
+            .to_string()
+            .contains("statement 2 at line 5, column 13 of cmds.txt"));
+
+        // a statement which ends too early points at its semicolon
+        let err = first_error("SELECT id\nFROM t\nWHERE;");
+        let (syntax_error, location) = located(err.as_ref());
+        assert_eq!(
+            (location.statement, location.line, location.column),
+            (1, 3, 6)
+        );
+        assert_eq!(syntax_error.found, None);
+        assert!(err.to_string().ends_with("Found the end of the command."));
+    }
+
+    #[test]
+    fn located_errors_count_chars_not_bytes() {
+        let err = first_error("INSERT t VALUES (\"héllo wörld\", 2 3);");
+        let (syntax_error, location) = located(err.as_ref());
+        assert_eq!((location.line, location.column), (1, 35));
+        assert_eq!(syntax_error.found.as_deref(), Some("3"));
+    }
+}
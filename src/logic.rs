@@ -1,66 +1,94 @@
-use super::base::{Data, Domain, Float, Identifier, Integer, Text};
+use super::base::{
+    BigInt, Boolean, Data, Date, Decimal, Domain, Double, Identifier, Integer, Key, Timestamp,
+    MAX_DECIMAL_PRECISION,
+};
 use super::relation::{MemTable, Table};
-use crate::binary_search_tree::BST;
+use crate::parser;
+use crate::sort::{SortKey, Sorter, SORT_MEMORY_LIMIT};
 use crate::DBError;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::error::Error;
+use std::ops::Bound;
 use std::vec;
 
+/// A record read from a table along with its record number
+type NumberedRecord = (usize, Vec<Data>);
+
 /// A representation of the AND and OR logical operators
 #[derive(Copy, Clone, PartialEq)]
-enum LogOp {
+pub enum LogOp {
     And,
     Or,
 }
 
 /// An abstraction over actual constraints and parenthesis enclosed groups of constraints (Conditions)
-enum BoolEval {
+pub enum BoolEval {
     Constraint(Constraint),
     Condition(Condition),
 }
 
 /// An abstraction over value operands and variable ones that come from tables
 /// The Identifier variant is a place holder to be converted to Attribute when a Table is specified
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Operand {
     Identifier(Identifier), // an identifier which has not been converted to an Attribute yet
     Attribute((usize, usize)), // coordinates in the joined_record (table, attri_num)
-    Value(Data),            // int, float, text (with certain extra restrictions from parsing)
+    Value(Data), // int, float, text (with certain extra restrictions from parsing), boolean, date, timestamp, or null
 }
 
 impl Operand {
-    /// Attempts to parse an operand out of a string reference. Reads integer first so '3' will map to Integer always
-    /// and '3.' will map to a Float
+    /// Attempts to parse an operand out of a string reference. Reads integer first so '3' will map to Integer always,
+    /// integers too large for an Integer will map to BigInt, '3.5' will map to an exact Decimal, and '3e5' will map to a Double. 'null' maps to Data::Null and 'true' and 'false' to Data::Boolean.
+    /// ISO-8601 dates and timestamps map to Data::Date and Data::Timestamp.
     ///
     /// # Errors
     ///
-    /// Fails when not an integer, float, or string (double-quoted) value in the string reference.
-    fn parse(op: &str) -> Result<Operand, DBError> {
-        if op.starts_with('"') && op.ends_with('"') && op.len() > 1 && op.len() < 33 {
-            // 0 to 30 characters in stringConst
-            return Ok(Operand::Value(Data::Text(
-                Text::from(&op[1..op.len() - 1]).unwrap(),
-            )));
+    /// Fails when not an integer, float, boolean, date, timestamp, null value, or Identifier in the string reference.
+    /// String literals are read by the parser, which makes them Text values.
+    pub fn parse(op: &str) -> Result<Operand, DBError> {
+        if op == "null" {
+            return Ok(Operand::Value(Data::Null));
+        }
+
+        if let Ok(boolean) = Boolean::from(op) {
+            return Ok(Operand::Value(Data::Boolean(boolean)));
         }
 
         if let Ok(int) = op.parse::<i32>() {
             return Ok(Operand::Value(Data::Integer(Integer::wrap(int))));
         }
 
-        if let Ok(float) = op.parse::<f64>() {
-            return Ok(Operand::Value(Data::Float(Float::wrap(float)))); // gotta love wrapper types
+        if let Ok(int) = op.parse::<i64>() {
+            return Ok(Operand::Value(Data::BigInt(BigInt::wrap(int))));
+        }
+
+        if let Ok(decimal) = Decimal::from(op) {
+            return Ok(Operand::Value(Data::Decimal(decimal)));
+        }
+
+        if let Ok(double) = Double::from(op) {
+            return Ok(Operand::Value(Data::Double(double))); // gotta love wrapper types
+        }
+
+        if let Ok(date) = Date::from(op) {
+            return Ok(Operand::Value(Data::Date(date)));
+        }
+
+        if let Ok(timestamp) = Timestamp::from(op) {
+            return Ok(Operand::Value(Data::Timestamp(timestamp)));
         }
 
         if let Ok(identifier) = Identifier::from(op) {
             return Ok(Operand::Identifier(identifier));
         }
 
-        return Err(DBError::ParseError("Could not parse Operand."));
+        Err(DBError::ParseError("Could not parse Operand."))
     }
 }
 
 /// Represents the left, right, and relative operator for any constraint.
-/// In a string this looks like 'attribute_name > 5'.
+/// In a string this looks like 'attribute_name > 5' or 'attribute_name IS NULL'.
 pub struct Constraint {
     pub left_op: Operand,
     pub rel_op: RelOp,
@@ -68,43 +96,16 @@ pub struct Constraint {
 }
 
 impl Constraint {
-    /// Attempts to read a Constraint out of the front of a string reference.
-    /// Returns a result with the constraint read and the rest of the string if Ok variant.
-    ///
-    /// # Errors
-    ///
-    /// Fails when a full Constraint cannot be read out of the string.
-    pub fn parse_split(prop: &str) -> Result<(Self, &str), Box<dyn Error>> {
-        let prop = prop.trim();
-
-        let (left_op, prop) = split_word(prop);
-        let prop = prop.trim_start();
-        let (rel_op, prop) = split_rel_op(prop)?;
-        let prop = prop.trim_start();
-        let (right_op, prop) = split_word(prop);
-
-        let left_op = Operand::parse(left_op)?;
-        let right_op = Operand::parse(right_op)?;
-
-        Ok((
-            Constraint {
-                left_op,
-                rel_op,
-                right_op,
-            },
-            prop,
-        ))
-    }
-
     /// Evaluates a Constraint after conversion with a specific table. Returns
-    /// true if the condition is true for the given joined_record.
+    /// whether the condition is true for the given joined_record, or None when it
+    /// is unknown because a value being compared is Data::Null.
     /// Note this is a joined record since a condition is made to work
     /// over a cartesian product of multiple tables.
     ///
     /// # Panics
     ///
     /// Panics when two incompatible data types are asked to be compared.
-    fn eval(&self, joined_record: &Vec<&Vec<Data>>) -> bool {
+    fn eval(&self, joined_record: &[&Vec<Data>]) -> Option<bool> {
         let left_data = match self.left_op {
             Operand::Value(ref data) => data,
             Operand::Attribute((table, attri)) => &joined_record[table][attri],
@@ -121,11 +122,31 @@ impl Constraint {
             ),
         };
 
+        // IS and IS NOT only ask whether a value is missing, so they are never unknown
+        if let RelOp::Is | RelOp::IsNot = self.rel_op {
+            return Some(
+                self.rel_op
+                    .cmp(left_data == &Data::Null, right_data == &Data::Null),
+            );
+        }
+
         match (left_data, right_data) {
-            (Data::Float(f1), Data::Float(f2)) => self.rel_op.cmp(f1.value(), f2.value()),
-            (Data::Integer(i1), Data::Integer(i2)) => self.rel_op.cmp(i1.value(), i2.value()),
-            (Data::Text(t1), Data::Text(t2)) => self.rel_op.cmp(t1.content(), t2.content()),
-            _ => panic!("Incompatible data types cannot be compared"), // Errors of this type should be found during the parsing of commands
+            (Data::Double(d1), Data::Double(d2)) => Some(self.rel_op.cmp(d1.value(), d2.value())),
+            (Data::Decimal(d1), Data::Decimal(d2)) => {
+                Some(self.rel_op.cmp(d1.cmp(d2), Ordering::Equal))
+            }
+            (Data::Integer(i1), Data::Integer(i2)) => Some(self.rel_op.cmp(i1.value(), i2.value())),
+            (Data::BigInt(i1), Data::BigInt(i2)) => Some(self.rel_op.cmp(i1.value(), i2.value())),
+            (Data::Text(t1), Data::Text(t2)) => Some(self.rel_op.cmp(t1.content(), t2.content())),
+            (Data::Boolean(b1), Data::Boolean(b2)) => Some(self.rel_op.cmp(b1.value(), b2.value())),
+            (Data::Date(d1), Data::Date(d2)) => Some(self.rel_op.cmp(d1.value(), d2.value())),
+            (Data::Timestamp(t1), Data::Timestamp(t2)) => {
+                Some(self.rel_op.cmp(t1.value(), t2.value()))
+            }
+            (Data::Null, _) | (_, Data::Null) => None,
+            // numbers of different Domains, or incompatible data types which Data::cmp panics on.
+            // Errors of that type should be found during the parsing of commands
+            _ => Some(self.rel_op.cmp(left_data.cmp(right_data), Ordering::Equal)),
         }
     }
 
@@ -135,7 +156,7 @@ impl Constraint {
     /// # Errors
     ///
     /// Fails if an Operand cannot change types.
-    pub fn convert_with(&mut self, tables: &Vec<&Table>) -> Result<(), Box<dyn Error>> {
+    pub fn convert_with(&mut self, tables: &[&Table]) -> Result<(), Box<dyn Error>> {
         'outer: {
             if let Operand::Identifier(id) = &self.left_op {
                 for (i, table) in tables.iter().enumerate() {
@@ -164,21 +185,48 @@ impl Constraint {
             }
         }
 
-        match (&self.left_op, &self.right_op) {
+        // keep a value on the right so that it only has to be checked one way around
+        if let (Operand::Value(_), Operand::Attribute(_)) = (&self.left_op, &self.right_op) {
+            std::mem::swap(&mut self.left_op, &mut self.right_op);
+            self.rel_op = self.rel_op.flip();
+        }
+
+        match (&self.left_op, &mut self.right_op) {
             (Operand::Attribute((i1,j1)), Operand::Attribute((i2, j2))) => {
-                if tables[*i1].attributes()[*j1].1 != tables[*i2].attributes()[*j2].1 {
+                if !tables[*i1].attributes()[*j1].1.is_comparable_with(&tables[*i2].attributes()[*j2].1) {
                     return Err(Box::new(DBError::ConstraintError("Attributes with incompatible Domains cannot be compared.")))
                 }
                 Ok(())
             }
-            (Operand::Attribute((i, j)), Operand::Value(value))|
-            (Operand::Value(value), Operand::Attribute((i, j))) => {
+            (Operand::Attribute((i, j)), Operand::Value(value)) => {
+                let attri_domain = tables[*i].attributes()[*j].1;
+                // a string literal or date stands for a Date or Timestamp when compared with one,
+                // a decimal literal for a Double, and an integer literal for a BigInt. Other numbers
+                // are compared with the attribute's values as they are
+                let converted_value = match (&*value, attri_domain) {
+                    (Data::Text(text), Domain::Date) => Some(Data::Date(Date::from(text.content())?)),
+                    (Data::Text(text), Domain::Timestamp) => Some(Data::Timestamp(Timestamp::from(text.content())?)),
+                    (Data::Date(date), Domain::Timestamp) => Some(Data::Timestamp(Timestamp::from_date(date))),
+                    (Data::Decimal(decimal), Domain::Double) => Some(Data::Double(Double::wrap(decimal.to_f64()))),
+                    (Data::Integer(int), Domain::BigInt) => Some(Data::BigInt(BigInt::from_integer(int))),
+                    _ => None,
+                };
+                if let Some(converted_value) = converted_value {
+                    *value = converted_value;
+                }
+
                 let value_domain = match value {
-                    Data::Float(_) => Domain::Float,
+                    Data::Double(_) => Domain::Double,
+                    Data::Decimal(_) => Domain::Decimal(MAX_DECIMAL_PRECISION, 0), // comparable with every Decimal
                     Data::Integer(_) => Domain::Integer,
-                    Data::Text(_) => Domain::Text
+                    Data::BigInt(_) => Domain::BigInt,
+                    Data::Text(_) => Domain::Text,
+                    Data::Boolean(_) => Domain::Boolean,
+                    Data::Date(_) => Domain::Date,
+                    Data::Timestamp(_) => Domain::Timestamp,
+                    Data::Null => return Ok(()), // a missing value fits every domain
                 };
-                if tables[*i].attributes()[*j].1 != value_domain {
+                if !attri_domain.is_comparable_with(&value_domain) {
                     Err(DBError::ParseError("Attribute compared with value from incorrect domain."))?
                 }
                 Ok(())
@@ -204,33 +252,61 @@ impl Constraint {
         }
     }
 
-    /// Returns the key value this Constraint contains if it is a key constraint in
-    /// the form 'key_attri = value'
-    fn get_key(&self, tables: &Vec<&Table>) -> Option<Data> {
-        if let Constraint {
-            left_op: Operand::Attribute((i, j)),
-            rel_op: RelOp::Equals,
-            right_op: Operand::Value(data),
-        } = self
-        {
-            if tables[*i].key_attri_num == Some(*j) {
-                return Some(data.clone());
-            }
-        } else if let Constraint {
-            left_op: Operand::Value(data),
-            rel_op: RelOp::Equals,
-            right_op: Operand::Attribute((i, j)),
-        } = self
-        {
-            if tables[*i].key_attri_num == Some(*j) {
-                return Some(data.clone());
+    /// Returns the attribute coordinates (table, attri_num) and the value of this Constraint
+    /// if it is in the form 'attri = value'
+    fn get_equality(&self) -> Option<(usize, usize, &Data)> {
+        match self {
+            Constraint {
+                left_op: Operand::Attribute((i, j)),
+                rel_op: RelOp::Equals,
+                right_op: Operand::Value(data),
             }
+            | Constraint {
+                left_op: Operand::Value(data),
+                rel_op: RelOp::Equals,
+                right_op: Operand::Attribute((i, j)),
+            } => Some((*i, *j, data)),
+            _ => None,
         }
+    }
 
-        None
+    /// Returns the attribute coordinates (table, attri_num) of this Constraint along with the
+    /// lower and upper bounds it puts on that attribute if it compares the attribute with a value.
+    /// Returns None for '!=' since it does not bound the attribute.
+    fn get_bounds(&self) -> Option<(usize, usize, Bound<&Data>, Bound<&Data>)> {
+        // 'value < attri' bounds the attribute the same way as 'attri > value'
+        let (i, j, rel_op, data) = match (&self.left_op, &self.right_op) {
+            (Operand::Attribute((i, j)), Operand::Value(data)) => (*i, *j, self.rel_op, data),
+            (Operand::Value(data), Operand::Attribute((i, j))) => {
+                (*i, *j, self.rel_op.flip(), data)
+            }
+            _ => return None,
+        };
+
+        let (lower, upper) = match rel_op {
+            RelOp::Equals => (Bound::Included(data), Bound::Included(data)),
+            RelOp::GreaterThan => (Bound::Excluded(data), Bound::Unbounded),
+            RelOp::GreaterThanOrEqual => (Bound::Included(data), Bound::Unbounded),
+            RelOp::LessThan => (Bound::Unbounded, Bound::Excluded(data)),
+            RelOp::LessThanOrEqual => (Bound::Unbounded, Bound::Included(data)),
+            RelOp::NotEqual | RelOp::Is | RelOp::IsNot => return None,
+        };
+        Some((i, j, lower, upper))
     }
 }
 
+/// An attribute named in an ORDER BY clause and whether the selection is sorted by it in descending order.
+pub struct OrderBy {
+    pub attribute: Identifier,
+    pub descending: bool,
+}
+
+/// The number of records a LIMIT clause keeps and the number of records its OFFSET skips before them.
+pub struct Limit {
+    pub count: usize,
+    pub offset: usize,
+}
+
 /// An wrapper for a list of alternating LogOps and BoolEvals. Also, contains all the main methods
 /// useful for selecting, updating, and deleting.
 pub struct Condition {
@@ -238,64 +314,20 @@ pub struct Condition {
 }
 
 impl Condition {
+    /// Creates a Condition out of BoolEvals paired with the LogOp before each of them.
+    /// The first LogOp should always be AND. No BoolEvals makes the Condition which is always true.
+    pub fn new(bool_evals: Vec<(LogOp, BoolEval)>) -> Self {
+        Condition { bool_evals }
+    }
+
     /// Attempts to read a Condition out of a string reference.
     ///
     /// # Errors
     ///
     /// Fails if a Constraint is missing operands or if
     /// there is no valid Logical Operator between Constraints/Conditions
-    pub fn parse(mut cond: &str) -> Result<Self, Box<dyn Error>> {
-        let mut bool_evals: Vec<(LogOp, BoolEval)> = Vec::new();
-
-        // used for when where clause is omitted
-        if cond.is_empty() {
-            return Ok(Condition { bool_evals });
-        }
-
-        // default value depended on later when checking if things are possible to load a table based on them
-        let mut last_log_op = LogOp::And;
-
-        loop {
-            cond = cond.trim_start();
-            // try reading off a chunk in parenthesis - Condition
-            if let Ok((chunk, short_cond)) = split_parenthesis_chunk(cond) {
-                cond = short_cond; // short_cond was the remaining piece of cond
-                bool_evals.push((
-                    last_log_op,
-                    BoolEval::Condition(
-                        Condition::parse(chunk)?, // recursively parse anything in parenthesis
-                    ),
-                ));
-            // try reading off a Constraint
-            } else if let Ok((constraint, short_cond)) = Constraint::parse_split(cond) {
-                cond = short_cond;
-                bool_evals.push((last_log_op, BoolEval::Constraint(constraint)));
-            } else {
-                Err(DBError::ParseError(
-                    "Did not find valid constraint or parenthesis chunk.",
-                ))?
-            }
-
-            cond = cond.trim_start();
-
-            let log_op;
-            (log_op, cond) = split_word(cond);
-
-            last_log_op = if log_op == "and" {
-                LogOp::And
-            } else if log_op == "or" {
-                LogOp::Or
-            } else {
-                cond = cond.trim();
-                if cond.is_empty() {
-                    break;
-                } else {
-                    Err(DBError::ParseError("Did not find valid logical operator."))?
-                }
-            }
-        }
-
-        Ok(Condition { bool_evals })
+    pub fn parse(cond: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(parser::parse_condition(cond)?)
     }
 
     /// Attempts to convert this Condition so that any Identifier variant Operands in its Constraints change to
@@ -304,7 +336,7 @@ impl Condition {
     /// # Errors
     ///
     /// Fails if an Operand cannot change types.
-    fn convert_with(&mut self, tables: &Vec<&Table>) -> Result<(), Box<dyn Error>> {
+    pub fn convert_with(&mut self, tables: &[&Table]) -> Result<(), Box<dyn Error>> {
         for (_, bool_eval) in self.bool_evals.iter_mut() {
             match bool_eval {
                 BoolEval::Condition(cond) => cond.convert_with(tables)?,
@@ -400,293 +432,408 @@ impl Condition {
         }
     }
 
-    /// Returns a list of the record_nums in BST order or uses the BST to search for a key if the
-    /// Condition requires it only be one. Note that this should only be used on Conditions
+    /// Returns a list of the record_nums which could satisfy this Condition in the table at table_num,
+    /// paired with whether they are in the order of the table's primary key.
+    /// Uses the primary key index to search for a key if the Condition requires it only be one.
+    /// Otherwise uses a secondary index to search for the value an indexed attribute is required
+    /// to equal. When neither is possible, returns every record_num, in key order if the table
+    /// has a primary key. Note that this should only be used on Conditions
     /// known to only relate to one Table - for instance those from split_load_helpers().
     ///
+    /// # Errors
+    ///
+    /// Fails when an index cannot be read.
+    ///
     /// # Panics
     ///
-    /// Could panic if called on a Condition that references multiple tables or
-    /// a table that does not own the given bst.
-    fn get_record_nums_from_bst(&mut self, bst: &BST, tables: &Vec<&Table>) -> Vec<usize> {
+    /// Could panic if called on a Condition that references a table other than the one at table_num.
+    fn get_record_nums(
+        &mut self,
+        table_num: usize,
+        tables: &[&Table],
+    ) -> Result<(Vec<usize>, bool), Box<dyn Error>> {
+        let table = tables[table_num];
+        let all_record_nums = || match table.index {
+            Some(ref index) => Ok((index.get_data()?, true)), // at least use key order
+            None => Ok(((0..table.record_count()).collect(), false)),
+        };
+
         for (log_op, _) in self.bool_evals.iter() {
             if *log_op == LogOp::Or {
-                return bst.get_data(); // all are possible
+                return all_record_nums(); // all are possible
             }
         }
 
-        // try to get a single key out of the Condition
-        let mut key: Option<Data> = None;
-
-        // use a while loop since we want to remove the key constraint and be able to adjust for that
-        let mut i = 0;
-        while i < self.bool_evals.len() {
-            let constraint = match &self.bool_evals[i].1 {
-                BoolEval::Condition(_) => {
-                    i += 1;
-                    continue;
-                } // ignore Conditions - don't worry about this recursively.
-                BoolEval::Constraint(constraint) => constraint,
-            };
-            let new_key = match constraint.get_key(tables) {
-                None => {
-                    i += 1;
-                    continue;
-                } // ignore non-key constraints
-                Some(new_key) => new_key,
-            };
-            // remove key constraints
-            self.bool_evals.swap_remove(i);
-            // set key based on the existence of one already and new_key
-            match &key {
-                Some(key) => {
-                    if *key == new_key {
-                        continue;
-                    } else {
-                        return vec![];
-                    }
+        // try to get the values the Condition requires for the key, or at least for a prefix of it
+        if let Some(ref index) = table.index {
+            let mut prefix = Vec::new();
+            for key_num in table.key_attri_nums.iter() {
+                match self.get_equal_value(*key_num) {
+                    Some(data) => prefix.push(data.clone()),
+                    None => break,
                 }
-                None => key = Some(new_key),
+            }
+            if !prefix.is_empty() {
+                return Ok((index.find_all(&Key::new(prefix))?, true));
             }
         }
 
-        match &key {
-            Some(key) => match bst.find(key) {
-                Some(rec_num) => vec![*rec_num],
-                None => vec![],
-            },
-            None => bst.get_data(), // if can't get a key, then at least use BST order
+        // no key to search for so look for an attribute with a secondary index that must equal a value
+        for attri_num in 0..table.attributes().len() {
+            if let (Some(index), Some(data)) = (
+                table.secondary_index_on(attri_num),
+                self.get_equal_value(attri_num),
+            ) {
+                return Ok((index.find_all(&Key::from(data.clone()))?, false));
+            }
         }
-    }
 
-    /// Returns a filtered list of valid table coordinates based on the given Condition.
-    /// Note this should only be used on Conditions known to only relate to one Table.
-    ///
-    /// # Panics
-    ///
-    /// Could panic if given a Condition that relates to more than one table or a condition
-    /// that relates to a table other than the one indicated by bst and table_num
-    pub fn filter_table_coords(
-        mut self,
-        mem_tables: &Vec<MemTable>,
-        table_num: usize,
-        bst: &Option<BST>,
-        tables: &Vec<&Table>,
-    ) -> Vec<usize> {
-        let mut selected: Vec<usize> = Vec::with_capacity(mem_tables[table_num].records.len());
-
-        let records_coords: Vec<usize> = match bst {
-            Some(bst) => self.get_record_nums_from_bst(bst, tables),
-            None => (0..mem_tables[table_num].records.len()).collect(),
-        };
-
-        let binding = vec![]; // this is not the most efficient way to go about doing this for a single table but it works
-        let mut joined_record: Vec<&Vec<Data>> = vec![&binding; mem_tables.len()];
-        for coord in records_coords.into_iter() {
-            joined_record[table_num] = &mem_tables[table_num].records[coord];
-            vec![&mem_tables[table_num].records[coord]];
-            if self.eval(&joined_record) {
-                selected.push(coord);
+        // otherwise only visit the range of keys the Condition allows for the first attribute of an index
+        if let (Some(index), Some(key_num)) = (&table.index, table.key_attri_nums.first()) {
+            if let Some((lower, upper)) = self.get_range(*key_num) {
+                let record_nums =
+                    index.range(key_bound(lower).as_ref(), key_bound(upper).as_ref())?;
+                return Ok((record_nums, true));
+            }
+        }
+        for attri_num in 0..table.attributes().len() {
+            if let (Some(index), Some((lower, upper))) = (
+                table.secondary_index_on(attri_num),
+                self.get_range(attri_num),
+            ) {
+                let record_nums =
+                    index.range(key_bound(lower).as_ref(), key_bound(upper).as_ref())?;
+                return Ok((record_nums, false));
             }
         }
 
-        selected
+        all_record_nums()
+    }
+
+    /// Returns the value the attribute at attri_num must equal according to the first Constraint
+    /// in the form 'attri = value', if there is one. Note that this should only be used on
+    /// Conditions without OR and known to only relate to one Table.
+    fn get_equal_value(&self, attri_num: usize) -> Option<&Data> {
+        self.bool_evals
+            .iter()
+            .find_map(|(_, bool_eval)| match bool_eval {
+                BoolEval::Constraint(constraint) => match constraint.get_equality() {
+                    Some((_, j, data)) if j == attri_num => Some(data),
+                    _ => None,
+                },
+                BoolEval::Condition(_) => None, // don't worry about this recursively
+            })
     }
 
-    /// Returns a filtered list of valid coordinates based on the given Condition. Note that
-    /// this should only be called with the tables used when converting this Condition.
+    /// Returns the tightest lower and upper bounds that the Constraints of this Condition put
+    /// on the attribute at attri_num, or None if none of them bound it. Note that this should
+    /// only be used on Conditions without OR and known to only relate to one Table.
+    fn get_range(&self, attri_num: usize) -> Option<(Bound<&Data>, Bound<&Data>)> {
+        let mut range = None;
+        for (_, bool_eval) in self.bool_evals.iter() {
+            let bounds = match bool_eval {
+                BoolEval::Constraint(constraint) => constraint.get_bounds(),
+                BoolEval::Condition(_) => None, // don't worry about this recursively
+            };
+            let (lower, upper) = match bounds {
+                Some((_, j, lower, upper)) if j == attri_num => (lower, upper),
+                _ => continue,
+            };
+            range = match range {
+                Some((prev_lower, prev_upper)) => Some((
+                    tighter_bound(prev_lower, lower, Ordering::Greater),
+                    tighter_bound(prev_upper, upper, Ordering::Less),
+                )),
+                None => Some((lower, upper)),
+            };
+        }
+        range
+    }
+
+    /// Attempts to read the records of the table at table_num with the given record_nums one at a
+    /// time, and returns those for which this Condition is true along with their record numbers,
+    /// in the order they were given in.
+    /// Note this should only be used on Conditions known to only relate to one Table.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a record cannot be read.
     ///
     /// # Panics
     ///
-    /// Could panic if the wrong reference is given for tables so that table_coords do not match up
-    /// to the actual attribute list lengths.
-    fn eval_coords(
+    /// Could panic if given a Condition that relates to more than one table or a condition
+    /// that relates to a table other than the one indicated by table_num
+    fn read_matching(
         &self,
-        table_coords: Vec<Vec<usize>>,
-        tables: &Vec<MemTable>,
-    ) -> Vec<Vec<usize>> {
-        let mut selected: Vec<Vec<usize>> = Vec::with_capacity(table_coords.len());
-
-        for coord in table_coords {
-            let mut joined_record = Vec::new();
-            for (table_num, record_num) in coord.iter().enumerate() {
-                joined_record.push(&tables[table_num].records[*record_num]);
-            }
-            if self.eval(&joined_record) {
-                selected.push(coord);
+        record_nums: Vec<usize>,
+        tables: &[&Table],
+        table_num: usize,
+    ) -> Result<Vec<NumberedRecord>, Box<dyn Error>> {
+        let mut selected = Vec::new();
+        let binding = vec![]; // the other tables are never looked at
+        for record_num in record_nums {
+            let record = tables[table_num].read_record(record_num)?;
+            let mut joined_record: Vec<&Vec<Data>> = vec![&binding; tables.len()];
+            joined_record[table_num] = &record;
+            if self.eval(&joined_record) == Some(true) {
+                selected.push((record_num, record));
             }
         }
-
-        selected
+        Ok(selected)
     }
 
-    /// Returns how this Condition evaluated on the given joined_record. Requires the condition
-    /// to have been converted.
-    fn eval(&self, joined_record: &Vec<&Vec<Data>>) -> bool {
-        let mut curr_evaluation = true;
+    /// Returns how this Condition evaluated on the given joined_record, or None when that is
+    /// unknown because of Data::Null values. AND binds tighter than OR, and both follow
+    /// three-valued logic - false AND unknown is false, while true OR unknown is true.
+    /// Requires the condition to have been converted.
+    fn eval(&self, joined_record: &[&Vec<Data>]) -> Option<bool> {
+        let mut prev_groups = Some(false); // the OR of every group of ANDs before the current one
+        let mut curr_group = Some(true);
 
         for (log_op, bool_eval) in self.bool_evals.iter() {
-            match (log_op, bool_eval) {
-                (LogOp::And, BoolEval::Condition(cond)) => {
-                    if curr_evaluation == false {
-                        continue;
-                    }
-                    if !cond.eval(joined_record) {
-                        curr_evaluation = false;
-                    }
-                }
-                (LogOp::Or, BoolEval::Condition(cond)) => {
-                    if curr_evaluation == true {
-                        return true;
-                    } else {
-                        curr_evaluation = cond.eval(joined_record);
-                    }
-                }
-                (LogOp::And, BoolEval::Constraint(constraint)) => {
-                    if curr_evaluation == false {
-                        continue;
-                    }
-                    if !constraint.eval(joined_record) {
-                        curr_evaluation = false;
-                    }
-                }
-                (LogOp::Or, BoolEval::Constraint(constraint)) => {
-                    if curr_evaluation == true {
-                        return true;
-                    } else {
-                        curr_evaluation = constraint.eval(joined_record);
-                    }
+            if *log_op == LogOp::Or {
+                prev_groups = or(prev_groups, curr_group);
+                if prev_groups == Some(true) {
+                    return Some(true);
                 }
+                curr_group = Some(true);
+            }
+            if curr_group == Some(false) {
+                continue;
             }
+            let evaluation = match bool_eval {
+                BoolEval::Condition(cond) => cond.eval(joined_record),
+                BoolEval::Constraint(constraint) => constraint.eval(joined_record),
+            };
+            curr_group = and(curr_group, evaluation);
         }
 
-        curr_evaluation
+        or(prev_groups, curr_group)
     }
 
-    /// Returns a MemTable which represents the selection out of the cartesian product of the Tables reffered to by tables.
+    /// Returns true for a Condition with nothing to check, such as that of a command without a
+    /// WHERE clause, which every record meets.
+    pub fn is_empty(&self) -> bool {
+        self.bool_evals.is_empty()
+    }
+
+    /// Returns false only if this Condition is false for record, so that a CHECK constraint
+    /// is met when its Condition is true or unknown. Requires the condition to have been
+    /// converted with only the record's Table.
+    pub fn allows(&self, record: &Vec<Data>) -> bool {
+        self.eval(&[record]) != Some(false)
+    }
+
+    /// Returns a MemTable which represents the selection out of the cartesian product of the Tables reffered to by tables,
+    /// sorted by the attributes in order_by and cut down to the records limit allows. The sort is skipped when the records
+    /// of the first table are read in the order of its primary key and order_by names a prefix of that key in a single
+    /// direction. The records of the first table are read one at a time, in the order of its index when it has one,
+    /// so without a sort no more of them are read once enough records are found to fill the limit.
     /// This does not require you to convert the Condition first since it will attempt that first.
     ///
     /// # Errors
     ///
-    /// Fails when cannot convert Constraints or when cannot read tables into memory.
-    pub fn select(mut self, tables: Vec<&Table>) -> Result<MemTable, Box<dyn Error>> {
-        if tables.len() == 0 {
+    /// Fails when cannot convert Constraints, when order_by names an attribute which is not in the tables,
+    /// or when cannot read records from the tables.
+    pub fn select(
+        mut self,
+        tables: Vec<&Table>,
+        order_by: &[OrderBy],
+        limit: Option<&Limit>,
+    ) -> Result<MemTable, Box<dyn Error>> {
+        if tables.is_empty() {
             Err(DBError::ConstraintError("Must select from a table."))?
         }
         // Replace all attributes in bool_evals list with table coordinates
         self.convert_with(&tables)?;
 
+        // find the attributes to sort by as coordinates, the same way that Constraints find theirs
+        let mut sort_coords = Vec::with_capacity(order_by.len());
+        for order in order_by {
+            let coord = tables.iter().enumerate().find_map(|(i, table)| {
+                table
+                    .attributes()
+                    .iter()
+                    .position(|(attribute, _)| *attribute == order.attribute)
+                    .map(|j| (i, j))
+            });
+            match coord {
+                Some(coord) => sort_coords.push((coord, order.descending)),
+                None => Err(DBError::ConstraintError(
+                    "Could not find an attribute in the tables with the name given in ORDER BY.",
+                ))?,
+            }
+        }
+
         // Get all single_table and Always true things as a separate condition
         let mut helpers = HashMap::new();
         self.split_load_helpers(&mut helpers, true);
 
-        // load MemTables
-        let mut mem_tables = Vec::new();
-        let mut new_attributes = Vec::new();
-        for table in tables.iter() {
-            let mem_table = MemTable::build(table)?;
-            new_attributes.push(mem_table.attributes.clone());
-            mem_tables.push(mem_table);
-        }
-
-        let new_attributes = new_attributes.concat();
-
-        // Filter memtables by single_table/always_trues condition (get coords for each table that match)
-        let mut record_nums_vec = Vec::new();
-        for (i, _) in mem_tables.iter().enumerate() {
-            record_nums_vec.push(match helpers.remove(&i) {
-                Some(helper) => helper.filter_table_coords(&mem_tables, i, &tables[i].bst, &tables),
-                None => Condition::parse("")?.filter_table_coords(
-                    &mem_tables,
-                    i,
-                    &tables[i].bst,
-                    &tables,
-                ),
-            });
-        }
+        let new_attributes: Vec<(Identifier, Domain)> = tables
+            .iter()
+            .flat_map(|table| table.attributes().iter().cloned())
+            .collect();
+
+        // the first table is the outermost loop of the cartesian product, so the selection is
+        // in the order of its key when its records are read in that order
+        let key_prefix = tables[0]
+            .key_attri_nums
+            .iter()
+            .zip(sort_coords.iter())
+            .take_while(|(key_num, ((i, j), _))| *i == 0 && j == *key_num)
+            .count();
+        let single_direction = sort_coords
+            .iter()
+            .all(|(_, descending)| *descending == sort_coords[0].1);
+        let mut needs_sort = !sort_coords.is_empty();
+
+        // find the candidates of each table which its single_table/always_trues condition allows
+        // through its indexes. Only the first table's record numbers are kept - its records are
+        // read one at a time as the selection is built.
+        let mut outer = None;
+        let mut records_vec = Vec::new();
+        for i in 0..tables.len() {
+            let mut helper = helpers
+                .remove(&i)
+                .unwrap_or_else(|| Condition::new(Vec::new()));
+            let (mut record_nums, key_order) = helper.get_record_nums(i, &tables)?;
+            if i > 0 {
+                let records = helper.read_matching(record_nums, &tables, i)?;
+                if records.is_empty() {
+                    return MemTable::build_from_records(vec![], new_attributes);
+                }
+                records_vec.push(
+                    records
+                        .into_iter()
+                        .map(|(_, record)| record)
+                        .collect::<Vec<_>>(),
+                );
+                continue;
+            }
 
-        for record_nums in record_nums_vec.iter() {
-            if record_nums.len() == 0 {
-                return Ok(MemTable::build_from_records(vec![], new_attributes)?);
+            if key_order && key_prefix == sort_coords.len() && single_direction {
+                needs_sort = false;
+                if sort_coords
+                    .first()
+                    .is_some_and(|(_, descending)| *descending)
+                {
+                    record_nums.reverse();
+                }
             }
+            outer = Some((helper, record_nums));
         }
+        let (outer_helper, outer_record_nums) = outer.unwrap(); // there is always a first table
 
-        let mut table_coords = Vec::new();
-
-        // count up
-        let mut cart_prod_key: Vec<usize> = vec![0; mem_tables.len()];
-        let mut cart_prod_elem: Vec<usize> = vec![0; mem_tables.len()];
+        // without a sort, only the records up to the end of the limit are needed
+        let max = match limit {
+            Some(limit) if !needs_sort => Some(limit.offset.saturating_add(limit.count)),
+            _ => None,
+        };
+        let mut selected = Vec::new();
+
+        // a sorted selection goes into runs as its records are found rather than being collected first
+        let mut sorter = None;
+        if needs_sort {
+            // the attribute numbers in the joined records
+            let offsets: Vec<usize> = tables
+                .iter()
+                .scan(0, |offset, table| {
+                    let table_offset = *offset;
+                    *offset += table.attributes().len();
+                    Some(table_offset)
+                })
+                .collect();
+            let sort_keys: Vec<SortKey> = sort_coords
+                .iter()
+                .map(|((i, j), descending)| (offsets[*i] + j, *descending))
+                .collect();
+            sorter = Some(Sorter::new(sort_keys, SORT_MEMORY_LIMIT, |run| {
+                tables[0].sort_run_path(run)
+            }));
+        }
 
-        'outer: loop {
-            for (i, key) in cart_prod_key.iter().enumerate() {
-                cart_prod_elem[i] = record_nums_vec[i][*key];
+        'outer: for record_num in outer_record_nums {
+            if max.is_some_and(|max| selected.len() >= max) {
+                break;
+            }
+            let outer_record = tables[0].read_record(record_num)?;
+            if outer_helper.eval(&[&outer_record]) != Some(true) {
+                continue;
             }
 
-            table_coords.push(cart_prod_elem.clone());
+            // count up through the cartesian product of the other tables' records
+            let mut cart_prod_key: Vec<usize> = vec![0; records_vec.len()];
+            let mut joined_record: Vec<&Vec<Data>> = Vec::with_capacity(tables.len());
+            loop {
+                if max.is_some_and(|max| selected.len() >= max) {
+                    break 'outer;
+                }
 
-            // increment cart_prod_key
-            for (i, key) in cart_prod_key.iter_mut().enumerate().rev() {
-                *key += 1;
-                if *key == record_nums_vec[i].len() {
-                    if i == 0 {
-                        break 'outer;
-                    } else {
-                        *key = 0;
+                joined_record.clear();
+                joined_record.push(&outer_record);
+                for (records, key) in records_vec.iter().zip(cart_prod_key.iter()) {
+                    joined_record.push(&records[*key]);
+                }
+                if self.eval(&joined_record) == Some(true) {
+                    let record = joined_record
+                        .iter()
+                        .flat_map(|record| record.iter().cloned())
+                        .collect();
+                    match sorter {
+                        Some(ref mut sorter) => sorter.push(record)?,
+                        None => selected.push(record),
                     }
-                } else {
+                }
+
+                // increment cart_prod_key, and move on to the next outer record once it wraps around
+                let incremented =
+                    cart_prod_key
+                        .iter_mut()
+                        .zip(records_vec.iter())
+                        .rev()
+                        .any(|(key, records)| {
+                            *key = (*key + 1) % records.len();
+                            *key != 0
+                        });
+                if !incremented {
                     break;
                 }
             }
         }
 
-        let selected = self.eval_coords(table_coords, &mem_tables);
-
-        let mut records = Vec::with_capacity(selected.len());
+        let mut records = match sorter {
+            Some(sorter) => sorter.finish()?,
+            None => selected,
+        };
 
-        for table_coord in selected {
-            let mut new_rec = Vec::new();
-            for (i, rec_num) in table_coord.iter().enumerate() {
-                for data in mem_tables[i].records[*rec_num].iter() {
-                    new_rec.push(data.clone());
-                }
-            }
-            records.push(new_rec);
+        if let Some(limit) = limit {
+            records.truncate(limit.offset.saturating_add(limit.count));
+            records.drain(..limit.offset.min(records.len()));
         }
 
-        Ok(MemTable::build_from_records(records, new_attributes)?)
+        MemTable::build_from_records(records, new_attributes)
     }
 
-    /// Will update every record in the table that matches the given Condition.
-    /// Returns a result which indicates whether or not the table you passed in was updated properly.
-    /// This does not require you to convert the Condition first since it will attempt that first.
+    /// Returns the record numbers of every record in the table that matches the given Condition,
+    /// for use with Table.update_all or Table.delete_all. This does not require you to convert
+    /// the Condition first since it will attempt that first.
     ///
     /// # Errors
     ///
-    /// Fails when cannot convert Constraints or when cannot read/write tables into memory.
-    pub fn update(
-        mut self,
-        table: &mut Table,
-        new_values: Vec<(Identifier, Data)>,
-    ) -> Result<(), Box<dyn Error>> {
-        self.convert_with(&vec![table])?;
-        table.update_all(self, new_values)
-    }
-
-    /// Will delete every record in the table that matches the given Condition.
-    /// Returns a result which indicates whether or not the table you passed in was deleted from properly.
-    /// This does not require you to convert the Condition first since it will attempt that first.
-    ///
-    /// # Errors
-    ///
-    /// Fails when cannot convert Constraints or when cannot read/write tables into memory.
-    pub fn delete(mut self, table: &mut Table) -> Result<(), Box<dyn Error>> {
-        self.convert_with(&vec![table])?;
-        table.delete_all(self)
+    /// Fails when cannot convert Constraints or when cannot read records from the table.
+    pub fn filter(mut self, table: &Table) -> Result<Vec<usize>, Box<dyn Error>> {
+        self.convert_with(&[table])?;
+        let (record_nums, _) = self.get_record_nums(0, &[table])?;
+        let matching = self.read_matching(record_nums, &[table], 0)?;
+        Ok(matching
+            .into_iter()
+            .map(|(record_num, _)| record_num)
+            .collect())
     }
 }
 
 /// An abstraction over Relative operators with a generalized .cmp method
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum RelOp {
     Equals,
     NotEqual,
@@ -694,6 +841,8 @@ pub enum RelOp {
     LessThan,
     GreaterThanOrEqual,
     LessThanOrEqual,
+    Is,
+    IsNot,
 }
 
 impl RelOp {
@@ -707,77 +856,306 @@ impl RelOp {
             RelOp::GreaterThanOrEqual => val1 >= val2,
             RelOp::LessThanOrEqual => val1 <= val2,
             RelOp::NotEqual => val1 != val2,
+            RelOp::Is => val1 == val2,
+            RelOp::IsNot => val1 != val2,
+        }
+    }
+
+    /// Returns the RelOp that gives the same result with its operands swapped,
+    /// so that 'val1 < val2' is the same as 'val2 > val1'.
+    fn flip(&self) -> RelOp {
+        match self {
+            RelOp::GreaterThan => RelOp::LessThan,
+            RelOp::LessThan => RelOp::GreaterThan,
+            RelOp::GreaterThanOrEqual => RelOp::LessThanOrEqual,
+            RelOp::LessThanOrEqual => RelOp::GreaterThanOrEqual,
+            rel_op => *rel_op,
         }
     }
 }
 
-/// Splits off a specified set of characters (mostly ascii-alphanumeric) until it sees a character not in the set.
-/// Returns the split up strings as a tuple.
-fn split_word(given: &str) -> (&str, &str) {
-    let mut double_quotes = false;
-    for (i, c) in given.char_indices() {
+/// Returns an iterator over the chars of given and their byte indices, paired with whether each
+/// char is part of a string literal. The double quotes around a literal are part of it, and a
+/// char after a backslash inside a literal is never taken as its closing double quote.
+pub fn chars_in_literals(given: &str) -> impl Iterator<Item = (usize, char, bool)> + '_ {
+    let mut in_literal = false; // is true when waiting for an end double quote
+    let mut escaped = false; // is true when the last char was a backslash in a literal
+    given.char_indices().map(move |(i, c)| {
+        if escaped {
+            escaped = false;
+        } else if in_literal && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_literal = !in_literal;
+            return (i, c, true);
+        }
+        (i, c, in_literal)
+    })
+}
+
+/// Returns the length in bytes of the string literal which given starts with, up to and including
+/// its closing double quote. A literal with no closing double quote runs to the end of given.
+pub fn literal_len(given: &str) -> usize {
+    chars_in_literals(given)
+        .find(|(_, _, in_literal)| !in_literal)
+        .map_or(given.len(), |(i, _, _)| i)
+}
+
+/// Returns cond with each word outside of double quotes replaced by what replace returns for it.
+/// Words for which replace returns None are left as they are. Words are runs of the same characters as
+/// the parser's words, along with '#'.
+pub fn replace_words(cond: &str, replace: impl Fn(&str) -> Option<String>) -> String {
+    let mut replaced = String::with_capacity(cond.len());
+    let mut rest = cond;
+    while let Some(c) = rest.chars().next() {
         if c == '"' {
-            double_quotes = !double_quotes;
+            // copy a string literal as it is
+            let end = literal_len(rest);
+            replaced.push_str(&rest[..end]);
+            rest = &rest[end..];
             continue;
         }
 
-        if !double_quotes && !c.is_ascii_alphanumeric() && c != '.' && c != '-' && c != '_' {
-            return (&given[..i], &given[i..]);
+        let word_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && !['.', '-', '_', ':', '#'].contains(&c))
+            .unwrap_or(rest.len());
+        if word_len == 0 {
+            replaced.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
         }
+        let word = &rest[..word_len];
+        replaced.push_str(&replace(word).unwrap_or_else(|| word.to_string()));
+        rest = &rest[word_len..];
     }
+    replaced
+}
 
-    return (&given, "");
+/// Returns the three-valued AND of two evaluations, where None is unknown.
+fn and(eval1: Option<bool>, eval2: Option<bool>) -> Option<bool> {
+    match (eval1, eval2) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
 }
 
-/// Splits off the characters which represent a RelOp and returns the RelOp paired with the rest of the string
-/// or an error if it does not find a RelOp.
-///
-/// # Errors
-///
-/// Fails when there is no RelOp to read
-fn split_rel_op(cond: &str) -> Result<(RelOp, &str), DBError> {
-    let (char1, char2);
-    if cond.len() < 2 {
-        char1 = &cond[..];
-        char2 = "";
-    } else {
-        char1 = &cond[..1];
-        char2 = &cond[1..2];
-    }
-
-    match (char1, char2) {
-        (">", "=") => Ok((RelOp::GreaterThanOrEqual, &cond[2..])),
-        ("<", "=") => Ok((RelOp::LessThanOrEqual, &cond[2..])),
-        ("!", "=") => Ok((RelOp::NotEqual, &cond[2..])),
-        ("=", _) => Ok((RelOp::Equals, &cond[1..])),
-        (">", _) => Ok((RelOp::GreaterThan, &cond[1..])),
-        ("<", _) => Ok((RelOp::LessThan, &cond[1..])),
-        _ => Err(DBError::ParseError("Did not find a valid RelOp.")),
+/// Returns the three-valued OR of two evaluations, where None is unknown.
+fn or(eval1: Option<bool>, eval2: Option<bool>) -> Option<bool> {
+    match (eval1, eval2) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
     }
 }
 
-/// Splits off the a parenthesis surrounded chunk if possible. Returns an Err variant if it cannot find one
-/// at the start of the string given. Returns a wrapped tuple of the split string otherwise.
+/// Returns a bound on the keys of an index which matches bound on the index's first attribute.
+fn key_bound(bound: Bound<&Data>) -> Bound<Key> {
+    bound.map(|data| Key::from(data.clone()))
+}
+
+/// Returns whichever of the two bounds allows fewer values. The tighter of two lower bounds is
+/// the one which is Ordering::Greater and the tighter of two upper bounds is the one which is
+/// Ordering::Less. When both bound the same value, the Excluded bound is tighter.
 ///
-/// # Errors
+/// # Panics
 ///
-/// Fails when there is no parenthesis chunk
-fn split_parenthesis_chunk(cond: &str) -> Result<(&str, &str), ()> {
-    if !cond.starts_with('(') {
-        return Err(());
+/// Panics when the bounds hold incompatible Data variants.
+fn tighter_bound<'a>(
+    bound1: Bound<&'a Data>,
+    bound2: Bound<&'a Data>,
+    tighter: Ordering,
+) -> Bound<&'a Data> {
+    let (data1, data2) = match (bound1, bound2) {
+        (Bound::Unbounded, bound) | (bound, Bound::Unbounded) => return bound,
+        (
+            Bound::Included(data1) | Bound::Excluded(data1),
+            Bound::Included(data2) | Bound::Excluded(data2),
+        ) => (data1, data2),
+    };
+    match data1.cmp(data2) {
+        Ordering::Equal if matches!(bound2, Bound::Excluded(_)) => bound2,
+        Ordering::Equal => bound1,
+        ordering if ordering == tighter => bound1,
+        _ => bound2,
     }
+}
 
-    let mut open_count = 0;
-    for (i, c) in cond.char_indices() {
-        if c == '(' {
-            open_count += 1;
-        } else if c == ')' {
-            open_count -= 1;
-        }
-        if open_count == 0 {
-            return Ok((&cond[1..i], &cond[i + 1..])); // remove open and closing of this chunk through selective indicies
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relation::ColumnConstraints;
+    use std::fs;
+
+    /// Returns an empty directory for a test to keep its tables in, ending in a '/'
+    /// the way database paths do.
+    fn test_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("mini_dbms_logic_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        format!("{}/", dir.display())
+    }
+
+    fn int(value: i32) -> Data {
+        Data::Integer(Integer::wrap(value))
+    }
+
+    /// Returns a table keyed by id with a second attribute v, holding ids 0 to 99 as v = id % 7.
+    /// The records are written in descending key order so record numbers differ from keys.
+    fn id_table(dir: &str) -> Table {
+        let attributes = vec![
+            (Identifier::from("id").unwrap(), Domain::Integer),
+            (Identifier::from("v").unwrap(), Domain::Integer),
+        ];
+        let constraints = vec![ColumnConstraints::default(); 2];
+        let mut table = Table::build("t", attributes, constraints, vec![0], dir).unwrap();
+        let records = (0..100).rev().map(|id| vec![int(id), int(id % 7)]);
+        table.write_records(records.collect()).unwrap();
+        table
+    }
+
+    #[test]
+    fn between_only_reads_the_range_of_the_index() {
+        let dir = test_dir("between");
+        let table = id_table(&dir);
+
+        let mut condition = parser::parse_condition("id BETWEEN 10 AND 20").unwrap();
+        condition.convert_with(&[&table]).unwrap();
+        let (record_nums, key_order) = condition.get_record_nums(0, &[&table]).unwrap();
+        assert!(key_order);
+        let ids: Vec<Data> = record_nums
+            .iter()
+            .map(|record_num| table.read_record(*record_num).unwrap()[0].clone())
+            .collect();
+        assert_eq!(ids, (10..=20).map(int).collect::<Vec<_>>());
+
+        // a tighter comparison on the same attribute narrows the range further
+        let mut condition = parser::parse_condition("id BETWEEN 10 AND 20 AND id > 18").unwrap();
+        condition.convert_with(&[&table]).unwrap();
+        let (record_nums, _) = condition.get_record_nums(0, &[&table]).unwrap();
+        assert_eq!(record_nums.len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn between_binds_like_and() {
+        let dir = test_dir("between_or");
+        let table = id_table(&dir);
+
+        // v = 3 OR (id >= 10 AND id <= 12 AND v != 4)
+        let condition =
+            parser::parse_condition("v = 3 OR id BETWEEN 10 AND 12 AND v != 4").unwrap();
+        let selection = condition.select(vec![&table], &[], None).unwrap();
+        let mut ids: Vec<i32> = selection
+            .records
+            .iter()
+            .map(|record| match record[0] {
+                Data::Integer(ref id) => *id.value(),
+                _ => unreachable!(),
+            })
+            .collect();
+        ids.sort();
+        let mut expected: Vec<i32> = (0..100).filter(|id| id % 7 == 3).collect();
+        expected.push(12); // 10 has v = 3 already, and 11 has v = 4
+        expected.sort();
+        assert_eq!(ids, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Returns a table keyed by id with a nullable attribute x, holding (0, NULL), (1, 1), and (2, 2).
+    fn null_table(dir: &str) -> Table {
+        let attributes = vec![
+            (Identifier::from("id").unwrap(), Domain::Integer),
+            (Identifier::from("x").unwrap(), Domain::Integer),
+        ];
+        let constraints = vec![ColumnConstraints::default(); 2];
+        let mut table = Table::build("n", attributes, constraints, vec![0], dir).unwrap();
+        let records = vec![
+            vec![int(0), Data::Null],
+            vec![int(1), int(1)],
+            vec![int(2), int(2)],
+        ];
+        table.write_records(records).unwrap();
+        table
+    }
+
+    /// Returns how cond evaluates on record, a record of table.
+    fn eval(cond: &str, table: &Table, record: &Vec<Data>) -> Option<bool> {
+        let mut condition = parser::parse_condition(cond).unwrap();
+        condition.convert_with(&[table]).unwrap();
+        condition.eval(&[record])
+    }
+
+    /// Returns the ids of the records of table which cond selects.
+    fn selected_ids(cond: &str, table: &Table) -> Vec<Data> {
+        let condition = parser::parse_condition(cond).unwrap();
+        let selection = condition.select(vec![table], &[], None).unwrap();
+        selection
+            .records
+            .into_iter()
+            .map(|record| record[0].clone())
+            .collect()
+    }
+
+    #[test]
+    fn comparisons_with_null_are_unknown() {
+        let dir = test_dir("null_cmp");
+        let table = null_table(&dir);
+        let null_record = vec![int(0), Data::Null];
+        let one_record = vec![int(1), int(1)];
+
+        for cond in ["x = NULL", "x != NULL", "x > 0", "x <= 0", "x = x"] {
+            assert_eq!(eval(cond, &table, &null_record), None, "{cond}");
         }
+        assert_eq!(eval("x = NULL", &table, &one_record), None);
+        assert_eq!(eval("x != NULL", &table, &one_record), None);
+        assert!(selected_ids("x = NULL", &table).is_empty());
+        assert!(selected_ids("x != NULL", &table).is_empty());
+        assert_eq!(selected_ids("x < 5", &table), vec![int(1), int(2)]);
+        assert_eq!(selected_ids("x != 1", &table), vec![int(2)]);
+
+        assert_eq!(eval("x IS NULL", &table, &null_record), Some(true));
+        assert_eq!(eval("x IS NOT NULL", &table, &null_record), Some(false));
+        assert_eq!(eval("x IS NULL", &table, &one_record), Some(false));
+        assert_eq!(selected_ids("x IS NULL", &table), vec![int(0)]);
+        assert_eq!(selected_ids("x IS NOT NULL", &table), vec![int(1), int(2)]);
+        fs::remove_dir_all(dir).unwrap();
     }
 
-    Err(())
+    #[test]
+    fn and_or_follow_three_valued_logic() {
+        let dir = test_dir("null_logic");
+        let table = null_table(&dir);
+        let record = vec![int(0), Data::Null];
+
+        // on this record 'x > 0' is unknown, 'id = 0' is true, and 'id = 1' is false
+        let truth_table = [
+            ("id = 0 AND x > 0", None),
+            ("x > 0 AND id = 0", None),
+            ("id = 1 AND x > 0", Some(false)),
+            ("x > 0 AND id = 1", Some(false)),
+            ("x > 0 AND x > 0", None),
+            ("id = 0 OR x > 0", Some(true)),
+            ("x > 0 OR id = 0", Some(true)),
+            ("id = 1 OR x > 0", None),
+            ("x > 0 OR id = 1", None),
+            ("x > 0 OR x > 0", None),
+            ("x > 0 OR id = 1 AND x > 0", None),
+            ("x > 0 AND id = 1 OR id = 0", Some(true)),
+            ("(x > 0 OR id = 0) AND x > 0", None),
+            ("(x > 0 AND id = 1) OR x IS NULL", Some(true)),
+        ];
+        for (cond, expected) in truth_table {
+            assert_eq!(eval(cond, &table, &record), expected, "{cond}");
+        }
+
+        // only a true condition selects a record
+        assert_eq!(selected_ids("id = 0 AND x > 0", &table), Vec::<Data>::new());
+        assert_eq!(
+            selected_ids("x > 1 OR id = 0", &table),
+            vec![int(0), int(2)]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -28,17 +28,26 @@ fn main() {
         cmds.push_str(&line); // always ends with '\n' so comments will be valid
 
         // iterate over all commands currently in cmds
-        for cmd in CmdIterator::over(&cmds) {
-            match mini_dbms::run_cmd(cmd.trim_start(), &mut db) {
-                Err(err) => {
+        let mut cmd_iter = CmdIterator::over(&cmds);
+        while let Some(cmd) = cmd_iter.next() {
+            match mini_dbms::run_cmd(&cmd, &mut db) {
+                Err(mut err) => {
+                    cmd_iter.locate(&mut err, None);
                     eprintln!("\t{}", err);
+                    // underline the part of the command which could not be parsed
+                    if let Some(DBError::SyntaxError(syntax_error)) = err.downcast_ref::<DBError>()
+                    {
+                        if let Some((line, carets)) = syntax_error.excerpt() {
+                            eprintln!("\t{line}\n\t{carets}");
+                        }
+                    }
                     // these error types are acceptable and come with more helpful messages for the user
                     if err.is::<DBError>() || err.is::<BSTInsertErr>() {
                         cmds.clear();
                         continue 'read;
                     }
 
-                    // save table sizes and bst indices since those are otherwise kept in memory
+                    // roll back any open transaction and empty the write-ahead log before stopping
                     mini_dbms::run_exit(&mut db).expect("FAILED TO SAVE ON EXIT");
                 }
 
@@ -0,0 +1,1201 @@
+use crate::aggregate::{Aggregate, Function};
+use crate::base::{Boolean, Data, Domain, Identifier, Text};
+use crate::logic::{BoolEval, Condition, Constraint, Limit, LogOp, Operand, OrderBy, RelOp};
+use crate::relation::{ColumnConstraints, RefAction, Reference};
+use crate::{DBError, SyntaxError};
+
+/// Contains the Statement tree which commands are parsed into
+mod ast;
+/// Contains the lexer which splits commands into tokens
+mod lexer;
+
+pub use ast::{ColumnDef, Projection, Select, Statement};
+use lexer::{lowercase_outside_quotes, tokenize, Token, TokenKind};
+
+/// Attempts to parse a whole command into a Statement.
+///
+/// # Errors
+///
+/// Fails when cmd is not one of the commands or has anything left over after one.
+pub fn parse_statement(cmd: &str) -> Result<Statement, DBError> {
+    let mut parser = Parser::over(cmd)?;
+    parser
+        .statement()
+        .and_then(|statement| {
+            parser.expect_end("Expected the end of the command.")?;
+            Ok(statement)
+        })
+        .map_err(|err| parser.syntax_error(err))
+}
+
+/// Attempts to parse a whole Condition, as it is written after WHERE. An empty string
+/// is the Condition which is always true.
+///
+/// # Errors
+///
+/// Fails when cond is not a Condition or has anything left over after one.
+pub fn parse_condition(cond: &str) -> Result<Condition, DBError> {
+    let mut parser = Parser::over(cond)?;
+    if parser.tokens.is_empty() {
+        return Ok(Condition::new(Vec::new()));
+    }
+    parser
+        .condition()
+        .and_then(|condition| {
+            parser.expect_end("Did not find valid logical operator.")?;
+            Ok(condition)
+        })
+        .map_err(|err| parser.syntax_error(err))
+}
+
+/// A recursive-descent parser over the tokens of one command. Each method reads one part of
+/// the grammar off of the front of the tokens which are left. When a method fails, the next
+/// token is the one which could not be parsed.
+struct Parser<'a> {
+    cmd: &'a str,
+    tokens: Vec<Token>,
+    /// The index of the next token to read
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Attempts to create a Parser over the tokens of cmd.
+    ///
+    /// # Errors
+    ///
+    /// Fails when cmd cannot be split into tokens.
+    fn over(cmd: &'a str) -> Result<Self, DBError> {
+        Ok(Parser {
+            cmd,
+            tokens: tokenize(cmd)?,
+            pos: 0,
+        })
+    }
+
+    /// Returns the kind of the next token without reading it.
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
+    }
+
+    /// Attempts to read a word.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is not a word.
+    fn word(&mut self, err: &'static str) -> Result<String, DBError> {
+        match self.peek() {
+            Some(TokenKind::Word(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(DBError::ParseError(err)),
+        }
+    }
+
+    /// Returns err as a SyntaxError at the next token when it is a ParseError. Other errors
+    /// are returned as they are.
+    fn syntax_error(&self, err: DBError) -> DBError {
+        let message = match err {
+            DBError::ParseError(message) => message,
+            err => return err,
+        };
+        let syntax_error = match self.tokens.get(self.pos) {
+            Some(token) => SyntaxError::new(
+                message,
+                token.start,
+                Some(self.cmd[token.start..token.end].to_string()),
+            ),
+            None => SyntaxError::new(message, self.cmd.len(), None),
+        };
+        DBError::SyntaxError(Box::new(syntax_error))
+    }
+
+    /// Reads the next token if it is of the given kind. Returns whether it was read.
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek() == Some(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reads the next token if it is the given (lowercase) word. Returns whether it was read.
+    fn eat_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(TokenKind::Word(next)) if next == word => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Attempts to read a token of the given kind.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is of another kind.
+    fn expect(&mut self, kind: &TokenKind, err: &'static str) -> Result<(), DBError> {
+        match self.eat(kind) {
+            true => Ok(()),
+            false => Err(DBError::ParseError(err)),
+        }
+    }
+
+    /// Attempts to read the given (lowercase) word.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is not the word.
+    fn expect_word(&mut self, word: &str, err: &'static str) -> Result<(), DBError> {
+        match self.eat_word(word) {
+            true => Ok(()),
+            false => Err(DBError::ParseError(err)),
+        }
+    }
+
+    /// Checks that every token has been read.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when there are tokens left.
+    fn expect_end(&self, err: &'static str) -> Result<(), DBError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(DBError::ParseError(err)),
+        }
+    }
+
+    /// Attempts to read an Identifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is not a word, or with the reason
+    /// the word is not a valid Identifier.
+    fn identifier(&mut self, err: &'static str) -> Result<Identifier, DBError> {
+        match self.peek() {
+            Some(TokenKind::Word(word)) => {
+                let identifier = Identifier::from(word)?;
+                self.pos += 1;
+                Ok(identifier)
+            }
+            _ => Err(DBError::ParseError(err)),
+        }
+    }
+
+    /// Attempts to read a list of Identifiers separated by commas.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when an item of the list is not an Identifier.
+    fn identifier_list(&mut self, err: &'static str) -> Result<Vec<Identifier>, DBError> {
+        let mut identifiers = vec![self.identifier(err)?];
+        while self.eat(&TokenKind::Comma) {
+            identifiers.push(self.identifier(err)?);
+        }
+        Ok(identifiers)
+    }
+
+    /// Attempts to read a list of Identifiers separated by commas and wrapped in parenthesis.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the list is not wrapped in parenthesis or an item of it is not an Identifier.
+    fn parenthesized_identifiers(&mut self) -> Result<Vec<Identifier>, DBError> {
+        self.expect(
+            &TokenKind::LeftParen,
+            "Lists must be wrapped in parenthesis.",
+        )?;
+        let identifiers = self.identifier_list("Expected an attribute name in the list.")?;
+        self.expect(
+            &TokenKind::RightParen,
+            "Cannot parse list that does not end with ')'",
+        )?;
+        Ok(identifiers)
+    }
+
+    /// Attempts to read an Operand. A string literal is a Text value and a word is whichever
+    /// Operand Operand::parse reads it as.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is neither a string literal nor a word,
+    /// or when the word is not an Operand.
+    fn operand(&mut self, err: &'static str) -> Result<Operand, DBError> {
+        let operand = match self.peek() {
+            Some(TokenKind::Str(content)) => Operand::Value(Data::Text(Text::from(content)?)),
+            Some(TokenKind::Word(word)) => Operand::parse(word)?,
+            _ => return Err(DBError::ParseError(err)),
+        };
+        self.pos += 1;
+        Ok(operand)
+    }
+
+    /// Attempts to read a constant value.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is not a constant.
+    fn value(&mut self, err: &'static str) -> Result<Data, DBError> {
+        match self.operand(err)? {
+            Operand::Value(data) => Ok(data),
+            _ => {
+                self.pos -= 1; // point back at the word which is not a constant
+                Err(DBError::ParseError(
+                    "String literal expected. Wrap literals in double quotes.",
+                ))
+            }
+        }
+    }
+
+    /// Attempts to read any one Statement.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the tokens do not start with one of the Statements.
+    fn statement(&mut self) -> Result<Statement, DBError> {
+        let directive = self.word("Failed to read command directive.")?;
+        match &directive[..] {
+            "create" => self.create(),
+            "drop" => {
+                self.expect_word("index", "Syntax error after directive DROP.")?;
+                Ok(Statement::DropIndex(
+                    self.identifier("DROP INDEX requires an index name.")?,
+                ))
+            }
+            "use" => Ok(Statement::Use(
+                self.identifier("USE requires a database name.")?,
+            )),
+            "describe" => match self.eat_word("all") {
+                true => Ok(Statement::Describe(None)),
+                false => Ok(Statement::Describe(Some(
+                    self.identifier("DESCRIBE requires at least one argument.")?,
+                ))),
+            },
+            "select" => Ok(Statement::Select(self.select()?)),
+            "let" => self.let_table(),
+            "insert" => self.insert(),
+            "update" => self.update(),
+            "delete" => self.delete(),
+            "input" => self.input(),
+            "rename" => self.rename(),
+            "begin" => {
+                self.no_arguments(Statement::Begin, "BEGIN command does not take arguments.")
+            }
+            "commit" => {
+                self.no_arguments(Statement::Commit, "COMMIT command does not take arguments.")
+            }
+            "rollback" => self.no_arguments(
+                Statement::Rollback,
+                "ROLLBACK command does not take arguments.",
+            ),
+            "exit" => self.no_arguments(Statement::Exit, "EXIT command does not take arguments."),
+            _ => {
+                self.pos -= 1; // point back at the directive
+                Err(DBError::ParseError("Failed to read command directive."))
+            }
+        }
+    }
+
+    /// Returns statement when there are no tokens left.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when there are tokens left.
+    fn no_arguments(&self, statement: Statement, err: &'static str) -> Result<Statement, DBError> {
+        self.expect_end(err)?;
+        Ok(statement)
+    }
+
+    /// Attempts to read the rest of CREATE DATABASE, CREATE TABLE, or CREATE INDEX.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the tokens after CREATE are not one of the three.
+    fn create(&mut self) -> Result<Statement, DBError> {
+        if self.eat_word("database") {
+            Ok(Statement::CreateDatabase(
+                self.identifier("CREATE DATABASE requires a database name.")?,
+            ))
+        } else if self.eat_word("table") {
+            self.create_table()
+        } else if self.eat_word("index") {
+            let name = self.identifier("CREATE INDEX requires an index name.")?;
+            self.expect_word(
+                "on",
+                "CREATE INDEX requires the form 'name ON table(attribute)'.",
+            )?;
+            let table = self.identifier("CREATE INDEX requires a table name after ON.")?;
+            let list_start = self.pos;
+            let mut attributes = self.parenthesized_identifiers()?;
+            if attributes.len() != 1 {
+                self.pos = list_start;
+                return Err(DBError::ParseError(
+                    "CREATE INDEX takes exactly one attribute.",
+                ));
+            }
+            Ok(Statement::CreateIndex {
+                name,
+                table,
+                attribute: attributes.remove(0),
+            })
+        } else {
+            Err(DBError::ParseError("Syntax error after directive CREATE."))
+        }
+    }
+
+    /// Attempts to read the rest of CREATE TABLE, from the table name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the table name or a definition in the list cannot be read.
+    fn create_table(&mut self) -> Result<Statement, DBError> {
+        let name = self.identifier("Not enough arguments for CREATE TABLE.")?;
+        self.expect(
+            &TokenKind::LeftParen,
+            "Lists must be wrapped in parenthesis.",
+        )?;
+
+        let mut columns = Vec::new();
+        let mut primary_key = None;
+        loop {
+            // a table-level 'PRIMARY KEY (attribute [, attribute]*)' clause
+            if self.eat_word("primary") {
+                self.expect_word("key", "Expected KEY after PRIMARY.")?;
+                if primary_key.is_some() {
+                    return Err(DBError::ParseError(
+                        "A table can only have one PRIMARY KEY.",
+                    ));
+                }
+                primary_key = Some(self.parenthesized_identifiers()?);
+            } else {
+                columns.push(self.column_def()?);
+            }
+
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(
+            &TokenKind::RightParen,
+            "Did not recognize a constraint in attribute definition.",
+        )?;
+
+        Ok(Statement::CreateTable {
+            name,
+            columns,
+            primary_key,
+        })
+    }
+
+    /// Attempts to read the definition of one attribute in CREATE TABLE: its name, its Domain,
+    /// and then its column constraints in any order.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the name, Domain, or a constraint cannot be read.
+    fn column_def(&mut self) -> Result<ColumnDef, DBError> {
+        let name = self.identifier("Expected an attribute name in the list.")?;
+        let domain = self.domain()?;
+
+        let mut column = ColumnDef {
+            name,
+            domain,
+            primary_key: false,
+            constraints: ColumnConstraints::default(),
+            referenced_key: None,
+        };
+        loop {
+            if self.eat_word("primary") {
+                self.expect_word("key", "Expected KEY after PRIMARY.")?;
+                column.primary_key = true;
+            } else if self.eat_word("not") {
+                self.expect_word("null", "Expected NULL after NOT.")?;
+                column.constraints.not_null = true;
+            } else if self.eat_word("default") {
+                column.constraints.default =
+                    Some(self.value("DEFAULT requires a value after it.")?);
+            } else if self.eat_word("check") {
+                column.constraints.check = Some(self.check()?);
+            } else if self.eat_word("references") {
+                let table = self.identifier("REFERENCES requires the form 'table(attribute)'.")?;
+                let list_start = self.pos;
+                let mut keys = self.parenthesized_identifiers()?;
+                if keys.len() != 1 {
+                    self.pos = list_start;
+                    return Err(DBError::ParseError(
+                        "REFERENCES requires the form 'table(attribute)'.",
+                    ));
+                }
+                column.constraints.references = Some(Reference {
+                    table,
+                    on_delete: RefAction::Restrict,
+                    on_update: RefAction::Restrict,
+                });
+                column.referenced_key = Some(keys.remove(0));
+            } else if self.eat_word("on") {
+                let on_delete = if self.eat_word("delete") {
+                    true
+                } else if self.eat_word("update") {
+                    false
+                } else {
+                    return Err(DBError::ParseError("Expected DELETE or UPDATE after ON."));
+                };
+                let action = self.ref_action()?;
+                match column.constraints.references {
+                    Some(ref mut reference) if on_delete => reference.on_delete = action,
+                    Some(ref mut reference) => reference.on_update = action,
+                    None if on_delete => {
+                        return Err(DBError::ParseError("ON DELETE must follow REFERENCES."))
+                    }
+                    None => return Err(DBError::ParseError("ON UPDATE must follow REFERENCES.")),
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(column)
+    }
+
+    /// Attempts to read a Domain, along with its parameters in parenthesis if it has any.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the next tokens are not a Domain.
+    fn domain(&mut self) -> Result<Domain, DBError> {
+        let start = self.pos;
+        let mut descriptor = self.word("Did not find a Domain for an Attribute in the list.")?;
+        // such as decimal(10, 2)
+        if self.eat(&TokenKind::LeftParen) {
+            let mut params = Vec::new();
+            loop {
+                params.push(self.word("Invalid Domain type.")?);
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect(&TokenKind::RightParen, "Invalid Domain type.")?;
+            descriptor = format!("{descriptor}({})", params.join(","));
+        }
+        Domain::from(&descriptor).inspect_err(|_| self.pos = start)
+    }
+
+    /// Attempts to read the Condition in parenthesis after CHECK. Returns the Condition as
+    /// it was written, with everything outside of its string literals lowercased.
+    ///
+    /// # Errors
+    ///
+    /// Fails when there is no Condition in parenthesis.
+    fn check(&mut self) -> Result<String, DBError> {
+        let err = "CHECK requires a Condition in parenthesis.";
+        self.expect(&TokenKind::LeftParen, err)?;
+        let start = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.cmd.len(), |token| token.start);
+        self.condition()?; // fail early on a Condition which cannot be parsed
+        let end = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.cmd.len(), |token| token.start);
+        self.expect(&TokenKind::RightParen, err)?;
+        Ok(lowercase_outside_quotes(self.cmd[start..end].trim()))
+    }
+
+    /// Attempts to read the action which follows ON DELETE or ON UPDATE.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the next words are not RESTRICT, CASCADE, or SET NULL.
+    fn ref_action(&mut self) -> Result<RefAction, DBError> {
+        if self.eat_word("set") {
+            self.expect_word("null", "Invalid REFERENCES action.")?;
+            return Ok(RefAction::SetNull);
+        }
+        let action = self.word("Invalid REFERENCES action.")?;
+        RefAction::from(&action).inspect_err(|_| self.pos -= 1)
+    }
+
+    /// Attempts to read the rest of a SELECT, from the attribute list on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the attribute list, the FROM clause, or the WHERE clause cannot be read,
+    /// or when aggregate functions are sorted with ORDER BY.
+    fn select(&mut self) -> Result<Select, DBError> {
+        let projection = match self.eat_word("all") {
+            true => Projection::All,
+            false => self.projection()?,
+        };
+        self.expect_word("from", "SELECT command requires FROM clause.")?;
+        let tables = self.identifier_list("Expected a table name to SELECT from.")?;
+        let condition = self.where_clause()?;
+        let order_start = self.pos;
+        let order_by = self.order_by_clause()?;
+        if matches!(projection, Projection::Aggregates(_)) && !order_by.is_empty() {
+            self.pos = order_start;
+            return Err(DBError::ParseError(
+                "ORDER BY cannot sort the single record of aggregate functions.",
+            ));
+        }
+        let limit = self.limit_clause()?;
+        Ok(Select {
+            projection,
+            tables,
+            condition,
+            order_by,
+            limit,
+        })
+    }
+
+    /// Attempts to read the list of attributes or of aggregate functions to SELECT.
+    ///
+    /// # Errors
+    ///
+    /// Fails when an item of the list is neither an Identifier nor an aggregate function,
+    /// or when attributes and aggregate functions are listed together.
+    fn projection(&mut self) -> Result<Projection, DBError> {
+        let mut attributes = Vec::new();
+        let mut aggregates = Vec::new();
+        loop {
+            let start = self.pos;
+            let is_aggregate = match self.aggregate()? {
+                Some(aggregate) => {
+                    aggregates.push(aggregate);
+                    true
+                }
+                None => {
+                    attributes.push(self.identifier("Expected an attribute name to SELECT.")?);
+                    false
+                }
+            };
+            if !attributes.is_empty() && !aggregates.is_empty() {
+                self.pos = start;
+                return Err(DBError::ParseError(match is_aggregate {
+                    true => "Cannot SELECT aggregate functions along with attributes.",
+                    false => "Cannot SELECT attributes along with aggregate functions.",
+                }));
+            }
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        match aggregates.is_empty() {
+            true => Ok(Projection::Attributes(attributes)),
+            false => Ok(Projection::Aggregates(aggregates)),
+        }
+    }
+
+    /// Attempts to read an aggregate function if the next tokens are the name of one followed by
+    /// '('. Returns None without reading anything otherwise, so that an attribute may be named
+    /// count or sum.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the parenthesis do not hold an attribute name, or '*' for COUNT.
+    fn aggregate(&mut self) -> Result<Option<Aggregate>, DBError> {
+        let function = match (self.peek(), self.tokens.get(self.pos + 1)) {
+            (Some(TokenKind::Word(word)), Some(next)) if next.kind == TokenKind::LeftParen => {
+                match Function::from(word) {
+                    Some(function) => function,
+                    None => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+        self.pos += 2;
+        let attribute = match function == Function::Count && self.eat(&TokenKind::Other('*')) {
+            true => None,
+            false => Some(self.identifier(match function {
+                Function::Count => "Expected an attribute name or '*' in parenthesis after COUNT.",
+                _ => "Expected an attribute name in parenthesis after an aggregate function.",
+            })?),
+        };
+        self.expect(
+            &TokenKind::RightParen,
+            "Expected ')' after the attribute of an aggregate function.",
+        )?;
+        Ok(Some(Aggregate {
+            function,
+            attribute,
+        }))
+    }
+
+    /// Attempts to read an ORDER BY clause if there is one. Returns the attributes it lists,
+    /// each of which is ascending unless it is followed by DESC.
+    ///
+    /// # Errors
+    ///
+    /// Fails when ORDER is not followed by BY and a list of attributes.
+    fn order_by_clause(&mut self) -> Result<Vec<OrderBy>, DBError> {
+        let mut order_by = Vec::new();
+        if !self.eat_word("order") {
+            return Ok(order_by);
+        }
+        self.expect_word("by", "Expected BY after ORDER.")?;
+        loop {
+            let attribute = self.identifier("Expected an attribute name to ORDER BY.")?;
+            let descending = self.eat_word("desc");
+            if !descending {
+                self.eat_word("asc"); // ascending is the default
+            }
+            order_by.push(OrderBy {
+                attribute,
+                descending,
+            });
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        Ok(order_by)
+    }
+
+    /// Attempts to read a LIMIT clause, along with the OFFSET after it, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Fails when LIMIT or OFFSET is not followed by a count of records.
+    fn limit_clause(&mut self) -> Result<Option<Limit>, DBError> {
+        if !self.eat_word("limit") {
+            return Ok(None);
+        }
+        let count = self.count("LIMIT requires a whole number of records.")?;
+        let offset = match self.eat_word("offset") {
+            true => self.count("OFFSET requires a whole number of records.")?,
+            false => 0,
+        };
+        Ok(Some(Limit { count, offset }))
+    }
+
+    /// Attempts to read a whole number, such as the count of records after LIMIT.
+    ///
+    /// # Errors
+    ///
+    /// Fails with err when the next token is not a whole number.
+    fn count(&mut self, err: &'static str) -> Result<usize, DBError> {
+        let start = self.pos;
+        let word = self.word(err)?;
+        word.parse().map_err(|_| {
+            self.pos = start;
+            DBError::ParseError(err)
+        })
+    }
+
+    /// Attempts to read a WHERE clause if there is one. Returns the Condition which is
+    /// always true if there is not.
+    ///
+    /// # Errors
+    ///
+    /// Fails when WHERE is not followed by a Condition.
+    fn where_clause(&mut self) -> Result<Condition, DBError> {
+        match self.eat_word("where") {
+            true => self.condition(),
+            false => Ok(Condition::new(Vec::new())),
+        }
+    }
+
+    /// Attempts to read the rest of LET, from the new table name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the KEY clause or the SELECT after it cannot be read.
+    fn let_table(&mut self) -> Result<Statement, DBError> {
+        let table = self.identifier("LET requires a table name.")?;
+        self.expect_word("key", "LET command requires KEY clause.")?;
+        // allow for none to be specified to express the selection having no key
+        let key = match self.eat_word("none") {
+            true => None,
+            false => Some(self.identifier("LET requires an attribute name after KEY.")?),
+        };
+        self.expect_word("select", "LET command requires SELECT clause.")?;
+        Ok(Statement::Let {
+            table,
+            key,
+            select: self.select()?,
+        })
+    }
+
+    /// Attempts to read the rest of INSERT, from the table name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the table name or the list of values cannot be read.
+    fn insert(&mut self) -> Result<Statement, DBError> {
+        let table = self.identifier("Invalid arguments for INSERT")?;
+        self.expect_word("values", "Invalid arguments for INSERT")?;
+        self.expect(
+            &TokenKind::LeftParen,
+            "Lists must be wrapped in parenthesis.",
+        )?;
+        let mut values = Vec::new();
+        loop {
+            values.push(match self.eat_word("default") {
+                true => None,
+                false => Some(self.value("Expected a value in the list to INSERT.")?),
+            });
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(
+            &TokenKind::RightParen,
+            "Cannot parse list that does not end with ')'",
+        )?;
+        Ok(Statement::Insert { table, values })
+    }
+
+    /// Attempts to read the rest of UPDATE, from the table name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the SET clause or the WHERE clause cannot be read.
+    fn update(&mut self) -> Result<Statement, DBError> {
+        let table = self.identifier("UPDATE requires a table name.")?;
+        self.expect_word("set", "UPDATE directive requires SET clause.")?;
+        let mut new_values = Vec::new();
+        loop {
+            let attribute =
+                self.identifier("Expected Attribute name in left operator for UPDATE SET clause.")?;
+            self.expect(
+                &TokenKind::RelOp(RelOp::Equals),
+                "Found incorrect operator in UPDATE SET clause.",
+            )?;
+            let value =
+                self.value("Expected Data value in right operator for UPDATE SET clause.")?;
+            new_values.push((attribute, value));
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        Ok(Statement::Update {
+            table,
+            new_values,
+            condition: self.where_clause()?,
+        })
+    }
+
+    /// Attempts to read the rest of DELETE, from the table name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the table name or the WHERE clause cannot be read.
+    fn delete(&mut self) -> Result<Statement, DBError> {
+        let table = self.identifier("DELETE requires a table name.")?;
+        let condition = match self.peek() {
+            Some(_) => Some(self.where_clause()?),
+            None => None,
+        };
+        Ok(Statement::Delete { table, condition })
+    }
+
+    /// Attempts to read the rest of INPUT, from the input file name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a file name is missing.
+    fn input(&mut self) -> Result<Statement, DBError> {
+        let file_name = self.file_name()?;
+        let output_name = match self.eat_word("output") {
+            true => Some(self.file_name()?),
+            false => None,
+        };
+        Ok(Statement::Input {
+            file_name,
+            output_name,
+        })
+    }
+
+    /// Attempts to read a file name. This is either a string literal or the text of the tokens
+    /// which follow each other without whitespace between them, such as 'cmds/test-1.txt',
+    /// and keeps its case.
+    ///
+    /// # Errors
+    ///
+    /// Fails when there is no file name to read.
+    fn file_name(&mut self) -> Result<String, DBError> {
+        let first = match self.tokens.get(self.pos) {
+            Some(Token {
+                kind: TokenKind::Str(name),
+                ..
+            }) => {
+                let name = name.clone();
+                self.pos += 1;
+                return Ok(name);
+            }
+            Some(token) if token.kind != TokenKind::Word(String::from("output")) => token.clone(),
+            _ => {
+                return Err(DBError::ParseError(
+                    "Could not find/read the given file for INPUT directive.",
+                ))
+            }
+        };
+        self.pos += 1;
+        let mut end = first.end;
+        while let Some(token) = self.tokens.get(self.pos) {
+            if token.start != end || matches!(token.kind, TokenKind::Str(_)) {
+                break;
+            }
+            end = token.end;
+            self.pos += 1;
+        }
+        Ok(self.cmd[first.start..end].to_string())
+    }
+
+    /// Attempts to read the rest of RENAME, from the table name on.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the table name or the list of attribute names cannot be read.
+    fn rename(&mut self) -> Result<Statement, DBError> {
+        let table = self.identifier("RENAME command requires attribute names")?;
+        Ok(Statement::Rename {
+            table,
+            attributes: self.parenthesized_identifiers()?,
+        })
+    }
+
+    /// Attempts to read a Condition: Constraints or Conditions in parenthesis, joined by AND or OR.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a Constraint is missing operands or a parenthesis is not closed.
+    fn condition(&mut self) -> Result<Condition, DBError> {
+        let mut bool_evals = Vec::new();
+        // default value depended on later when checking if things are possible to load a table based on them
+        let mut log_op = LogOp::And;
+        loop {
+            if self.eat(&TokenKind::LeftParen) {
+                let condition = self.condition()?; // recursively parse anything in parenthesis
+                self.expect(
+                    &TokenKind::RightParen,
+                    "Did not find valid logical operator.",
+                )?;
+                bool_evals.push((log_op, BoolEval::Condition(condition)));
+            } else {
+                // the two Constraints of a BETWEEN are ANDed, which binds tighter than an OR before them
+                let mut constraint_log_op = log_op;
+                for constraint in self.constraint()? {
+                    bool_evals.push((constraint_log_op, BoolEval::Constraint(constraint)));
+                    constraint_log_op = LogOp::And;
+                }
+            }
+
+            log_op = if self.eat_word("and") {
+                LogOp::And
+            } else if self.eat_word("or") {
+                LogOp::Or
+            } else {
+                break;
+            };
+        }
+        Ok(Condition::new(bool_evals))
+    }
+
+    /// Attempts to read a Constraint. An attribute on its own, as in 'WHERE active',
+    /// is read as 'active = TRUE'. 'id BETWEEN 1 AND 5' is read as the two Constraints
+    /// 'id >= 1' and 'id <= 5', which must both be met.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a full Constraint cannot be read.
+    fn constraint(&mut self) -> Result<Vec<Constraint>, DBError> {
+        let left_op = self.operand("Did not find valid constraint or parenthesis chunk.")?;
+
+        if self.eat_word("between") {
+            let lower = self.operand("Did not find an Operand after BETWEEN.")?;
+            self.expect_word("and", "Expected AND between the bounds of BETWEEN.")?;
+            let upper = self.operand("Did not find an Operand after the AND of BETWEEN.")?;
+            return Ok(vec![
+                Constraint {
+                    left_op: left_op.clone(),
+                    rel_op: RelOp::GreaterThanOrEqual,
+                    right_op: lower,
+                },
+                Constraint {
+                    left_op,
+                    rel_op: RelOp::LessThanOrEqual,
+                    right_op: upper,
+                },
+            ]);
+        }
+
+        let (rel_op, right_op) = if self.eat_word("is") {
+            let rel_op = match self.eat_word("not") {
+                true => RelOp::IsNot,
+                false => RelOp::Is,
+            };
+            self.expect_word("null", "Expected NULL after IS or IS NOT.")?;
+            (rel_op, Operand::Value(Data::Null))
+        } else if let Some(TokenKind::RelOp(rel_op)) = self.peek() {
+            let rel_op = *rel_op;
+            self.pos += 1;
+            (
+                rel_op,
+                self.operand("Did not find an Operand after the RelOp.")?,
+            )
+        } else if let Operand::Identifier(_) = left_op {
+            (
+                RelOp::Equals,
+                Operand::Value(Data::Boolean(Boolean::wrap(true))),
+            )
+        } else {
+            return Err(DBError::ParseError("Did not find a valid RelOp."));
+        };
+
+        Ok(vec![Constraint {
+            left_op,
+            rel_op,
+            right_op,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Integer;
+
+    fn names(identifiers: &[Identifier]) -> Vec<&str> {
+        identifiers
+            .iter()
+            .map(|identifier| identifier.name())
+            .collect()
+    }
+
+    fn int(value: i32) -> Data {
+        Data::Integer(Integer::wrap(value))
+    }
+
+    #[test]
+    fn parses_every_statement() {
+        let parse = |cmd: &str| parse_statement(cmd).unwrap();
+        assert!(matches!(
+            parse("CREATE DATABASE shop"),
+            Statement::CreateDatabase(name) if name.name() == "shop"
+        ));
+        assert!(matches!(parse("USE shop"), Statement::Use(name) if name.name() == "shop"));
+        assert!(matches!(parse("DESCRIBE ALL"), Statement::Describe(None)));
+        assert!(matches!(
+            parse("describe items"),
+            Statement::Describe(Some(name)) if name.name() == "items"
+        ));
+        assert!(matches!(
+            parse("DROP INDEX by_price"),
+            Statement::DropIndex(name) if name.name() == "by_price"
+        ));
+        assert!(matches!(parse("BEGIN"), Statement::Begin));
+        assert!(matches!(parse("COMMIT"), Statement::Commit));
+        assert!(matches!(parse("ROLLBACK"), Statement::Rollback));
+        assert!(matches!(parse("EXIT"), Statement::Exit));
+
+        let Statement::CreateTable { name, columns, primary_key } = parse(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name VARCHAR(20) NOT NULL DEFAULT \"none\",
+            price DECIMAL(8, 2) CHECK (price > 0),
+            maker INTEGER REFERENCES makers(id) ON DELETE CASCADE)",
+        ) else {
+            panic!("not a CREATE TABLE");
+        };
+        assert_eq!(name.name(), "items");
+        let column_names: Vec<&str> = columns.iter().map(|column| column.name.name()).collect();
+        assert_eq!(column_names, ["id", "name", "price", "maker"]);
+        assert!(columns[0].primary_key && !columns[1].primary_key);
+        assert!(matches!(columns[1].domain, Domain::VarChar(20)));
+        assert!(columns[1].constraints.not_null);
+        assert_eq!(
+            columns[1].constraints.default,
+            Some(Data::Text(Text::from("none").unwrap()))
+        );
+        assert!(matches!(columns[2].domain, Domain::Decimal(8, 2)));
+        assert!(columns[2].constraints.check.is_some());
+        let reference = columns[3].constraints.references.as_ref().unwrap();
+        assert_eq!(reference.table.name(), "makers");
+        assert!(matches!(reference.on_delete, RefAction::Cascade));
+        assert!(matches!(reference.on_update, RefAction::Restrict));
+        assert_eq!(columns[3].referenced_key.as_ref().unwrap().name(), "id");
+        assert!(primary_key.is_none());
+
+        let Statement::CreateTable { primary_key, .. } =
+            parse("CREATE TABLE pairs (a INTEGER, b TEXT, PRIMARY KEY (a, b))")
+        else {
+            panic!("not a CREATE TABLE");
+        };
+        assert_eq!(names(&primary_key.unwrap()), ["a", "b"]);
+
+        let Statement::CreateIndex {
+            name,
+            table,
+            attribute,
+        } = parse("CREATE INDEX by_price ON items (price)")
+        else {
+            panic!("not a CREATE INDEX");
+        };
+        assert_eq!(
+            [name.name(), table.name(), attribute.name()],
+            ["by_price", "items", "price"]
+        );
+
+        let Statement::Select(select) =
+            parse("SELECT id, name FROM items, makers WHERE id > 3 ORDER BY name DESC, id LIMIT 5")
+        else {
+            panic!("not a SELECT");
+        };
+        let Projection::Attributes(ref projection) = select.projection else {
+            panic!("not a projection of attributes");
+        };
+        assert_eq!(names(projection), ["id", "name"]);
+        assert_eq!(names(&select.tables), ["items", "makers"]);
+        assert!(!select.condition.is_empty());
+        let order_by: Vec<(&str, bool)> = select
+            .order_by
+            .iter()
+            .map(|order| (order.attribute.name(), order.descending))
+            .collect();
+        assert_eq!(order_by, [("name", true), ("id", false)]);
+        let limit = select.limit.unwrap();
+        assert_eq!((limit.count, limit.offset), (5, 0));
+        let Statement::Select(select) = parse("SELECT ALL FROM items LIMIT 5 OFFSET 2") else {
+            panic!("not a SELECT");
+        };
+        let limit = select.limit.unwrap();
+        assert_eq!((limit.count, limit.offset), (5, 2));
+
+        let Statement::Select(select) = parse("SELECT COUNT(*), SUM(price) FROM items") else {
+            panic!("not a SELECT");
+        };
+        let Projection::Aggregates(aggregates) = select.projection else {
+            panic!("not a projection of aggregates");
+        };
+        assert!(
+            matches!(aggregates[0].function, Function::Count) && aggregates[0].attribute.is_none()
+        );
+        assert!(matches!(aggregates[1].function, Function::Sum));
+        assert_eq!(aggregates[1].attribute.as_ref().unwrap().name(), "price");
+        assert!(
+            select.condition.is_empty() && select.order_by.is_empty() && select.limit.is_none()
+        );
+
+        let Statement::Let { table, key, select } =
+            parse("LET cheap KEY id SELECT ALL FROM items WHERE price < 5")
+        else {
+            panic!("not a LET");
+        };
+        assert_eq!(table.name(), "cheap");
+        assert_eq!(key.unwrap().name(), "id");
+        assert!(matches!(select.projection, Projection::All));
+        assert!(matches!(
+            parse("LET copy KEY NONE SELECT ALL FROM items"),
+            Statement::Let { key: None, .. }
+        ));
+
+        let Statement::Insert { table, values } =
+            parse("INSERT items VALUES (1, \"pen\", DEFAULT, NULL)")
+        else {
+            panic!("not an INSERT");
+        };
+        assert_eq!(table.name(), "items");
+        assert_eq!(
+            values,
+            [
+                Some(int(1)),
+                Some(Data::Text(Text::from("pen").unwrap())),
+                None,
+                Some(Data::Null)
+            ]
+        );
+
+        let Statement::Update {
+            table,
+            new_values,
+            condition,
+        } = parse("UPDATE items SET price = 2, maker = NULL WHERE id = 1")
+        else {
+            panic!("not an UPDATE");
+        };
+        assert_eq!(table.name(), "items");
+        let new_names: Vec<&str> = new_values.iter().map(|(name, _)| name.name()).collect();
+        assert_eq!(new_names, ["price", "maker"]);
+        assert_eq!(new_values[1].1, Data::Null);
+        assert!(!condition.is_empty());
+        assert!(matches!(
+            parse("UPDATE items SET price = 2"),
+            Statement::Update { condition, .. } if condition.is_empty()
+        ));
+
+        assert!(matches!(
+            parse("DELETE items WHERE id = 1"),
+            Statement::Delete {
+                condition: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("DELETE items"),
+            Statement::Delete {
+                condition: None,
+                ..
+            }
+        ));
+
+        let Statement::Input {
+            file_name,
+            output_name,
+        } = parse("INPUT cmds.txt OUTPUT out.txt")
+        else {
+            panic!("not an INPUT");
+        };
+        assert_eq!(
+            (file_name.as_str(), output_name.as_deref()),
+            ("cmds.txt", Some("out.txt"))
+        );
+
+        let Statement::Rename { table, attributes } = parse("RENAME items (a, b, c, d)") else {
+            panic!("not a RENAME");
+        };
+        assert_eq!(table.name(), "items");
+        assert_eq!(names(&attributes), ["a", "b", "c", "d"]);
+
+        for cmd in [
+            "",
+            "SELECT",
+            "SELECT id FROM",
+            "CREATE TABLE t",
+            "INSERT t VALUES (1",
+            "BEGIN now",
+            "DROP items",
+        ] {
+            assert!(parse_statement(cmd).is_err(), "{cmd}");
+        }
+    }
+
+    #[test]
+    fn reserved_keywords_are_not_identifiers() {
+        for keyword in [
+            "order",
+            "by",
+            "asc",
+            "desc",
+            "limit",
+            "offset",
+            "references",
+            "on",
+            "index",
+            "drop",
+            "begin",
+            "commit",
+            "rollback",
+            "between",
+        ] {
+            assert!(Identifier::from(keyword).is_err(), "{keyword}");
+            let as_table = format!("CREATE TABLE {keyword} (id INTEGER)");
+            assert!(parse_statement(&as_table).is_err(), "{as_table}");
+            let as_attribute = format!("CREATE TABLE t (id INTEGER, {keyword} INTEGER)");
+            assert!(parse_statement(&as_attribute).is_err(), "{as_attribute}");
+            let as_index = format!("CREATE INDEX {keyword} ON t (id)");
+            assert!(parse_statement(&as_index).is_err(), "{as_index}");
+        }
+        // keywords may still start a longer name
+        assert!(parse_statement("CREATE TABLE orders (ordered INTEGER, index_no INTEGER)").is_ok());
+    }
+}
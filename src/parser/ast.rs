@@ -0,0 +1,120 @@
+use crate::aggregate::Aggregate;
+use crate::base::{Data, Domain, Identifier};
+use crate::logic::{Condition, Limit, OrderBy};
+use crate::relation::ColumnConstraints;
+
+/// A command as read by the parser, holding everything needed to run it.
+pub enum Statement {
+    /// CREATE DATABASE name
+    CreateDatabase(Identifier),
+    /// CREATE TABLE name '(' column [, column]* [, PRIMARY KEY '(' attribute [, attribute]* ')'] ')'
+    CreateTable {
+        name: Identifier,
+        columns: Vec<ColumnDef>,
+        /// The attributes listed by a PRIMARY KEY clause after the columns
+        primary_key: Option<Vec<Identifier>>,
+    },
+    /// CREATE INDEX name ON table '(' attribute ')'
+    CreateIndex {
+        name: Identifier,
+        table: Identifier,
+        attribute: Identifier,
+    },
+    /// DROP INDEX name
+    DropIndex(Identifier),
+    /// USE name
+    Use(Identifier),
+    /// DESCRIBE table, or DESCRIBE ALL when None
+    Describe(Option<Identifier>),
+    /// SELECT ...
+    Select(Select),
+    /// LET table KEY attribute SELECT ..., where KEY NONE is None
+    Let {
+        table: Identifier,
+        key: Option<Identifier>,
+        select: Select,
+    },
+    /// INSERT table VALUES '(' value [, value]* ')', where DEFAULT is None
+    Insert {
+        table: Identifier,
+        values: Vec<Option<Data>>,
+    },
+    /// UPDATE table SET attribute = value [, attribute = value]* [WHERE condition]
+    Update {
+        table: Identifier,
+        new_values: Vec<(Identifier, Data)>,
+        condition: Condition,
+    },
+    /// DELETE table [WHERE condition], which deletes the whole table without a condition
+    Delete {
+        table: Identifier,
+        condition: Option<Condition>,
+    },
+    /// INPUT file_name [OUTPUT output_name]
+    Input {
+        file_name: String,
+        output_name: Option<String>,
+    },
+    /// RENAME table '(' attribute [, attribute]* ')'
+    Rename {
+        table: Identifier,
+        attributes: Vec<Identifier>,
+    },
+    Begin,
+    Commit,
+    Rollback,
+    Exit,
+}
+
+impl Statement {
+    /// Returns true for the Statements which change the schema or the current database,
+    /// which ROLLBACK cannot undo.
+    pub fn changes_schema(&self) -> bool {
+        matches!(
+            self,
+            Statement::CreateDatabase(_)
+                | Statement::CreateTable { .. }
+                | Statement::CreateIndex { .. }
+                | Statement::DropIndex(_)
+                | Statement::Use(_)
+                | Statement::Let { .. }
+                | Statement::Delete {
+                    condition: None,
+                    ..
+                }
+        )
+    }
+}
+
+/// SELECT (ALL | attribute [, attribute]* | aggregate [, aggregate]*) FROM table [, table]* [WHERE condition]
+/// [ORDER BY attribute [ASC|DESC] [, attribute [ASC|DESC]]*] [LIMIT count [OFFSET offset]]
+pub struct Select {
+    pub projection: Projection,
+    pub tables: Vec<Identifier>,
+    pub condition: Condition,
+    /// The attributes to sort by, which do not have to be projected. Empty without ORDER BY.
+    pub order_by: Vec<OrderBy>,
+    /// The records to keep after sorting, or None for all of them
+    pub limit: Option<Limit>,
+}
+
+/// What a SELECT lists for each record it selects.
+pub enum Projection {
+    /// SELECT ALL
+    All,
+    /// The attributes to project
+    Attributes(Vec<Identifier>),
+    /// The aggregate functions to compute across the selected records, which give a single record
+    Aggregates(Vec<Aggregate>),
+}
+
+/// The definition of one attribute in CREATE TABLE.
+pub struct ColumnDef {
+    pub name: Identifier,
+    pub domain: Domain,
+    pub primary_key: bool,
+    /// The DEFAULT value is kept as the parser read it until it is converted to the attribute's Domain
+    pub constraints: ColumnConstraints,
+    /// The attribute named by REFERENCES table '(' attribute ')'
+    pub referenced_key: Option<Identifier>,
+}
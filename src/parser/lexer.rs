@@ -0,0 +1,133 @@
+use crate::base::Text;
+use crate::logic::{chars_in_literals, RelOp};
+use crate::{DBError, SyntaxError};
+
+/// The kinds of tokens that commands are made of.
+#[derive(Clone, PartialEq)]
+pub enum TokenKind {
+    /// A keyword, identifier, number, date, or timestamp. These are runs of ascii-alphanumeric
+    /// characters, '.', '-', '_', and ':', and are lowercased so that commands are not case-sensitive.
+    Word(String),
+    /// The content of a double quoted string literal, with its escape sequences read.
+    Str(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    RelOp(RelOp),
+    /// Any other character. These are only allowed in the file names of INPUT.
+    Other(char),
+}
+
+/// A token along with where it is found in the command it was read from.
+#[derive(Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The byte offset of the token's first character
+    pub start: usize,
+    /// The byte offset just past the token's last character
+    pub end: usize,
+}
+
+/// Attempts to split a command into its tokens. Whitespace only separates tokens.
+///
+/// # Errors
+///
+/// Fails with a SyntaxError at the string literal when it has no closing double quote or
+/// cannot be read by Text::from_literal.
+pub fn tokenize(cmd: &str) -> Result<Vec<Token>, DBError> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    while let Some(c) = cmd[start..].chars().next() {
+        let rest = &cmd[start..];
+        let (kind, len) = match c {
+            _ if c.is_whitespace() => {
+                start += c.len_utf8();
+                continue;
+            }
+            '"' => {
+                let (len, text) = match literal_end(rest) {
+                    Some(len) => (len, Text::from_literal(&rest[..len])),
+                    None => (
+                        rest.len(),
+                        Err(DBError::ParseError(
+                            "String literal is missing its closing double quote.",
+                        )),
+                    ),
+                };
+                let text = match text {
+                    Ok(text) => text,
+                    Err(DBError::ParseError(message)) => {
+                        let found = Some(rest[..len].to_string());
+                        let err = SyntaxError::new(message, start, found);
+                        return Err(DBError::SyntaxError(Box::new(err)));
+                    }
+                    Err(err) => return Err(err),
+                };
+                (TokenKind::Str(text.content().to_string()), len)
+            }
+            '(' => (TokenKind::LeftParen, 1),
+            ')' => (TokenKind::RightParen, 1),
+            ',' => (TokenKind::Comma, 1),
+            '=' => (TokenKind::RelOp(RelOp::Equals), 1),
+            '!' if rest.starts_with("!=") => (TokenKind::RelOp(RelOp::NotEqual), 2),
+            '<' if rest.starts_with("<=") => (TokenKind::RelOp(RelOp::LessThanOrEqual), 2),
+            '>' if rest.starts_with(">=") => (TokenKind::RelOp(RelOp::GreaterThanOrEqual), 2),
+            '<' => (TokenKind::RelOp(RelOp::LessThan), 1),
+            '>' => (TokenKind::RelOp(RelOp::GreaterThan), 1),
+            _ if is_word_char(c) => {
+                let len = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+                (TokenKind::Word(rest[..len].to_lowercase()), len)
+            }
+            _ => (TokenKind::Other(c), c.len_utf8()),
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: start + len,
+        });
+        start += len;
+    }
+    Ok(tokens)
+}
+
+/// Returns true for the characters which words are made of. ':' is only found in timestamps.
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || ['.', '-', '_', ':'].contains(&c)
+}
+
+/// Returns the length in bytes of the string literal which rest starts with, up to and including
+/// its closing double quote, or None when it has no closing double quote. A double quote which
+/// is written twice or after a backslash does not close the literal.
+fn literal_end(rest: &str) -> Option<usize> {
+    let mut escaped = false;
+    let mut chars = rest.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            match chars.peek() {
+                Some((_, '"')) => {
+                    chars.next();
+                }
+                _ => return Some(i + 1),
+            }
+        }
+    }
+    None
+}
+
+/// Returns cmd with everything outside of double quotes lowercased, the same way that
+/// tokenize lowercases words, while string literals keep their case.
+pub fn lowercase_outside_quotes(cmd: &str) -> String {
+    let mut lowercased = String::with_capacity(cmd.len());
+    for (_, c, in_literal) in chars_in_literals(cmd) {
+        if in_literal {
+            lowercased.push(c);
+        } else {
+            lowercased.extend(c.to_lowercase());
+        }
+    }
+    lowercased
+}
@@ -1,41 +1,255 @@
-use crate::base::{Data, Domain, Float, Identifier, Integer, Text};
-use crate::binary_search_tree::{BSTInsertErr, BST};
-use crate::logic::Condition;
+use crate::b_plus_tree::{check_key_len, BPlusTree};
+use crate::base::{
+    BigInt, Boolean, Data, Date, Decimal, Domain, Double, Float, Identifier, Integer, Key, Text,
+    Timestamp,
+};
+use crate::binary_search_tree::BST;
+use crate::logic::{replace_words, Condition};
+use crate::wal::{Wal, WalOp};
 use crate::DBError;
 use std::error::Error;
-use std::fs::{self, File, OpenOptions};
+use std::fs::{self, File};
 use std::io::{Read, Seek};
 use std::io::{SeekFrom, Write};
+use std::path::Path;
 use std::vec;
 
+/// The record number, old key, and new key of a record whose key is changed by an UPDATE
+type KeyChange = (usize, Key, Key);
+
+/// The record number, old record, and new record of a record changed by an UPDATE
+type RecordChange = (usize, Vec<Data>, Vec<Data>);
+
+/// The record number of a record to update and the labelled values to put in it
+pub type RecordUpdate = (usize, Vec<(Identifier, Data)>);
+
+/// Starts every table file. Files from before records had a null bitmap have no magic and start
+/// with the primary key number instead, whose first byte is always 0.
+const MAGIC: &[u8; 8] = b"MDBDAT05";
+
+/// Started table files from before Text was stored out of line, when every Text took FIXED_TEXT_LEN bytes
+const FIXED_TEXT_MAGIC: &[u8; 8] = b"MDBDAT04";
+
+/// Started table files from before Domains were written with parameters, when each took one byte
+const ONE_BYTE_DOMAIN_MAGIC: &[u8; 8] = b"MDBDAT03";
+
+/// Started table files from before attributes had constraints in their metadata
+const NO_CONSTRAINTS_MAGIC: &[u8; 8] = b"MDBDAT02";
+
+/// The byte that older table files wrote for the Float Domain, which Double replaced
+const FLOAT_DOMAIN: u8 = 2;
+
+/// The number of space padded bytes that older table files stored every Text in
+const FIXED_TEXT_LEN: u16 = 100;
+
+/// A way that older table files stored the values of an attribute which the current format does not
+#[derive(Clone, Copy)]
+enum LegacyValue {
+    /// 5 byte Floats, which are now Doubles
+    Float,
+    /// FIXED_TEXT_LEN byte Text, which is now a VarChar of the same limit
+    FixedText,
+}
+
+/// Tags which start each constraint kept with an attribute in a table's metadata
+const NOT_NULL_TAG: u8 = 0;
+const DEFAULT_TAG: u8 = 1;
+const CHECK_TAG: u8 = 2;
+const REFERENCES_TAG: u8 = 3;
+
+/// What happens to the records that reference a record when it is deleted or its key is changed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RefAction {
+    /// The delete or update fails
+    Restrict,
+    /// The referencing records are deleted too, or changed to reference the new key
+    Cascade,
+    /// The referencing records are changed to reference nothing with Data::Null
+    SetNull,
+}
+
+impl RefAction {
+    /// Attempt to read a RefAction out of the words which follow ON DELETE or ON UPDATE.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the words are not 'restrict', 'cascade', or 'set null'.
+    pub fn from(descriptor: &str) -> Result<Self, DBError> {
+        match descriptor {
+            "restrict" => Ok(RefAction::Restrict),
+            "cascade" => Ok(RefAction::Cascade),
+            "set null" => Ok(RefAction::SetNull),
+            _ => Err(DBError::ParseError("Invalid REFERENCES action.")),
+        }
+    }
+
+    /// Returns a string representation for a given RefAction variant.
+    pub fn to_string(self) -> &'static str {
+        match self {
+            RefAction::Restrict => "RESTRICT",
+            RefAction::Cascade => "CASCADE",
+            RefAction::SetNull => "SET NULL",
+        }
+    }
+
+    /// Reads a RefAction back from the u8 written for it in a table's metadata.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the u8 does not stand for a RefAction.
+    fn from_byte(byte: u8) -> Result<Self, DBError> {
+        match byte {
+            0 => Ok(RefAction::Restrict),
+            1 => Ok(RefAction::Cascade),
+            2 => Ok(RefAction::SetNull),
+            _ => Err(DBError::FileFormatError(
+                "Incorrect REFERENCES action read.",
+            )),
+        }
+    }
+}
+
+/// A REFERENCES constraint, which requires an attribute to hold the primary key of a record in
+/// the referenced table or Data::Null. Only tables with a primary key of one attribute can be referenced.
+#[derive(Clone)]
+pub struct Reference {
+    pub table: Identifier,
+    pub on_delete: RefAction,
+    pub on_update: RefAction,
+}
+
+/// The NOT NULL, DEFAULT, CHECK, and REFERENCES constraints that a column definition puts on an attribute.
+#[derive(Clone, Default)]
+pub struct ColumnConstraints {
+    pub not_null: bool,
+    /// The value INSERT gives the attribute when it is not given one. Data::Null if None.
+    pub default: Option<Data>,
+    /// A Condition that no record may make false. Once in a Table, each attribute in it is
+    /// written as '#{attri_num}' so that RENAME does not need to change it.
+    pub check: Option<String>,
+    pub references: Option<Reference>,
+}
+
+impl ColumnConstraints {
+    /// Returns the byte representation of the constraints as they are stored in the table's
+    /// metadata. This is a u8 count followed by a u8 tag, u16 length, and bytes for each.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut constraints: Vec<(u8, Vec<u8>)> = Vec::new();
+        if self.not_null {
+            constraints.push((NOT_NULL_TAG, Vec::new()));
+        }
+        if let Some(ref default) = self.default {
+            constraints.push((DEFAULT_TAG, default.as_bytes()));
+        }
+        if let Some(ref check) = self.check {
+            constraints.push((CHECK_TAG, check.as_bytes().to_vec()));
+        }
+        if let Some(ref reference) = self.references {
+            let mut reference_bytes = vec![reference.on_delete as u8, reference.on_update as u8];
+            reference_bytes.extend_from_slice(reference.table.name().as_bytes());
+            constraints.push((REFERENCES_TAG, reference_bytes));
+        }
+
+        let mut bytes = vec![constraints.len() as u8];
+        for (tag, constraint_bytes) in constraints {
+            bytes.push(tag);
+            bytes.extend_from_slice(&(constraint_bytes.len() as u16).to_be_bytes());
+            bytes.extend(constraint_bytes);
+        }
+        bytes
+    }
+
+    /// Attempts to read constraints written by to_bytes from the front of file.
+    /// Returns them along with the number of bytes read.
+    ///
+    /// # Errors
+    ///
+    /// Fails when cannot read from the file or when the constraints are in a bad format.
+    fn read_from(mut file: impl Read) -> Result<(Self, usize), Box<dyn Error>> {
+        let mut constraints = ColumnConstraints::default();
+        let mut count: [u8; 1] = [0];
+        file.read_exact(&mut count)?;
+        let mut bytes_read = 1;
+        for _ in 0..count[0] {
+            let mut tag_and_len: [u8; 3] = [0; 3];
+            file.read_exact(&mut tag_and_len)?;
+            let mut constraint_bytes =
+                vec![0; u16::from_be_bytes([tag_and_len[1], tag_and_len[2]]) as usize];
+            file.read_exact(&mut constraint_bytes)?;
+            bytes_read += 3 + constraint_bytes.len();
+
+            match tag_and_len[0] {
+                NOT_NULL_TAG => constraints.not_null = true,
+                DEFAULT_TAG => constraints.default = Some(Data::from_bytes(&constraint_bytes)?),
+                CHECK_TAG => constraints.check = Some(String::from_utf8(constraint_bytes)?),
+                REFERENCES_TAG if constraint_bytes.len() > 2 => {
+                    constraints.references = Some(Reference {
+                        table: Identifier::from(std::str::from_utf8(&constraint_bytes[2..])?)?,
+                        on_delete: RefAction::from_byte(constraint_bytes[0])?,
+                        on_update: RefAction::from_byte(constraint_bytes[1])?,
+                    })
+                }
+                _ => Err(DBError::FileFormatError(
+                    "Incorrect attribute constraint read.",
+                ))?,
+            }
+        }
+        Ok((constraints, bytes_read))
+    }
+}
+
+/// The state of a Table's open transaction at the start of a statement, as given by Table.savepoint.
+pub struct Savepoint {
+    pending_len: usize,
+    shadowed_len: usize,
+    record_count: usize,
+    heap_len: u64,
+}
+
 /// An object providing table management in files.
-/// Tables will save themselves in .dat files and save
-/// their bsts in .index files.
+/// Tables will save themselves in .dat files, keep
+/// their primary key indexes in .index files, keep
+/// each secondary index in a '{table}.{index}.idx' file,
+/// and keep the values of Text attributes in a .heap file.
 pub struct Table {
     attributes: Vec<(Identifier, Domain)>,
+    /// The constraints on each attribute, in the same order as attributes
+    constraints: Vec<ColumnConstraints>,
     record_count: usize,
     meta_offset: usize,
-    pub bst: Option<BST>,
-    pub key_attri_num: Option<usize>,
+    pub index: Option<BPlusTree>,
+    /// Named indexes which may hold the same key for many records
+    secondary_indexes: Vec<(Identifier, BPlusTree)>,
+    /// The attribute numbers that make up the primary key, in key order. Empty without a key.
+    pub key_attri_nums: Vec<usize>,
     record_length: u32,
     pub file_path: String,
+    /// The length of the heap file which holds the values of the Table's Text attributes
+    heap_len: u64,
+    wal: Wal,
+    /// Changes made since BEGIN which have not been committed. None outside of a transaction.
+    pending: Option<Vec<WalOp>>,
+    /// Files which have a private shadow copy for the open transaction
+    shadowed: Vec<String>,
 }
 
 impl Table {
     /// Create an instance of Table and save its metadata to a file. Also
-    /// create the file for the bst if specified.
+    /// create the file for the index if a primary key is specified.
     /// The files created will use the format '{dir}{name}.{ext}'
     ///
     /// # Errors
     ///
-    /// Fails when cannot write to the filesystem or
+    /// Fails when cannot write to the filesystem, when the table's file already exists, or
     /// when there are two attributes given in the list that have the
-    /// same name. Also requires that the primary_key, if specified,
-    /// must be within the bounds of the table's attributes.
+    /// same name. Also requires that the attributes of the primary_key, if any
+    /// are given, must be within the bounds of the table's attributes and distinct,
+    /// and that each CHECK constraint is a Condition on the table's attributes.
     pub fn build(
         name: &str,
         attributes: Vec<(Identifier, Domain)>,
-        primary_key: Option<usize>,
+        mut constraints: Vec<ColumnConstraints>,
+        primary_key: Vec<usize>,
         dir: &str,
     ) -> Result<Self, Box<dyn Error>> {
         for (i, attri1) in attributes.iter().enumerate() {
@@ -48,86 +262,471 @@ impl Table {
             }
         }
 
-        let name = Identifier::from(name)?; // reject name if not an identifier
-        let mut record_length: u32 = 0;
-        let meta_offset = attributes.len() * 20 + 24; //  16 bytes at the beginning + 19 bytes per + 1 byte per + 8 bytes at the end
-        for (_, domain) in attributes.iter() {
-            record_length += Domain::size_in_bytes(domain);
-        }
-
-        let file_path = format!("{dir}{}.dat", name.name());
-        let mut file = File::create_new(&file_path)?;
-
-        if let Some(prim_key_num) = primary_key {
-            if prim_key_num < attributes.len() {
-                file.write_all(&prim_key_num.to_be_bytes())?;
-            } else {
+        for (i, prim_key_num) in primary_key.iter().enumerate() {
+            if *prim_key_num >= attributes.len() {
                 return Err(Box::new(DBError::ConstraintError(
                     "Primary Key attribute index cannot be larger than the number of attributes.",
                 )));
             }
-        } else {
-            file.write_all(&0_usize.to_be_bytes())?;
+            if primary_key[..i].contains(prim_key_num) {
+                return Err(Box::new(DBError::ConstraintError(
+                    "Cannot use an attribute twice in a Primary Key.",
+                )));
+            }
         }
 
-        // attribute list size
-        file.write_all(&attributes.len().to_be_bytes())?; // 8 bytes
-
-        for (attribute, domain) in attributes.iter() {
-            attribute.write_to_file(&file)?;
-            domain.write_to_file(&file)?;
+        let name = Identifier::from(name)?; // reject name if not an identifier
+        for constraint in constraints.iter_mut() {
+            if let Some(ref mut check) = constraint.check {
+                *check = replace_words(check, |word| {
+                    attributes
+                        .iter()
+                        .position(|(identifier, _)| identifier.name() == word)
+                        .map(|attri_num| format!("#{attri_num}"))
+                });
+            }
         }
+        let record_length = Table::null_bitmap_len(attributes.len()) as u32
+            + attributes
+                .iter()
+                .map(|(_, domain)| domain.size_in_bytes())
+                .sum::<u32>();
 
-        // table_size
-        file.write_all(&0_usize.to_be_bytes())
-            .expect("Should be able to write to file."); // usize default
-
-        let (bst, key_attri_num) = if let Some(prim_key_num) = primary_key {
-            let bst_path = format!("{dir}{}.index", name.name());
-            let bst = BST::new();
-            bst.write_to_file(&bst_path)?; // this way the table will know it has a primary key based on the existence of this file
-            (Some(bst), Some(prim_key_num))
-        } else {
-            (None, None)
-        };
+        let file_path = format!("{dir}{}.dat", name.name());
+        let index_path = format!("{dir}{}.index", name.name());
 
-        Ok(Table {
+        let mut table = Table {
             attributes,
+            constraints,
             record_count: 0,
-            meta_offset,
-            bst,
-            key_attri_num,
+            meta_offset: 0,
+            index: if primary_key.is_empty() {
+                None
+            } else {
+                Some(BPlusTree::new(&index_path, true, primary_key.clone()))
+            },
+            secondary_indexes: Vec::new(),
+            key_attri_nums: primary_key,
             record_length,
             file_path,
-        })
+            heap_len: 0,
+            wal: Wal::open(dir),
+            pending: None,
+            shadowed: Vec::new(),
+        };
+
+        table.check_conditions()?;
+        if Path::new(&table.file_path).exists() {
+            Err(DBError::ConstraintError(
+                "Table with name given already exists.",
+            ))?
+        }
+
+        // the header write creates the file, so a crash never leaves a table file without one.
+        // this way the table will know it has a primary key based on the existence of the index file
+        let header = table.header_bytes()?;
+        table.meta_offset = header.len();
+        let mut ops = vec![WalOp::Write {
+            path: table.file_path.clone(),
+            offset: 0,
+            bytes: header,
+        }];
+        if table.has_heap() {
+            ops.push(WalOp::SetLen {
+                path: table.heap_path(),
+                len: 0,
+            });
+        }
+        table.apply_ops(ops)?;
+
+        Ok(table)
     }
 
-    /// Attempts to remove the table and its bst from the filesystem and consume the instance.
+    /// Attempts to remove the table and its indexes from the filesystem and consume the instance.
     ///
     /// # Errors
     ///
     /// Fails when cannot find or delete the files.
     pub fn clean_up(self) -> Result<(), Box<dyn Error>> {
-        fs::remove_file(&self.file_path)?;
-        match self.bst {
-            Some(_) => {
-                let mut bst_path = self.file_path.clone();
-                bst_path.replace_range(self.file_path.len() - 3.., "index");
-                fs::remove_file(&bst_path)?; // use full match to use the ? op
+        let mut ops = vec![WalOp::Remove {
+            path: self.file_path.clone(),
+        }];
+        if self.has_heap() {
+            ops.push(WalOp::Remove {
+                path: self.heap_path(),
+            });
+        }
+        if self.index.is_some() {
+            ops.push(WalOp::Remove {
+                path: self.index_path(),
+            });
+        }
+        for (_, index) in self.secondary_indexes.iter() {
+            ops.push(WalOp::Remove {
+                path: index.path().to_string(),
+            });
+        }
+        self.wal.commit(&ops)
+    }
+
+    /// Returns the path of the .index file that stores this Table's index
+    fn index_path(&self) -> String {
+        let mut index_path = self.file_path.clone();
+        index_path.replace_range(self.file_path.len() - 3.., "index");
+        index_path
+    }
+
+    /// Returns the path of the .heap file that stores the values of this Table's Text attributes
+    fn heap_path(&self) -> String {
+        let mut heap_path = self.file_path.clone();
+        heap_path.replace_range(self.file_path.len() - 3.., "heap");
+        heap_path
+    }
+
+    /// Returns true if this Table has Text attributes, and so a heap file to keep their values in.
+    fn has_heap(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|(_, domain)| *domain == Domain::Text)
+    }
+
+    /// Opens the heap file to read the values of Text attributes from, or returns None when
+    /// the Table has no heap.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the Table has a heap but its file cannot be opened.
+    fn open_heap(&self) -> Result<Option<File>, std::io::Error> {
+        if !self.has_heap() {
+            return Ok(None);
+        }
+        Ok(Some(File::open(self.read_path(&self.heap_path()))?))
+    }
+
+    /// Returns the path of the .idx file that stores the secondary index named index_name
+    fn secondary_index_path(&self, index_name: &str) -> String {
+        let table_path = &self.file_path[..self.file_path.len() - 4]; // without the .dat
+        format!("{table_path}.{index_name}.idx")
+    }
+
+    /// Returns the path of the .sort file which holds run number run of an external merge sort
+    /// of a selection from this Table
+    pub fn sort_run_path(&self, run: usize) -> String {
+        let table_path = &self.file_path[..self.file_path.len() - 4]; // without the .dat
+        format!("{table_path}.{run}.sort")
+    }
+
+    /// Returns every index of this Table, starting with the primary key index if there is one.
+    fn trees_mut(&mut self) -> impl Iterator<Item = &mut BPlusTree> {
+        self.index
+            .iter_mut()
+            .chain(self.secondary_indexes.iter_mut().map(|(_, index)| index))
+    }
+
+    /// Returns the secondary index on the attribute numbered attri_num if there is one.
+    pub fn secondary_index_on(&self, attri_num: usize) -> Option<&BPlusTree> {
+        self.secondary_indexes
+            .iter()
+            .map(|(_, index)| index)
+            .find(|index| index.columns() == [attri_num])
+    }
+
+    /// Returns true if this Table has a secondary index named index_name.
+    pub fn has_secondary_index(&self, index_name: &str) -> bool {
+        self.secondary_indexes
+            .iter()
+            .any(|(name, _)| name.name() == index_name)
+    }
+
+    /// Returns the path of the private copy kept for the file at path during a transaction
+    fn shadow_path(path: &str) -> String {
+        format!("{path}.txn")
+    }
+
+    /// Returns the path the table's file at path should be read from. Once an open transaction
+    /// has changed the file, this is the shadow copy holding the uncommitted changes.
+    fn read_path(&self, path: &str) -> String {
+        if self.shadowed.iter().any(|shadowed| shadowed == path) {
+            Table::shadow_path(path)
+        } else {
+            path.to_string()
+        }
+    }
+
+    /// Applies ops, along with every change made to the indexes since the last call, to the
+    /// table's files. Outside of a transaction the ops go through the write-ahead log as one
+    /// entry. Inside one they are only applied to shadow copies of the files, which nothing
+    /// outside of the transaction reads, and are kept until COMMIT.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the files cannot be copied or written to.
+    fn apply_ops(&mut self, mut ops: Vec<WalOp>) -> Result<(), Box<dyn Error>> {
+        for index in self.trees_mut() {
+            ops.append(&mut index.take_ops()?);
+        }
+
+        let pending = match self.pending {
+            Some(ref mut pending) => pending,
+            None => return self.wal.commit(&ops),
+        };
+
+        for op in ops.iter() {
+            let path = op.path().to_string();
+            let shadow_path = Table::shadow_path(&path);
+            if !self.shadowed.contains(&path) {
+                if Path::new(&path).exists() {
+                    fs::copy(&path, &shadow_path)?;
+                }
+                self.shadowed.push(path);
+            }
+            op.with_path(shadow_path).apply()?;
+        }
+        pending.extend(ops);
+
+        // the indexes must now read the pages they have written back from their shadows
+        let shadowed = std::mem::take(&mut self.shadowed);
+        for index in self.trees_mut() {
+            if shadowed.iter().any(|path| path == index.path()) {
+                let shadow_path = Table::shadow_path(index.path());
+                index.read_from(shadow_path);
             }
-            None => (),
         }
+        self.shadowed = shadowed;
         Ok(())
     }
 
+    /// Throws away the changes made to the indexes since the last apply_ops. Used when
+    /// a statement fails after it has started changing the indexes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when an index cannot be read back from its file.
+    fn discard_index_changes(&mut self) -> Result<(), Box<dyn Error>> {
+        for index in self.trees_mut() {
+            index.discard_changes()?;
+        }
+        Ok(())
+    }
+
+    /// Starts buffering this table's changes for a transaction.
+    pub fn begin_transaction(&mut self) {
+        self.pending = Some(Vec::new());
+    }
+
+    /// Returns true if this table has uncommitted changes in the open transaction.
+    pub fn has_pending(&self) -> bool {
+        matches!(self.pending, Some(ref pending) if !pending.is_empty())
+    }
+
+    /// Takes the changes buffered since BEGIN so they can be committed through the
+    /// write-ahead log together with those of the other tables.
+    pub fn take_pending(&mut self) -> Vec<WalOp> {
+        match self.pending {
+            Some(ref mut pending) => std::mem::take(pending),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns how far the open transaction has come, so that the changes of a statement which
+    /// fails partway can be thrown away with rollback_to.
+    pub fn savepoint(&self) -> Savepoint {
+        Savepoint {
+            pending_len: self.pending.as_ref().map_or(0, Vec::len),
+            shadowed_len: self.shadowed.len(),
+            record_count: self.record_count,
+            heap_len: self.heap_len,
+        }
+    }
+
+    /// Throws away the changes made in the open transaction since savepoint was taken. The shadow
+    /// copies the changes were applied to are copied again from the table's files and given
+    /// only the changes made before savepoint, and the indexes forget the pages they read.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the shadow copies cannot be rebuilt or an index cannot be read back.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) -> Result<(), Box<dyn Error>> {
+        let pending = match self.pending {
+            Some(ref mut pending) => pending,
+            None => return Ok(()),
+        };
+        self.record_count = savepoint.record_count;
+        self.heap_len = savepoint.heap_len;
+        if pending.len() == savepoint.pending_len {
+            return self.discard_index_changes(); // nothing was applied since the savepoint
+        }
+        pending.truncate(savepoint.pending_len);
+
+        // files first changed after the savepoint are read from the table's own files again
+        for path in self.shadowed.drain(savepoint.shadowed_len..) {
+            let shadow_path = Table::shadow_path(&path);
+            if Path::new(&shadow_path).exists() {
+                fs::remove_file(shadow_path)?;
+            }
+        }
+        for path in self.shadowed.iter() {
+            let shadow_path = Table::shadow_path(path);
+            if Path::new(&shadow_path).exists() {
+                fs::remove_file(&shadow_path)?;
+            }
+            if Path::new(path).exists() {
+                fs::copy(path, &shadow_path)?;
+            }
+            for op in pending.iter().filter(|op| op.path() == path) {
+                op.with_path(shadow_path.clone()).apply()?;
+            }
+        }
+
+        let shadowed = std::mem::take(&mut self.shadowed);
+        for index in self.trees_mut() {
+            let read_path = match shadowed.iter().any(|path| path == index.path()) {
+                true => Table::shadow_path(index.path()),
+                false => index.path().to_string(),
+            };
+            index.read_from(read_path);
+            index.reload()?;
+        }
+        self.shadowed = shadowed;
+        Ok(())
+    }
+
+    /// Stops buffering changes and removes the table's shadow copies. Once the changes
+    /// are committed the shadows match the real files, and on rollback they are thrown away.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the shadow copies cannot be removed.
+    pub fn end_transaction(&mut self) -> Result<(), std::io::Error> {
+        self.pending = None;
+        for path in self.shadowed.drain(..) {
+            fs::remove_file(Table::shadow_path(&path))?;
+        }
+        for index in self.trees_mut() {
+            let path = index.path().to_string();
+            index.read_from(path);
+        }
+        Ok(())
+    }
+
+    /// Returns the metadata that starts the table's file. This is the magic, the primary key
+    /// number, the attribute list, the constraints on each attribute, and the record count.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the metadata cannot be formatted.
+    fn header_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut header: Vec<u8> = Vec::with_capacity(self.meta_offset);
+        header.write_all(MAGIC)?;
+        // any other key attributes are kept in the index header
+        let key_attri_num = self.key_attri_nums.first().copied().unwrap_or(0);
+        header.write_all(&key_attri_num.to_be_bytes())?;
+
+        // attribute list size
+        header.write_all(&self.attributes.len().to_be_bytes())?; // 8 bytes
+
+        for (attribute, domain) in self.attributes.iter() {
+            attribute.write_to_file(&mut header)?;
+            domain.write_to_file(&mut header)?;
+        }
+
+        for constraints in self.constraints.iter() {
+            header.write_all(&constraints.to_bytes())?;
+        }
+
+        // table_size
+        header.write_all(&self.record_count.to_be_bytes())?;
+        Ok(header)
+    }
+
     /// Returns a reference to the Table's attributes list
     pub fn attributes(&self) -> &Vec<(Identifier, Domain)> {
         &self.attributes
     }
 
+    /// Returns the number of records in the Table, which is kept in memory
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /// Returns a reference to the constraints on each of the Table's attributes
+    pub fn constraints(&self) -> &Vec<ColumnConstraints> {
+        &self.constraints
+    }
+
+    /// Returns the value the attribute at attri_num is given by INSERT when it is not given one.
+    ///
+    /// # Panics
+    ///
+    /// Panics when attri_num is out of the bounds of the attributes list.
+    pub fn default_value(&self, attri_num: usize) -> Data {
+        self.constraints[attri_num]
+            .default
+            .clone()
+            .unwrap_or(Data::Null)
+    }
+
+    /// Returns the CHECK constraint of the attribute at attri_num with the attribute names put
+    /// back in, as it would be written in CREATE TABLE.
+    fn check_text(&self, attri_num: usize) -> Option<String> {
+        let check = self.constraints[attri_num].check.as_ref()?;
+        Some(replace_words(check, |word| {
+            let attri_num: usize = word.strip_prefix('#')?.parse().ok()?;
+            let (identifier, _) = self.attributes.get(attri_num)?;
+            Some(identifier.name().to_string())
+        }))
+    }
+
+    /// Returns the CHECK constraints of the Table's attributes as Conditions converted to
+    /// evaluate the Table's records.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a CHECK constraint is not a Condition on the Table's attributes.
+    fn check_conditions(&self) -> Result<Vec<Condition>, Box<dyn Error>> {
+        let mut checks = Vec::new();
+        for attri_num in 0..self.attributes.len() {
+            if let Some(check) = self.check_text(attri_num) {
+                let mut check = Condition::parse(&check)?;
+                check.convert_with(&[self])?;
+                checks.push(check);
+            }
+        }
+        Ok(checks)
+    }
+
+    /// Checks that record meets the NOT NULL constraint of each attribute, makes
+    /// none of the CHECK constraints in checks false, and has keys short enough for its indexes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the record breaks one of the constraints.
+    fn check_constraints(&self, record: &Vec<Data>, checks: &[Condition]) -> Result<(), DBError> {
+        for (data, constraints) in record.iter().zip(self.constraints.iter()) {
+            if constraints.not_null && *data == Data::Null {
+                return Err(DBError::ConstraintError(
+                    "Cannot set an attribute which is NOT NULL to NULL.",
+                ));
+            }
+        }
+        if checks.iter().any(|check| !check.allows(record)) {
+            return Err(DBError::ConstraintError(
+                "Record does not meet the CHECK constraint of an attribute.",
+            ));
+        }
+        let secondary_indexes = self.secondary_indexes.iter().map(|(_, index)| index);
+        for index in self.index.iter().chain(secondary_indexes) {
+            check_key_len(&Key::from_record(record, index.columns()))?;
+        }
+        Ok(())
+    }
+
     /// Attempts to read the metadata of a Table from the file given by
     /// '{dir}{name}.dat'. Note, this will search for a file in the form
-    /// '{dir}{name}.index' that represents a bst for this table and load that as well.
+    /// '{dir}{name}.index' that holds the index for this table and open that as well,
+    /// along with every secondary index file in the form '{dir}{name}.{index}.idx'.
+    /// An index file from before indexes were paged is rebuilt from the records, and
+    /// a table file in an older format is rewritten in the current one.
     /// Returns the instance of Table read if successful.
     ///
     /// # Errors
@@ -140,88 +739,306 @@ impl Table {
         let _ = Identifier::from(name)?; // does copy the string but more importantly fails if name is not an identifier
         let name = name.to_string();
         let file_path = format!("{dir}{name}.dat");
-        let bst_file_path = format!("{dir}{name}.index");
-        let bst = match BST::read_from_file(&bst_file_path) {
-            Ok(bst) => Some(bst),
-            Err(_) => None, // fails if the file is not there or in a bad format
-        };
+        let index_path = format!("{dir}{name}.index");
+        let has_index = Path::new(&index_path).exists();
+        let index = BPlusTree::open_if_current(&index_path)?; // None if the file is not there or in an older format
+
+        let mut secondary_indexes = Vec::new();
+        for file in fs::read_dir(dir)? {
+            let file_name = file?.file_name();
+            let index_name = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(&format!("{name}.")))
+                .and_then(|file_name| file_name.strip_suffix(".idx"));
+            if let Some(index_name) = index_name {
+                let index_path = format!("{dir}{name}.{index_name}.idx");
+                secondary_indexes
+                    .push((Identifier::from(index_name)?, BPlusTree::open(&index_path)?));
+            }
+        }
 
         let mut file = File::open(&file_path)?;
 
         let mut key_attri_num: [u8; 8] = [0; 8];
         file.read_exact(&mut key_attri_num)?;
+        let is_current = &key_attri_num == MAGIC;
+        let has_domain_params = is_current || &key_attri_num == FIXED_TEXT_MAGIC;
+        let has_constraints = has_domain_params || &key_attri_num == ONE_BYTE_DOMAIN_MAGIC;
+        let has_null_bitmap = has_constraints || &key_attri_num == NO_CONSTRAINTS_MAGIC;
+        if has_null_bitmap {
+            file.read_exact(&mut key_attri_num)?;
+        }
         let key_attri_num = usize::from_be_bytes(key_attri_num);
 
-        let key_attri_num = if let Some(_) = bst {
-            Some(key_attri_num)
-        } else {
-            None
+        // the index knows every key attribute, but an index which must be rebuilt only had one
+        let key_attri_nums = match (has_index, &index) {
+            (true, Some(index)) => index.columns().to_vec(),
+            (true, None) => vec![key_attri_num],
+            (false, _) => Vec::new(),
         };
 
         let mut attribute_list_len: [u8; 8] = [0; 8];
         file.read_exact(&mut attribute_list_len)?;
         let attribute_list_len = usize::from_be_bytes(attribute_list_len);
 
-        let meta_offset = attribute_list_len * 20 + 24;
+        // each attribute is its 19 byte name followed by its Domain
+        let attribute_len = if has_domain_params { 22 } else { 20 };
+        let mut meta_offset = attribute_list_len * attribute_len + 24;
+        if has_null_bitmap {
+            meta_offset += 8;
+        }
 
-        let mut attributes_bytes: Vec<u8> = vec![0; attribute_list_len as usize * 20];
+        let mut attributes_bytes: Vec<u8> = vec![0; attribute_list_len * attribute_len];
         file.read_exact(&mut attributes_bytes)?;
 
+        let mut constraints = Vec::with_capacity(attribute_list_len);
+        for _ in 0..attribute_list_len {
+            if has_constraints {
+                let (constraint, bytes_read) = ColumnConstraints::read_from(&mut file)?;
+                meta_offset += bytes_read;
+                constraints.push(constraint);
+            } else {
+                constraints.push(ColumnConstraints::default());
+            }
+        }
+
         let mut record_count: [u8; 8] = [0; 8];
         file.read_exact(&mut record_count)?;
         let record_count = usize::from_be_bytes(record_count);
 
-        let mut attributes: Vec<(Identifier, Domain)> =
-            Vec::with_capacity(attribute_list_len as usize);
+        let mut attributes: Vec<(Identifier, Domain)> = Vec::with_capacity(attribute_list_len);
+        let mut legacy_values = Vec::with_capacity(attribute_list_len); // kept until the file is upgraded
+        let mut record_length = Table::null_bitmap_len(attribute_list_len) as u32;
 
-        let mut record_length: u32 = 0;
-        for _ in 0..attribute_list_len {
-            let domain = attributes_bytes.remove(attributes_bytes.len() - 1);
-            let domain = match domain {
-                0 => Domain::Integer,
-                1 => Domain::Text,
-                2 => Domain::Float,
-                _ => {
-                    return Err(Box::new(DBError::FileFormatError(
-                        "Incorrect Domain type read.",
-                    )))
-                }
+        for attribute_bytes in attributes_bytes.chunks(attribute_len) {
+            let identifier = String::from_utf8(attribute_bytes[..19].to_vec())?; // 19 is identifier size written
+            let identifier = Identifier::from(identifier.trim())?;
+            let domain_bytes = match has_domain_params {
+                true => attribute_bytes[19..].try_into().unwrap(),
+                false => [attribute_bytes[19], 0, 0],
+            };
+            let (domain, legacy_value) = match domain_bytes[0] {
+                _ if is_current => (Domain::from_bytes(&domain_bytes)?, None),
+                FLOAT_DOMAIN => (Domain::Double, Some(LegacyValue::Float)),
+                byte if byte == Domain::Text.byte() => (
+                    Domain::VarChar(FIXED_TEXT_LEN),
+                    Some(LegacyValue::FixedText),
+                ),
+                _ => (Domain::from_bytes(&domain_bytes)?, None),
             };
             record_length += Domain::size_in_bytes(&domain);
-            let identifier = attributes_bytes.split_off(attributes_bytes.len() - 19); // 19 is identifier size written
-            let identifier = String::from_utf8(identifier)?;
-            let identifier = Identifier::from(&identifier.trim())?;
             attributes.push((identifier, domain));
+            legacy_values.push(legacy_value);
         }
-        attributes.reverse(); // because read in reverse order... consider just reading this in reverse in other places and omitting this
+        let heap_len = fs::metadata(format!("{dir}{name}.heap")).map_or(0, |heap| heap.len());
 
-        Ok(Table {
+        let mut table = Table {
             attributes,
+            constraints,
             record_count,
             meta_offset,
-            bst,
-            key_attri_num,
+            index,
+            secondary_indexes,
+            key_attri_nums,
             record_length,
             file_path,
-        })
+            heap_len,
+            wal: Wal::open(dir),
+            pending: None,
+            shadowed: Vec::new(),
+        };
+
+        if !is_current {
+            table.upgrade_file(has_null_bitmap, &legacy_values)?;
+        }
+        if has_index && table.index.is_none() {
+            table.rebuild_index()?;
+        }
+        Ok(table)
+    }
+
+    /// Returns the number of bytes at the start of each record that mark which of its
+    /// attribute_count values are Data::Null, one bit per attribute.
+    fn null_bitmap_len(attribute_count: usize) -> usize {
+        attribute_count.div_ceil(8)
     }
 
-    /// Attempts to write the record_count of a Table instance to the file
-    /// the table was read from or originally wrote itself to. This is necessary
-    /// since record count does not need to be written every time a record is written
-    /// in the case of mass deletion or updates.
+    /// Rewrites a table file in an older format with the current metadata, adding an empty
+    /// null bitmap to the start of every record if they have none and turning the values of
+    /// each attribute with a legacy_value into the format of its Domain. The whole file goes
+    /// through the write-ahead log as one entry.
     ///
     /// # Errors
     ///
-    /// Fails when cannot write to the file.
-    pub fn write_record_count(&self) -> Result<(), Box<dyn Error>> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .append(false)
-            .open(&self.file_path)?;
-        file.seek(SeekFrom::Start(self.meta_offset as u64 - 8))?; // 8 bytes at the end of the meta
-        file.write(&self.record_count.to_be_bytes())?;
-        Ok(())
+    /// Fails when the file cannot be read from or written to or holds an invalid value.
+    fn upgrade_file(
+        &mut self,
+        has_null_bitmap: bool,
+        legacy_values: &[Option<LegacyValue>],
+    ) -> Result<(), Box<dyn Error>> {
+        let null_bitmap_len = Table::null_bitmap_len(self.attributes.len());
+        let prev_null_bitmap_len = if has_null_bitmap { null_bitmap_len } else { 0 };
+        let prev_value_lens: Vec<usize> = self
+            .attributes
+            .iter()
+            .zip(legacy_values)
+            .map(|((_, domain), legacy_value)| match legacy_value {
+                Some(LegacyValue::Float) => Float::byte_len(),
+                Some(LegacyValue::FixedText) => FIXED_TEXT_LEN as usize,
+                None => domain.size_in_bytes() as usize,
+            })
+            .collect();
+        let prev_record_length = prev_null_bitmap_len + prev_value_lens.iter().sum::<usize>();
+
+        let mut file = File::open(&self.file_path)?;
+        let mut records_bytes: Vec<u8> = vec![0; prev_record_length * self.record_count];
+        file.seek(SeekFrom::Start(self.meta_offset as u64))?;
+        file.read_exact(&mut records_bytes)?;
+
+        let mut file_bytes = self.header_bytes()?;
+        self.meta_offset = file_bytes.len();
+        for record_bytes in records_bytes.chunks(prev_record_length) {
+            let (null_bitmap, mut values_bytes) = record_bytes.split_at(prev_null_bitmap_len);
+            file_bytes.extend_from_slice(null_bitmap);
+            file_bytes.extend(vec![0; null_bitmap_len - prev_null_bitmap_len]);
+            for (attri_num, value_len) in prev_value_lens.iter().enumerate() {
+                let value_bytes;
+                (value_bytes, values_bytes) = values_bytes.split_at(*value_len);
+                let is_null =
+                    has_null_bitmap && null_bitmap[attri_num / 8] & (1 << (attri_num % 8)) != 0;
+                match legacy_values[attri_num] {
+                    Some(_) if is_null => file_bytes.resize(
+                        file_bytes.len() + self.attributes[attri_num].1.size_in_bytes() as usize,
+                        0,
+                    ),
+                    Some(LegacyValue::Float) => {
+                        let float = Float::from_bytes(value_bytes.try_into().unwrap())?;
+                        file_bytes.extend_from_slice(&Double::wrap(*float.value()).to_bytes());
+                    }
+                    Some(LegacyValue::FixedText) => file_bytes
+                        .extend(Text::from_bytes(value_bytes)?.to_varchar_bytes(FIXED_TEXT_LEN)?),
+                    None => file_bytes.extend_from_slice(value_bytes),
+                }
+            }
+        }
+
+        // the new file is at least as long as the old one so it covers every old byte
+        let file_op = WalOp::Write {
+            path: self.file_path.clone(),
+            offset: 0,
+            bytes: file_bytes,
+        };
+        self.apply_ops(vec![file_op])
+    }
+
+    /// Returns the op which writes record_count over the count stored at the end
+    /// of the table's metadata.
+    fn record_count_op(&self, record_count: usize) -> WalOp {
+        WalOp::Write {
+            path: self.file_path.clone(),
+            offset: self.meta_offset as u64 - 8, // 8 bytes at the end of the meta
+            bytes: record_count.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Returns the op which writes record_bytes over the record at record_num in the file.
+    fn record_op(&self, record_num: usize, record_bytes: Vec<u8>) -> WalOp {
+        WalOp::Write {
+            path: self.file_path.clone(),
+            offset: (self.meta_offset + record_num * self.record_length as usize) as u64,
+            bytes: record_bytes,
+        }
+    }
+
+    /// Returns the op which appends heap_bytes to the end of the heap file and counts them in heap_len.
+    fn heap_op(&mut self, heap_bytes: Vec<u8>) -> WalOp {
+        let offset = self.heap_len;
+        self.heap_len += heap_bytes.len() as u64;
+        WalOp::Write {
+            path: self.heap_path(),
+            offset,
+            bytes: heap_bytes,
+        }
+    }
+
+    /// Returns the byte representation of record as it is stored in the file. This is the null
+    /// bitmap followed by each value, where a Data::Null value is stored as zeroed bytes.
+    ///
+    /// A Text value is stored as the u64 offset and u32 length of its bytes in the heap. Its
+    /// bytes are pushed onto heap_bytes, which are to be appended to the heap, unless prev gives
+    /// the old version of the record and its bytes and the value is the same as it was there.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the record does not match the format of the Table instance
+    /// or when an attribute of the primary key is Data::Null.
+    fn record_to_bytes(
+        &self,
+        record: &[Data],
+        prev: Option<(&[Data], &[u8])>,
+        heap_bytes: &mut Vec<u8>,
+    ) -> Result<Vec<u8>, DBError> {
+        let mut record_bytes: Vec<u8> = Vec::with_capacity(self.record_length as usize);
+        record_bytes.resize(Table::null_bitmap_len(self.attributes.len()), 0);
+        for (i, (data, (_, domain))) in record.iter().zip(self.attributes.iter()).enumerate() {
+            match (data, domain) {
+                (Data::Null, domain) => {
+                    if self.key_attri_nums.contains(&i) {
+                        return Err(DBError::ConstraintError(
+                            "Primary Key attributes cannot be NULL.",
+                        ));
+                    }
+                    record_bytes[i / 8] |= 1 << (i % 8);
+                    record_bytes.resize(record_bytes.len() + domain.size_in_bytes() as usize, 0);
+                }
+                (Data::Integer(int), Domain::Integer) => {
+                    record_bytes.extend_from_slice(&int.to_bytes())
+                }
+                (Data::BigInt(int), Domain::BigInt) => {
+                    record_bytes.extend_from_slice(&int.to_bytes())
+                }
+                (Data::Double(double), Domain::Double) => {
+                    record_bytes.extend_from_slice(&double.to_bytes())
+                }
+                (Data::Decimal(decimal), Domain::Decimal(precision, scale)) => {
+                    record_bytes.extend_from_slice(&decimal.rescale(*precision, *scale)?.to_bytes())
+                }
+                (Data::Text(text), Domain::Text) => {
+                    let offset = record_bytes.len();
+                    match prev {
+                        // an unchanged value keeps its place in the heap
+                        Some((prev_record, prev_bytes)) if prev_record[i] == *data => {
+                            record_bytes.extend_from_slice(&prev_bytes[offset..offset + 12])
+                        }
+                        _ => {
+                            let heap_offset = self.heap_len + heap_bytes.len() as u64;
+                            heap_bytes.extend_from_slice(text.content().as_bytes());
+                            record_bytes.extend_from_slice(&heap_offset.to_be_bytes());
+                            record_bytes
+                                .extend_from_slice(&(text.content().len() as u32).to_be_bytes());
+                        }
+                    }
+                }
+                (Data::Text(text), Domain::VarChar(limit)) => {
+                    record_bytes.extend(text.to_varchar_bytes(*limit)?)
+                }
+                (Data::Boolean(boolean), Domain::Boolean) => {
+                    record_bytes.extend_from_slice(&boolean.to_bytes())
+                }
+                (Data::Date(date), Domain::Date) => {
+                    record_bytes.extend_from_slice(&date.to_bytes())
+                }
+                (Data::Timestamp(timestamp), Domain::Timestamp) => {
+                    record_bytes.extend_from_slice(&timestamp.to_bytes())
+                }
+                _ => {
+                    return Err(DBError::ConstraintError(
+                        "Cannot write record with invalid data order.",
+                    ))
+                }
+            }
+        }
+        Ok(record_bytes)
     }
 
     /// Creates a string representation for each attribute and pushes
@@ -229,7 +1046,7 @@ impl Table {
     /// information to describe the table to the user.
     ///
     /// Indicates the name and domain for each attribute as well as
-    /// the location of the primary key.
+    /// the location of the primary key and of any secondary indexes.
     pub fn attributes_to_string_vec(&self) -> Vec<String> {
         let mut output = Vec::new();
         let attri_iter = self.attributes.iter();
@@ -241,75 +1058,119 @@ impl Table {
             } else {
                 "\t\t\t"
             };
-            if self.key_attri_num == Some(i) {
-                output.push(format!(
-                    "{}{tabs}{}\tPRIMARY KEY",
-                    attribute.name().to_uppercase(),
-                    domain.to_string()
-                ));
+            let mut line = if self.key_attri_nums.contains(&i) {
+                format!(
+                    "{}{tabs}{domain}\tPRIMARY KEY",
+                    attribute.name().to_uppercase()
+                )
             } else {
-                output.push(format!(
-                    "{}{tabs}{}",
-                    attribute.name().to_uppercase(),
-                    domain.to_string()
+                format!("{}{tabs}{domain}", attribute.name().to_uppercase())
+            };
+            let constraints = &self.constraints[i];
+            if constraints.not_null {
+                line.push_str("\tNOT NULL");
+            }
+            if let Some(ref default) = constraints.default {
+                line.push_str(&format!("\tDEFAULT {default}"));
+            }
+            if let Some(check) = self.check_text(i) {
+                line.push_str(&format!("\tCHECK ({check})"));
+            }
+            if let Some(ref reference) = constraints.references {
+                line.push_str(&format!(
+                    "\tREFERENCES {}",
+                    reference.table.name().to_uppercase()
                 ));
+                if reference.on_delete != RefAction::Restrict {
+                    line.push_str(&format!(" ON DELETE {}", reference.on_delete.to_string()));
+                }
+                if reference.on_update != RefAction::Restrict {
+                    line.push_str(&format!(" ON UPDATE {}", reference.on_update.to_string()));
+                }
+            }
+            for (index_name, index) in self.secondary_indexes.iter() {
+                if index.columns().contains(&i) {
+                    line.push_str(&format!("\tINDEX {}", index_name.name().to_uppercase()));
+                }
             }
+            output.push(line);
         }
         output
     }
 
-    /// Attempts to write the given record to the file
-    /// the table was read from or originally wrote itself to.
-    /// Does not write the updated record_count although it does
-    /// add one to it in memory. Records are always written at the
-    /// end of the table.
+    /// Checks the given record against the Table's format, constraints, and key, then adds it
+    /// to the indexes and record_count in memory. Returns the op that writes the record
+    /// at the end of the table without applying it. checks are the Table's check_conditions,
+    /// and the record's Text values are pushed onto heap_bytes.
     ///
     /// # Errors
     ///
-    /// Fails when cannot write to the file or
-    /// when the record does not match the format of
-    /// the Table instance.
-    pub fn write_record(&mut self, record: Vec<Data>) -> Result<(), Box<dyn Error>> {
-        let mut file = OpenOptions::new().append(true).open(&self.file_path)?;
-        let mut record_bytes: Vec<u8> = Vec::new();
-        for (data, (_, domain)) in record.iter().zip(self.attributes.iter()) {
-            match (data, domain) {
-                (Data::Integer(int), Domain::Integer) => {
-                    record_bytes.append(&mut int.to_bytes().to_vec())
-                }
-                (Data::Float(float), Domain::Float) => {
-                    record_bytes.append(&mut float.to_bytes().to_vec())
-                }
-                (Data::Text(text), Domain::Text) => {
-                    record_bytes.append(&mut text.to_bytes().to_vec())
-                }
-                _ => {
-                    return Err(Box::new(DBError::ConstraintError(
-                        "Cannot write record with invalid data order.",
-                    )))
-                }
-            }
+    /// Fails when the record does not match the format of
+    /// the Table instance, breaks a constraint, or when its key already exists.
+    fn stage_record(
+        &mut self,
+        record: Vec<Data>,
+        checks: &[Condition],
+        heap_bytes: &mut Vec<u8>,
+    ) -> Result<WalOp, Box<dyn Error>> {
+        if record.len() != self.attributes.len() {
+            Err(DBError::ConstraintError(
+                "Cannot write record with the wrong number of attributes.",
+            ))?
         }
-        if let Some(ref mut bst) = self.bst {
-            let key = record[self.key_attri_num.unwrap()].clone();
-            bst.insert(key, self.record_count)?;
+        self.check_constraints(&record, checks)?;
+        let record_bytes = self.record_to_bytes(&record, None, heap_bytes)?;
+        let record_num = self.record_count;
+        for index in self.trees_mut() {
+            index.insert(Key::from_record(&record, index.columns()), record_num)?;
         }
         self.record_count += 1;
 
-        file.write_all(&record_bytes)?;
-        Ok(())
+        Ok(self.record_op(self.record_count - 1, record_bytes))
     }
 
-    /// An alias for calling write_record and write_record_count successively.
+    /// Attempts to write the given record to the end of the table along with the
+    /// updated record_count. Both go through the write-ahead log as a single entry.
     ///
     /// # Errors
     ///
     /// Fails when cannot write to the file or
     /// when the record does not match the format of
     /// the Table instance.
-    pub fn write_single_record(&mut self, record: Vec<Data>) -> Result<(), Box<dyn Error>> {
-        self.write_record(record)?;
-        self.write_record_count()
+    pub fn write_record(&mut self, record: Vec<Data>) -> Result<(), Box<dyn Error>> {
+        self.write_records(vec![record])
+    }
+
+    /// Attempts to write every given record to the end of the table along with the
+    /// updated record_count. All of them go through the write-ahead log as a single entry
+    /// so that no records are written if any of them fails to check out.
+    ///
+    /// # Errors
+    ///
+    /// Fails when cannot write to the file or
+    /// when a record does not match the format or constraints of
+    /// the Table instance.
+    pub fn write_records(&mut self, records: Vec<Vec<Data>>) -> Result<(), Box<dyn Error>> {
+        let checks = self.check_conditions()?;
+        let prev_record_count = self.record_count;
+        let mut ops = Vec::with_capacity(records.len() + 2);
+        let mut heap_bytes = Vec::new();
+        for record in records {
+            match self.stage_record(record, &checks, &mut heap_bytes) {
+                Ok(op) => ops.push(op),
+                Err(err) => {
+                    // forget the records staged before this one
+                    self.record_count = prev_record_count;
+                    self.discard_index_changes()?;
+                    return Err(err);
+                }
+            }
+        }
+        if !heap_bytes.is_empty() {
+            ops.push(self.heap_op(heap_bytes));
+        }
+        ops.push(self.record_count_op(self.record_count));
+        self.apply_ops(ops)
     }
 
     /// Attempts to read a record back from the file.
@@ -320,27 +1181,85 @@ impl Table {
     /// Fails when the file cannot be read from or
     /// when the file is in a bad format.
     pub fn read_record(&self, record_num: usize) -> Result<Vec<Data>, Box<dyn Error>> {
+        self.record_from_bytes(&self.read_record_bytes(record_num)?, &mut self.open_heap()?)
+    }
+
+    /// Attempts to read the bytes of a record from the file, as they were written by record_to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read from or the record is out of the table's bounds.
+    fn read_record_bytes(&self, record_num: usize) -> Result<Vec<u8>, Box<dyn Error>> {
         if record_num > self.record_count {
             return Err(Box::new(DBError::ConstraintError(
                 "Cannot find a record which is out of the table's bounds",
             )));
         }
-        let mut file = File::open(&self.file_path)?;
+        let mut file = File::open(self.read_path(&self.file_path))?;
         let mut record_bytes: Vec<u8> = vec![0; self.record_length as usize];
 
         file.seek(SeekFrom::Start(
             (self.meta_offset + record_num * self.record_length as usize) as u64,
         ))?;
         file.read_exact(&mut record_bytes)?;
+        Ok(record_bytes)
+    }
+
+    /// Reads a Text value out of heap from the u64 offset and u32 length that a record stores for it.
+    ///
+    /// # Errors
+    ///
+    /// Fails when there is no heap or when the value cannot be read from it.
+    fn read_heap_text(heap: &mut Option<File>, pointer: &[u8]) -> Result<Text, Box<dyn Error>> {
+        let heap = heap.as_mut().ok_or(DBError::FileFormatError(
+            "Cannot read a Text value without a heap file.",
+        ))?;
+        let heap_offset = u64::from_be_bytes(pointer[..8].try_into().unwrap());
+        let len = u32::from_be_bytes(pointer[8..12].try_into().unwrap());
+        let mut text_bytes = vec![0; len as usize];
+        heap.seek(SeekFrom::Start(heap_offset))?;
+        heap.read_exact(&mut text_bytes)?;
+        Ok(Text::from(std::str::from_utf8(&text_bytes)?)?)
+    }
+
+    /// Reads a record back from its byte representation in the file, reading the values
+    /// of Text attributes out of heap, which is opened by open_heap.
+    ///
+    /// # Errors
+    ///
+    /// Fails when record_bytes are in a bad format.
+    fn record_from_bytes(
+        &self,
+        record_bytes: &[u8],
+        heap: &mut Option<File>,
+    ) -> Result<Vec<Data>, Box<dyn Error>> {
         let mut record: Vec<Data> = Vec::with_capacity(self.attributes.len());
-        let mut offset = 0;
-        for (_, domain) in self.attributes.iter() {
+        let mut offset = Table::null_bitmap_len(self.attributes.len());
+        for (i, (_, domain)) in self.attributes.iter().enumerate() {
+            if record_bytes[i / 8] & (1 << (i % 8)) != 0 {
+                offset += domain.size_in_bytes() as usize;
+                record.push(Data::Null);
+                continue;
+            }
             let data = match domain {
-                Domain::Float => {
-                    offset += Float::byte_len();
-                    Data::Float(Float::from_bytes(
-                        &record_bytes[offset - 5..offset].try_into().unwrap(),
-                    )?)
+                Domain::BigInt => {
+                    offset += BigInt::byte_len();
+                    Data::BigInt(BigInt::from_bytes(
+                        &record_bytes[offset - 8..offset].try_into().unwrap(),
+                    ))
+                }
+                Domain::Double => {
+                    offset += Double::byte_len();
+                    Data::Double(Double::from_bytes(
+                        &record_bytes[offset - 8..offset].try_into().unwrap(),
+                    ))
+                }
+                Domain::Decimal(_, scale) => {
+                    offset += Decimal::byte_len();
+                    Data::Decimal(Decimal::from_bytes(
+                        &record_bytes[offset - 16..offset].try_into().unwrap(),
+                        *scale,
+                    ))
                 }
                 Domain::Integer => {
                     offset += Integer::byte_len();
@@ -349,8 +1268,34 @@ impl Table {
                     ))
                 }
                 Domain::Text => {
-                    offset += 100;
-                    Data::Text(Text::from_bytes(&record_bytes[offset - 100..offset])?)
+                    offset += 12; // the u64 offset and u32 length of the value in the heap
+                    Data::Text(Table::read_heap_text(
+                        heap,
+                        &record_bytes[offset - 12..offset],
+                    )?)
+                }
+                Domain::VarChar(limit) => {
+                    let start = offset;
+                    offset += *limit as usize + 2;
+                    Data::Text(Text::from_varchar_bytes(&record_bytes[start..offset])?)
+                }
+                Domain::Boolean => {
+                    offset += Boolean::byte_len();
+                    Data::Boolean(Boolean::from_bytes(
+                        &record_bytes[offset - 1..offset].try_into().unwrap(),
+                    )?)
+                }
+                Domain::Date => {
+                    offset += Date::byte_len();
+                    Data::Date(Date::from_bytes(
+                        &record_bytes[offset - 4..offset].try_into().unwrap(),
+                    ))
+                }
+                Domain::Timestamp => {
+                    offset += Timestamp::byte_len();
+                    Data::Timestamp(Timestamp::from_bytes(
+                        &record_bytes[offset - 8..offset].try_into().unwrap(),
+                    ))
                 }
             };
             record.push(data)
@@ -358,21 +1303,100 @@ impl Table {
         Ok(record)
     }
 
-    /// Attempts to write this Table's bst to the file.
-    /// If the Table has no bst, it will simply return an
-    /// empty Ok variant immediately.
+    /// Rebuilds this Table's index from the records in the file and writes it through
+    /// the write-ahead log. Used to replace index files written before indexes were paged.
     ///
     /// # Errors
     ///
-    /// Fails when cannot write to the file.
-    pub fn write_bst(&self) -> Result<(), std::io::Error> {
-        if let Some(ref bst) = self.bst {
-            let mut bst_path = self.file_path.clone();
-            bst_path.replace_range(self.file_path.len() - 3.., "index");
-            bst.write_to_file(&bst_path)
-        } else {
-            Ok(())
+    /// Fails when cannot read/write the files or when the file holds duplicate keys.
+    pub fn rebuild_index(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.key_attri_nums.is_empty() {
+            return Ok(());
+        }
+
+        let index_path = self.index_path();
+        let mut index = BPlusTree::new(&index_path, true, self.key_attri_nums.clone());
+        for (record_num, record) in self.read_all_data()?.into_iter().enumerate() {
+            index.insert(Key::from_record(&record, &self.key_attri_nums), record_num)?;
+        }
+
+        // clear the old file before the new pages go in
+        let clear_op = WalOp::SetLen {
+            path: index_path,
+            len: 0,
+        };
+        self.index = Some(index);
+        self.apply_ops(vec![clear_op])
+    }
+
+    /// Attempts to create a secondary index named index_name on the attribute named attribute
+    /// and fill it with every record in the table. The index is written to
+    /// '{dir}{table}.{index}.idx' through the write-ahead log as a single entry.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the attribute is not in the table, when the table already has an index
+    /// named index_name, or when the files cannot be read from or written to.
+    pub fn create_index(
+        &mut self,
+        index_name: Identifier,
+        attribute: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let column = match self
+            .attributes
+            .iter()
+            .position(|(identifier, _)| identifier.name() == attribute)
+        {
+            Some(column) => column,
+            None => Err(DBError::ConstraintError(
+                "Cannot index an attribute which is not in the table.",
+            ))?,
+        };
+        if self.has_secondary_index(index_name.name()) {
+            Err(DBError::ConstraintError(
+                "Cannot create two indexes with the same name.",
+            ))?
+        }
+
+        let index_path = self.secondary_index_path(index_name.name());
+        let mut index = BPlusTree::new(&index_path, false, vec![column]);
+        for (record_num, mut record) in self.read_all_data()?.into_iter().enumerate() {
+            index.insert(Key::from(record.swap_remove(column)), record_num)?;
+        }
+
+        self.secondary_indexes.push((index_name, index));
+        if let Err(err) = self.apply_ops(Vec::new()) {
+            self.secondary_indexes.pop();
+            return Err(err);
         }
+        Ok(())
+    }
+
+    /// Attempts to remove the secondary index named index_name along with its file.
+    /// Returns false without changing anything when the table has no index by that name.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the index file cannot be removed.
+    pub fn drop_index(&mut self, index_name: &str) -> Result<bool, Box<dyn Error>> {
+        let position = match self
+            .secondary_indexes
+            .iter()
+            .position(|(name, _)| name.name() == index_name)
+        {
+            Some(position) => position,
+            None => return Ok(false),
+        };
+
+        let index = self.secondary_indexes.remove(position);
+        let remove_op = WalOp::Remove {
+            path: index.1.path().to_string(),
+        };
+        if let Err(err) = self.apply_ops(vec![remove_op]) {
+            self.secondary_indexes.insert(position, index);
+            return Err(err);
+        }
+        Ok(true)
     }
 
     /// Attempts to read every record in the order stored in the file.
@@ -382,38 +1406,24 @@ impl Table {
     /// Fails if cannot read from the file or
     /// when a record is found in a bad format.
     fn read_all_data(&self) -> Result<Vec<Vec<Data>>, Box<dyn Error>> {
-        let mut file = File::open(&self.file_path)?;
+        let mut heap = self.open_heap()?;
+        self.read_all_bytes()?
+            .chunks(self.record_length as usize)
+            .map(|record_bytes| self.record_from_bytes(record_bytes, &mut heap))
+            .collect()
+    }
+
+    /// Attempts to read the bytes of every record in the table, one after another.
+    ///
+    /// # Errors
+    ///
+    /// Fails if cannot read from the file.
+    fn read_all_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut file = File::open(self.read_path(&self.file_path))?;
         let mut records_bytes: Vec<u8> = vec![0; self.record_length as usize * self.record_count];
         file.seek(SeekFrom::Start(self.meta_offset as u64))?;
         file.read_exact(&mut records_bytes)?;
-        let mut records: Vec<Vec<Data>> = Vec::new();
-        let mut offset = 0;
-        for _ in 0..self.record_count {
-            let mut record: Vec<Data> = Vec::with_capacity(self.attributes.len());
-            for (_, domain) in self.attributes.iter() {
-                let data = match domain {
-                    Domain::Float => {
-                        offset += Float::byte_len();
-                        Data::Float(Float::from_bytes(
-                            &records_bytes[offset - 5..offset].try_into().unwrap(),
-                        )?)
-                    }
-                    Domain::Integer => {
-                        offset += Integer::byte_len();
-                        Data::Integer(Integer::from_bytes(
-                            &records_bytes[offset - 4..offset].try_into().unwrap(),
-                        ))
-                    }
-                    Domain::Text => {
-                        offset += 100; // the max text byte length and the length we always store it as for tables
-                        Data::Text(Text::from_bytes(&records_bytes[offset - 100..offset])?)
-                    }
-                };
-                record.push(data)
-            }
-            records.push(record);
-        }
-        Ok(records)
+        Ok(records_bytes)
     }
 
     /// Attempts to overwrite the attribute names in the file
@@ -437,38 +1447,45 @@ impl Table {
                 }
             }
         }
-        let mut file = OpenOptions::new().write(true).open(&self.file_path)?;
+        let prev_attributes = std::mem::take(&mut self.attributes);
+        self.attributes = new_attributes
+            .into_iter()
+            .zip(prev_attributes.iter())
+            .map(|(attribute, (_, domain))| (attribute, *domain))
+            .collect();
 
         // just overwrite all front meta since seek-writing wouldn't greatly improve performance at all
-        file.write_all(&self.key_attri_num.unwrap_or_else(|| 0_usize).to_be_bytes())?;
-        file.write_all(&self.attributes.len().to_be_bytes())?; // 8 bytes
-
-        let mut zipped_attris = Vec::with_capacity(self.attributes.len());
-        for (attribute, (_, domain)) in new_attributes.into_iter().zip(&self.attributes) {
-            attribute.write_to_file(&file)?;
-            domain.write_to_file(&file)?;
-            zipped_attris.push((attribute, domain.clone()));
+        let header_op = WalOp::Write {
+            path: self.file_path.clone(),
+            offset: 0,
+            bytes: self.header_bytes()?,
+        };
+        if let Err(err) = self.apply_ops(vec![header_op]) {
+            self.attributes = prev_attributes;
+            return Err(err);
         }
-
-        self.attributes = zipped_attris;
         Ok(())
     }
 
-    /// Similar to write_record except that this writes over a specific location
-    /// in the file to replace an existing record and does not update the record_count.
-    /// Also updates the attached bst when a key value is changed.
+    /// Builds the new version of the record at record_num with the labelled values in new_values
+    /// put in. Returns the op that writes it over the old record without applying it, along with
+    /// the old and new versions of the record. Nothing in the Table is changed.
+    /// checks are the Table's check_conditions, and changed Text values are pushed onto heap_bytes.
     ///
     /// # Errors
     ///
-    /// Fails when the new_values for the record do not match the Table's attributes or
-    /// when the file cannot be written to. Also requires that the record_num
-    /// given is within the Table's record_count.
-    fn update_record(
-        &mut self,
+    /// Fails when the new_values for the record do not match the Table's attributes, when the new
+    /// record breaks a constraint, or when the file cannot be read from. Also requires that the
+    /// record_num given is within the Table's record_count.
+    fn stage_update(
+        &self,
         record_num: usize,
         new_values: &Vec<(Identifier, Data)>,
-    ) -> Result<(), Box<dyn Error>> {
-        let prev_record = self.read_record(record_num)?;
+        checks: &[Condition],
+        heap_bytes: &mut Vec<u8>,
+    ) -> Result<(WalOp, RecordChange), Box<dyn Error>> {
+        let prev_bytes = self.read_record_bytes(record_num)?;
+        let prev_record = self.record_from_bytes(&prev_bytes, &mut self.open_heap()?)?;
         let mut record = prev_record.clone();
 
         for (i, (identifier, _)) in self.attributes.iter().enumerate() {
@@ -479,142 +1496,297 @@ impl Table {
             }
         }
 
-        // consider extrating this logic into a separate function since it's used a couple times
-        let mut record_bytes: Vec<u8> = Vec::new();
-        for (data, (_, domain)) in record.iter().zip(self.attributes.iter()) {
-            match (data, domain) {
-                (Data::Integer(int), Domain::Integer) => {
-                    record_bytes.append(&mut int.to_bytes().to_vec())
-                }
-                (Data::Float(float), Domain::Float) => {
-                    record_bytes.append(&mut float.to_bytes().to_vec())
-                }
-                (Data::Text(text), Domain::Text) => {
-                    record_bytes.append(&mut text.to_bytes().to_vec())
-                }
-                _ => {
-                    return Err(Box::new(DBError::ConstraintError(
-                        "Cannot write record with invalid data order.",
-                    )))
-                }
-            }
-        }
+        self.check_constraints(&record, checks)?;
+        let record_bytes =
+            self.record_to_bytes(&record, Some((&prev_record, &prev_bytes)), heap_bytes)?;
 
-        let mut file = OpenOptions::new().write(true).open(&self.file_path)?;
-        file.seek(SeekFrom::Start(
-            (self.meta_offset + record_num * self.record_length as usize) as u64,
-        ))?;
+        Ok((
+            self.record_op(record_num, record_bytes),
+            (record_num, prev_record, record),
+        ))
+    }
 
-        if let Some(ref mut bst) = self.bst {
-            if prev_record[0] != record[0] {
-                bst.remove(&prev_record[0]);
-                let key = record[0].clone();
-                match bst.insert(key, record_num) {
-                    Err(BSTInsertErr) => Err(DBError::ConstraintError(
-                        "Cannot set a key to the value of another key in the table.",
-                    ))?,
-                    _ => (),
-                }
-            }
+    /// Checks that moving each record in key_changes from its old key to its new key leaves
+    /// every key in the index unique. A new key may only match an existing key if the record
+    /// holding that key is moving off of it in the same update.
+    ///
+    /// # Errors
+    ///
+    /// Fails when two new keys are equal or when a new key is held by a record that is not moving.
+    fn check_key_changes(&self, key_changes: &[KeyChange]) -> Result<(), Box<dyn Error>> {
+        let index = match self.index {
+            Some(ref index) => index,
+            None => return Ok(()),
+        };
+
+        // keys given up by the moving records are free to be taken again
+        let mut vacated_keys = BST::new();
+        for (record_num, prev_key, _) in key_changes {
+            vacated_keys.insert(prev_key.clone(), *record_num)?;
         }
 
-        file.write_all(&record_bytes)?;
+        let mut new_keys = BST::new();
+        for (record_num, _, new_key) in key_changes {
+            if new_keys.find(new_key).is_some() {
+                Err(DBError::ConstraintError(
+                    "Cannot set more than one key value at a time.",
+                ))?
+            }
+            new_keys.insert(new_key.clone(), *record_num)?;
+            // each vacated key can only be taken once
+            if vacated_keys.remove(new_key).is_none() && index.find(new_key)?.is_some() {
+                Err(DBError::ConstraintError(
+                    "Cannot set a key to the value of another key in the table.",
+                ))?
+            }
+        }
         Ok(())
     }
 
-    /// Calls update_record for each record selected by the converted condition. This function is used
-    /// by Condition itself, so using Condition.update is preferred over using this. Puts labelled values in
-    /// new_values into each record it writes over.
+    /// Updates each record in updates, putting its labelled values into the record it writes over.
+    /// The record numbers are usually found with Condition.filter. Note that this does not check
+    /// REFERENCES constraints, which need the other tables of the database.
+    ///
+    /// Every new record and key is checked before anything is written, and all of the records
+    /// go through the write-ahead log as a single entry, so either every row changes or none do.
+    /// The heap is compacted in the same entry once changed Text values leave more than half of it unused.
     ///
     /// # Errors
     ///
-    /// Fails if the Condition provided has not been converted to use the given Table - i.e. called by
-    /// something other than Condition.update. Also fails for all the reasons update_record fails including
-    /// file access issues and incorrect record formatting.
+    /// Fails when the new values do not match the Table's attributes or constraints, when a
+    /// record number is out of the Table's bounds, or when the files cannot be read from or written to.
     ///
     /// In addition, this ensures that a user does not update more than one key value in the table to the same
     /// value.
-    pub fn update_all(
+    pub fn update_all(&mut self, updates: Vec<RecordUpdate>) -> Result<(), Box<dyn Error>> {
+        // check if updating a key more than once - which is illegal. A key made of more than one
+        // attribute may still be unique when one of its attributes is set on many records.
+        if let ([key_num], Some(_)) = (&self.key_attri_nums[..], &self.index) {
+            let key_name = self.attributes[*key_num].0.name();
+            let key_updates = updates
+                .iter()
+                .filter(|(_, new_values)| new_values.iter().any(|(id, _)| id.name() == key_name))
+                .count();
+            if key_updates > 1 {
+                // updating a key more than once
+                Err(DBError::ConstraintError(
+                    "Cannot set more than one key value at a time.",
+                ))?
+            }
+        }
+
+        // stage every record before writing any of them
+        let checks = self.check_conditions()?;
+        let mut ops = Vec::with_capacity(updates.len() + 1);
+        let mut record_changes = Vec::with_capacity(updates.len());
+        let mut heap_bytes = Vec::new();
+        for (record_num, new_values) in updates {
+            let (op, record_change) =
+                self.stage_update(record_num, &new_values, &checks, &mut heap_bytes)?;
+            ops.push(op);
+            record_changes.push(record_change);
+        }
+        if !heap_bytes.is_empty() {
+            ops.push(self.heap_op(heap_bytes));
+        }
+
+        let key_changes: Vec<KeyChange> = match self.index {
+            Some(_) => record_changes
+                .iter()
+                .map(|(record_num, prev_record, record)| {
+                    (
+                        *record_num,
+                        Key::from_record(prev_record, &self.key_attri_nums),
+                        Key::from_record(record, &self.key_attri_nums),
+                    )
+                })
+                .filter(|(_, prev_key, key)| prev_key != key)
+                .collect(),
+            None => Vec::new(),
+        };
+        self.check_key_changes(&key_changes)?;
+
+        // changed Text values leave their old bytes behind in the heap
+        if self.heap_len > 0
+            && self
+                .attributes
+                .iter()
+                .any(|(_, domain)| *domain == Domain::Text)
+        {
+            let mut records = self.read_all_data()?;
+            for (record_num, _, record) in record_changes.iter() {
+                records[*record_num] = record.clone();
+            }
+            let compact_ops = self.compact_heap(&records)?;
+            if !compact_ops.is_empty() {
+                // the compacted heap and records already hold every change
+                ops = compact_ops;
+            }
+        }
+
+        // move the keys in the indexes so the change is written with the records
+        if let Err(err) = self.move_keys(key_changes, &record_changes) {
+            self.discard_index_changes()?;
+            return Err(err);
+        }
+
+        self.apply_ops(ops)
+    }
+
+    /// Moves each record in key_changes from its old key to its new key in the index, and
+    /// each record in record_changes to its new values in the secondary indexes.
+    /// Removing every old key first lets records swap keys with one another.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a new key is already in the index or when an index cannot be read.
+    fn move_keys(
         &mut self,
-        cond: Condition,
-        new_values: Vec<(Identifier, Data)>,
+        key_changes: Vec<KeyChange>,
+        record_changes: &[RecordChange],
     ) -> Result<(), Box<dyn Error>> {
-        // load MemTable
-        let mem_table = MemTable::build(self)?;
-        let record_nums: Vec<usize> =
-            cond.filter_table_coords(&vec![mem_table], 0, &self.bst, &vec![self]);
-
-        // check if updating a key more than once - which is illegal
-        if self.bst.is_some() && record_nums.len() > 1 {
-            for (id, _) in new_values.iter() {
-                if id.name() == self.attributes[0].0.name() {
-                    // updating a key more than once
-                    Err(DBError::ConstraintError(
-                        "Cannot set more than one key value at a time.",
-                    ))?
-                }
+        if let Some(ref mut index) = self.index {
+            for (_, prev_key, _) in key_changes.iter() {
+                index.remove(prev_key)?;
+            }
+            for (record_num, _, key) in key_changes {
+                index.insert(key, record_num)?;
             }
         }
 
-        for record_num in record_nums {
-            self.update_record(record_num, &new_values)?;
+        for (_, index) in self.secondary_indexes.iter_mut() {
+            for (record_num, prev_record, record) in record_changes {
+                let prev_key = Key::from_record(prev_record, index.columns());
+                let key = Key::from_record(record, index.columns());
+                if prev_key != key {
+                    index.remove_entry(&prev_key, *record_num)?;
+                    index.insert(key, *record_num)?;
+                }
+            }
         }
         Ok(())
     }
 
-    /// Removes each record selected by the converted condition. This function is used
-    /// by Condition itself, so using Condition.delete is preferred over using this. Note
+    /// Removes each record in record_nums, which are usually found with Condition.filter. Note
     /// that this does not preserve the order of the records in the file. Instead, loads
-    /// all records, looks for the records to delete through the Condition and
-    /// swap-removes them from highest to lowest position in the file. The bst is then
-    /// reconstructed after deletion.
+    /// all records and swap-removes the ones to delete from highest to lowest position in
+    /// the file. Only the records moved into the holes are rewritten and only their index
+    /// entries are changed, unless the heap is compacted. This does not check REFERENCES
+    /// constraints, which need the other tables of the database.
     ///
     /// # Errors
     ///
-    /// Fails when cannot read/write to files or when the Condition provided has not been
-    /// converted to use the given Table - i.e. called by something other than Condition.delete
-    pub fn delete_all(&mut self, cond: Condition) -> Result<(), Box<dyn Error>> {
-        let mut mem_tables = vec![MemTable::build(self)?];
-        let mut record_nums = cond.filter_table_coords(&mem_tables, 0, &self.bst, &vec![self]);
-        let mut mem_table = mem_tables.remove(0);
-
-        if record_nums.len() == 0 {
+    /// Fails when cannot read/write to files or when a record number is out of the Table's bounds.
+    pub fn delete_all(&mut self, mut record_nums: Vec<usize>) -> Result<(), Box<dyn Error>> {
+        if record_nums.is_empty() {
             return Ok(());
         }
 
-        record_nums.sort(); // could be in bst order but we want highest to lowest table position
-        for record_num in record_nums.into_iter().rev() {
-            mem_table.records.swap_remove(record_num); // must swap remove highest numbers first
+        record_nums.sort(); // could be in index order but we want highest to lowest table position
+        record_nums.dedup();
+        if record_nums[record_nums.len() - 1] >= self.record_count {
+            Err(DBError::ConstraintError(
+                "Cannot find a record which is out of the table's bounds",
+            ))?
         }
+        let records_bytes = self.read_all_bytes()?;
+        let records_bytes = records_bytes.chunks(self.record_length as usize).collect();
+        let records = self.read_all_data()?;
+        let ops = match self.stage_deletes(records, records_bytes, &record_nums) {
+            Ok(ops) => ops,
+            Err(err) => {
+                self.discard_index_changes()?;
+                return Err(err);
+            }
+        };
+        self.apply_ops(ops)?;
 
-        // clear the bst
-        match self.bst {
-            None => (),
-            Some(ref mut bst) => *bst = BST::new(),
-        }
+        self.record_count -= record_nums.len();
+        Ok(())
+    }
 
-        let file = OpenOptions::new().write(true).open(&self.file_path)?;
-        file.set_len(self.meta_offset as u64)?;
+    /// Swap-removes the sorted record_nums from records, along with their bytes in records_bytes,
+    /// and changes the indexes to match. Returns the ops which write every moved record into its
+    /// new position, cut the file down to the remaining records, update the record_count, and
+    /// compact the heap if it needs to be.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a remaining record does not match the format of the Table or
+    /// when the index cannot be read.
+    fn stage_deletes(
+        &mut self,
+        mut records: Vec<Vec<Data>>,
+        mut records_bytes: Vec<&[u8]>,
+        record_nums: &[usize],
+    ) -> Result<Vec<WalOp>, Box<dyn Error>> {
+        for index in self.trees_mut() {
+            for record_num in record_nums {
+                let key = Key::from_record(&records[*record_num], index.columns());
+                index.remove_entry(&key, *record_num)?;
+            }
+        }
 
-        self.record_count = 0; // set to 0 since write_record_count will increment it
+        let mut ops = Vec::new();
+        for record_num in record_nums.iter().rev() {
+            let moved_from = records.len() - 1;
+            records.swap_remove(*record_num); // must swap remove highest numbers first
+            records_bytes.swap_remove(*record_num);
+            let moved = match records.get(*record_num) {
+                Some(moved) => moved,
+                None => continue, // the last record was removed so nothing moved
+            };
 
-        for record in mem_table.records {
-            self.write_record(record)?;
+            ops.push(self.record_op(*record_num, records_bytes[*record_num].to_vec()));
+            for index in self.trees_mut() {
+                let key = Key::from_record(moved, index.columns());
+                index.remove_entry(&key, moved_from)?;
+                index.insert(key, *record_num)?;
+            }
         }
-        self.write_record_count()?;
 
-        // recreate the bst
-        match self.bst {
-            None => (),
-            Some(ref mut bst) => {
-                let mut bst_path = self.file_path.clone();
-                bst_path.replace_range(self.file_path.len() - 3.., "index");
-                bst.write_to_file(&bst_path)?;
-                *bst = BST::read_from_file(&bst_path)?; // balances the bst... not the best way to do this but it works for the purposes we need
-            }
+        ops.push(WalOp::SetLen {
+            path: self.file_path.clone(),
+            len: (self.meta_offset + records.len() * self.record_length as usize) as u64,
+        });
+        ops.push(self.record_count_op(records.len()));
+        ops.append(&mut self.compact_heap(&records)?);
+        Ok(ops)
+    }
+
+    /// Returns the ops which rewrite the heap with only the Text values of records, which are
+    /// all of the records left in the Table, once more than half of the heap is not used by them.
+    /// Every record is rewritten to point into the new heap. Returns no ops otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a record does not match the format of the Table.
+    fn compact_heap(&mut self, records: &[Vec<Data>]) -> Result<Vec<WalOp>, DBError> {
+        let used_len: u64 = records
+            .iter()
+            .flat_map(|record| record.iter().zip(self.attributes.iter()))
+            .map(|(data, (_, domain))| match (data, domain) {
+                (Data::Text(text), Domain::Text) => text.content().len() as u64,
+                _ => 0,
+            })
+            .sum();
+        if self.heap_len <= used_len * 2 {
+            return Ok(Vec::new());
         }
 
-        Ok(())
+        self.heap_len = 0;
+        let mut heap_bytes = Vec::with_capacity(used_len as usize);
+        let mut ops = Vec::with_capacity(records.len() + 2);
+        for (record_num, record) in records.iter().enumerate() {
+            let record_bytes = self.record_to_bytes(record, None, &mut heap_bytes)?;
+            ops.push(self.record_op(record_num, record_bytes));
+        }
+        ops.push(WalOp::SetLen {
+            path: self.heap_path(),
+            len: 0,
+        });
+        ops.push(self.heap_op(heap_bytes));
+        Ok(ops)
     }
 }
 
@@ -637,7 +1809,7 @@ impl MemTable {
             attributes: table
                 .attributes
                 .iter()
-                .map(|(identifier, domain)| ((*identifier).clone(), domain.clone()))
+                .map(|(identifier, domain)| ((*identifier).clone(), *domain))
                 .collect(),
             projection: (0..table.attributes.len()).collect(), // start with all attributes projected
         })
@@ -703,7 +1875,7 @@ impl MemTable {
     /// Returns a vector of strings such that when each is printed with a \n attached
     /// the MemTable comes out nicely formatted. This pays attention to the projection list.
     pub fn to_string_vec(&self) -> Vec<String> {
-        if self.records.len() == 0 {
+        if self.records.is_empty() {
             return vec![String::from("\nNothing Found.\n")];
         }
 
@@ -718,7 +1890,7 @@ impl MemTable {
         for record in self.records.iter() {
             for (i, attri_num) in self.projection.iter().enumerate() {
                 let data = &record[*attri_num];
-                let data_len = data.string_len(); // two spaces and two pipe characters
+                let data_len = data.to_cell_string().chars().count();
                 if data_len > attribute_lengths[i] {
                     attribute_lengths[i] = data_len;
                 }
@@ -737,7 +1909,7 @@ impl MemTable {
         // println stuff
 
         let row_hyphens = vec!["-"; row_length].concat();
-        output.push(format!("{row_hyphens}"));
+        output.push(row_hyphens.to_string());
 
         let mut top_line = Vec::with_capacity(self.attributes.len() * 3 + 1);
         let extra_spaces = vec![" "; row_num_string_length].concat();
@@ -749,9 +1921,9 @@ impl MemTable {
             top_line.push(id_string);
             top_line.push(" | ".to_string());
         }
-        output.push(format!("{}", top_line.concat()));
+        output.push(top_line.concat().to_string());
 
-        output.push(format!("{row_hyphens}"));
+        output.push(row_hyphens.to_string());
 
         // print each record
         for (i, record) in self.records.iter().enumerate() {
@@ -762,13 +1934,14 @@ impl MemTable {
             new_line.push(format!("| {num_string} | "));
             for (i, attri_num) in self.projection.iter().enumerate() {
                 let data = &record[*attri_num];
-                let data_string = data.to_string(); // two spaces and two pipe characters
-                let extra_spaces = vec![" "; attribute_lengths[i] - data_string.len()].concat();
+                let data_string = data.to_cell_string();
+                let data_len = data_string.chars().count(); // pad by chars so that columns line up
+                let extra_spaces = vec![" "; attribute_lengths[i] - data_len].concat();
                 let data_string = [data_string, extra_spaces].concat();
                 new_line.push(data_string);
                 new_line.push(" | ".to_string());
             }
-            output.push(format!("{}", new_line.concat()));
+            output.push(new_line.concat().to_string());
         }
 
         output.push(format!("{row_hyphens}\n"));
@@ -802,3 +1975,189 @@ impl MemTable {
         projected_record
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory for a test to keep its tables in, ending in a '/'
+    /// the way database paths do.
+    fn test_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("mini_dbms_relation_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        format!("{}/", dir.display())
+    }
+
+    fn int(value: i32) -> Data {
+        Data::Integer(Integer::wrap(value))
+    }
+
+    fn id(name: &str) -> Identifier {
+        Identifier::from(name).unwrap()
+    }
+
+    /// Returns a table keyed by (a, b) with attributes n and m, where n must be less than m
+    /// and n has a secondary index, holding each of records.
+    fn pair_table(dir: &str, records: Vec<Vec<Data>>) -> Table {
+        let attributes = ["a", "b", "n", "m"]
+            .into_iter()
+            .map(|name| (id(name), Domain::Integer))
+            .collect();
+        let mut constraints = vec![ColumnConstraints::default(); 4];
+        constraints[2].check = Some(String::from("n < m"));
+        let mut table = Table::build("t", attributes, constraints, vec![0, 1], dir).unwrap();
+        table.write_records(records).unwrap();
+        table.create_index(id("by_n"), "n").unwrap();
+        table
+    }
+
+    /// Returns every record of table along with the record numbers the primary key
+    /// and secondary index find for it.
+    fn records_and_entries(table: &Table) -> Vec<(Vec<Data>, usize, Vec<usize>)> {
+        let index = table.index.as_ref().unwrap();
+        let by_n = table.secondary_index_on(2).unwrap();
+        table
+            .read_all_data()
+            .unwrap()
+            .into_iter()
+            .map(|record| {
+                let key_entry = index.find(&Key::from_record(&record, &[0, 1])).unwrap();
+                let n_entries = by_n.find_all(&Key::from_record(&record, &[2])).unwrap();
+                (record, key_entry.unwrap(), n_entries)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn failed_updates_change_nothing() {
+        let dir = test_dir("failed_update");
+        let records = vec![
+            vec![int(1), int(1), int(0), int(10)],
+            vec![int(1), int(2), int(1), int(10)],
+            vec![int(1), int(3), int(2), int(3)],
+            vec![int(2), int(3), int(3), int(10)],
+        ];
+        let mut table = pair_table(&dir, records);
+        let before = records_and_entries(&table);
+
+        // the last record moves onto the key of one which is not updated
+        let move_keys = (0..3).map(|record_num| (record_num, vec![(id("a"), int(2))]));
+        assert!(table.update_all(move_keys.collect()).is_err());
+        assert_eq!(records_and_entries(&table), before);
+
+        // the last record breaks the CHECK once n is 5
+        let set_n = (0..3).map(|record_num| (record_num, vec![(id("n"), int(5))]));
+        assert!(table.update_all(set_n.collect()).is_err());
+        assert_eq!(records_and_entries(&table), before);
+
+        let table = Table::read_from_file("t", &dir).unwrap();
+        assert_eq!(records_and_entries(&table), before);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn updates_may_swap_keys() {
+        let dir = test_dir("swap_keys");
+        let records = vec![
+            vec![int(1), int(1), int(0), int(10)],
+            vec![int(1), int(2), int(1), int(10)],
+        ];
+        let mut table = pair_table(&dir, records);
+
+        let swap = vec![(0, vec![(id("b"), int(2))]), (1, vec![(id("b"), int(1))])];
+        table.update_all(swap).unwrap();
+        let swapped = vec![
+            (vec![int(1), int(2), int(0), int(10)], 0, vec![0]),
+            (vec![int(1), int(1), int(1), int(10)], 1, vec![1]),
+        ];
+        assert_eq!(records_and_entries(&table), swapped);
+
+        let table = Table::read_from_file("t", &dir).unwrap();
+        assert_eq!(records_and_entries(&table), swapped);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn printed_cells_escape_control_characters() {
+        let attributes = vec![(id("id"), Domain::Integer), (id("name"), Domain::Text)];
+        let text = |content: &str| Data::Text(Text::from(content).unwrap());
+        let records = vec![
+            vec![int(1), text("two\nlines")],
+            vec![int(2), text("tab\there, é")],
+            vec![int(3), text("back\\slash\r\0")],
+        ];
+        let lines = MemTable::build_from_records(records, attributes)
+            .unwrap()
+            .to_string_vec();
+
+        let rows: Vec<&str> = lines
+            .iter()
+            .map(|line| line.trim_end_matches('\n'))
+            .collect();
+        assert!(rows
+            .iter()
+            .all(|row| !row.contains(['\n', '\t', '\r', '\0'])));
+        let width = rows[1].chars().count();
+        let mut lines_of_cells = rows.iter().filter(|row| row.starts_with('|'));
+        assert!(
+            lines_of_cells.all(|row| row.chars().count() == width),
+            "{rows:?}"
+        );
+        assert!(rows[3].contains("| two\\nlines "));
+        assert!(rows[4].contains("| tab\\there, é "));
+        assert!(rows[5].contains("| back\\\\slash\\r\\0 "));
+    }
+
+    #[test]
+    fn long_key_values_are_rejected_before_writing() {
+        let dir = test_dir("long_keys");
+        let attributes = vec![
+            (id("name"), Domain::Text),
+            (id("tag"), Domain::VarChar(1000)),
+            (id("note"), Domain::Text),
+        ];
+        let constraints = vec![ColumnConstraints::default(); 3];
+        let mut table = Table::build("t", attributes, constraints, vec![0], &dir).unwrap();
+        table.create_index(id("by_tag"), "tag").unwrap();
+        let text = |len: usize| Data::Text(Text::from(&"x".repeat(len)).unwrap());
+        let message = |err: Box<dyn Error>| err.to_string();
+
+        table
+            .write_record(vec![text(249), text(249), text(5000)])
+            .unwrap();
+        let err = table
+            .write_record(vec![text(250), text(1), text(1)])
+            .unwrap_err();
+        assert!(message(err).contains("cannot take more than 249 bytes"));
+        let err = table
+            .write_record(vec![text(1), text(250), text(1)])
+            .unwrap_err();
+        assert!(message(err).contains("cannot take more than 249 bytes"));
+        let set_tag = vec![(0, vec![(id("tag"), text(300))])];
+        assert!(table.update_all(set_tag).is_err());
+        assert_eq!(table.record_count(), 1);
+        assert_eq!(
+            table.read_record(0).unwrap(),
+            [text(249), text(249), text(5000)]
+        );
+
+        // an index on an attribute which already holds a long value cannot be created
+        assert!(table.create_index(id("by_note"), "note").is_err());
+        assert!(table.secondary_index_on(2).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+
+        // five values which each fit are too long for one key together
+        let dir = test_dir("long_composite_key");
+        let attributes = (0..5)
+            .map(|i| (id(&format!("a{i}")), Domain::Text))
+            .collect();
+        let constraints = vec![ColumnConstraints::default(); 5];
+        let mut table = Table::build("t", attributes, constraints, (0..5).collect(), &dir).unwrap();
+        let err = table.write_record(vec![text(249); 5]).unwrap_err();
+        assert!(message(err).contains("1005 bytes together"));
+        table.write_record(vec![text(195); 5]).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
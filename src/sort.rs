@@ -0,0 +1,335 @@
+use crate::base::Data;
+use crate::DBError;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+
+/// The most bytes of records that a Sorter for a SELECT holds in memory. Larger selections are
+/// sorted in runs of about this many bytes, which are written to files and then merged.
+pub const SORT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// The attribute number of a value to sort records by, paired with whether it is sorted
+/// in descending order.
+pub type SortKey = (usize, bool);
+
+/// Returns the Ordering of two records by the values at each of the sort_keys in turn. Null
+/// values sort before all others, so they come first in ascending order and last in descending order.
+///
+/// # Panics
+///
+/// Panics when the values at a sort key cannot be compared or are out of bounds.
+pub fn cmp_records(record1: &[Data], record2: &[Data], sort_keys: &[SortKey]) -> Ordering {
+    for (attri_num, descending) in sort_keys {
+        let ordering = record1[*attri_num].cmp(&record2[*attri_num]);
+        let ordering = if *descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Sorts records by sort_keys as they are produced, keeping records with equal values in the order
+/// they were pushed in. At most about memory_limit bytes of records are held in memory - once
+/// they take more, they are sorted and written to a run file, and finish merges the runs with an
+/// external merge sort. The run files come from run_path for each run number, and are removed
+/// once the Sorter is dropped.
+pub struct Sorter<F: Fn(usize) -> String> {
+    sort_keys: Vec<SortKey>,
+    memory_limit: usize,
+    run_path: F,
+    run: Vec<Vec<Data>>,
+    run_size: usize,
+    run_paths: Vec<String>,
+    record_count: usize,
+}
+
+impl<F: Fn(usize) -> String> Sorter<F> {
+    /// Returns a Sorter holding no records, which writes a run once they take memory_limit bytes.
+    pub fn new(sort_keys: Vec<SortKey>, memory_limit: usize, run_path: F) -> Self {
+        Sorter {
+            sort_keys,
+            memory_limit,
+            run_path,
+            run: Vec::new(),
+            run_size: 0,
+            run_paths: Vec::new(),
+            record_count: 0,
+        }
+    }
+
+    /// Attempts to add record to those being sorted, writing the records held in memory to a
+    /// run file once they take memory_limit bytes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a run file cannot be written to.
+    pub fn push(&mut self, record: Vec<Data>) -> Result<(), Box<dyn Error>> {
+        self.run_size += record_size(&record);
+        self.run.push(record);
+        self.record_count += 1;
+        if self.run_size >= self.memory_limit {
+            self.write_run()?;
+        }
+        Ok(())
+    }
+
+    /// Attempts to return every record pushed, in sorted order. Records which never filled a run
+    /// are sorted in memory, and otherwise the runs are read back and merged.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the run files cannot be written to or read from.
+    pub fn finish(mut self) -> Result<Vec<Vec<Data>>, Box<dyn Error>> {
+        if self.run_paths.is_empty() {
+            let mut records = std::mem::take(&mut self.run);
+            records.sort_by(|rec1, rec2| cmp_records(rec1, rec2, &self.sort_keys));
+            return Ok(records);
+        }
+        if !self.run.is_empty() {
+            self.write_run()?;
+        }
+        merge_runs(&self.run_paths, self.record_count, &self.sort_keys)
+    }
+
+    /// Attempts to sort the records held in memory and write them to the next run file. The path
+    /// of the file is pushed onto run_paths as soon as the file is created.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the run file cannot be written to.
+    fn write_run(&mut self) -> Result<(), Box<dyn Error>> {
+        // the stable sort keeps equal records in order within a run, and merge_runs
+        // takes equal records from earlier runs first
+        self.run
+            .sort_by(|rec1, rec2| cmp_records(rec1, rec2, &self.sort_keys));
+        let path = (self.run_path)(self.run_paths.len());
+        let mut file = BufWriter::new(File::create(&path)?);
+        self.run_paths.push(path);
+        for record in self.run.drain(..) {
+            write_record(&mut file, &record)?;
+        }
+        file.flush()?;
+        self.run_size = 0;
+        Ok(())
+    }
+}
+
+impl<F: Fn(usize) -> String> Drop for Sorter<F> {
+    /// Removes the run files, including those of a sort that stopped partway through.
+    fn drop(&mut self) {
+        for path in self.run_paths.iter() {
+            let _ = fs::remove_file(path); // any left behind are removed when the database is next opened
+        }
+    }
+}
+
+/// Returns about how many bytes record takes in memory.
+fn record_size(record: &[Data]) -> usize {
+    let text_len: usize = record
+        .iter()
+        .map(|data| match data {
+            Data::Text(text) => text.content().len(),
+            _ => 0,
+        })
+        .sum();
+    size_of::<Vec<Data>>() + size_of_val(record) + text_len
+}
+
+/// Attempts to merge the sorted runs in the files at run_paths into one sorted list of records.
+///
+/// # Errors
+///
+/// Fails when a run file cannot be read from.
+fn merge_runs(
+    run_paths: &[String],
+    record_count: usize,
+    sort_keys: &[SortKey],
+) -> Result<Vec<Vec<Data>>, Box<dyn Error>> {
+    let mut runs = Vec::with_capacity(run_paths.len());
+    let mut heads = Vec::with_capacity(run_paths.len()); // the next record of each run
+    for path in run_paths {
+        let mut run = BufReader::new(File::open(path)?);
+        heads.push(read_record(&mut run)?);
+        runs.push(run);
+    }
+
+    let mut sorted = Vec::with_capacity(record_count);
+    loop {
+        // the first run holding the least record, so that equal records keep their order
+        let mut least: Option<usize> = None;
+        for (i, head) in heads.iter().enumerate() {
+            if let Some(record) = head {
+                let is_less = match least.and_then(|least| heads[least].as_ref()) {
+                    Some(least_record) => {
+                        cmp_records(record, least_record, sort_keys) == Ordering::Less
+                    }
+                    None => true,
+                };
+                if is_less {
+                    least = Some(i);
+                }
+            }
+        }
+        let Some(least) = least else {
+            break; // every run is empty
+        };
+        let next = read_record(&mut runs[least])?;
+        if let Some(record) = std::mem::replace(&mut heads[least], next) {
+            sorted.push(record);
+        }
+    }
+    Ok(sorted)
+}
+
+/// Attempts to write a record to a run file as its number of values followed by each value's
+/// length and the bytes Data::as_bytes gives for it.
+///
+/// # Errors
+///
+/// Fails when the file cannot be written to.
+fn write_record(file: &mut impl Write, record: &[Data]) -> Result<(), Box<dyn Error>> {
+    file.write_all(&(record.len() as u32).to_be_bytes())?;
+    for data in record {
+        let bytes = data.as_bytes();
+        file.write_all(&(bytes.len() as u32).to_be_bytes())?;
+        file.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Attempts to read the next record written by write_record out of a run file.
+/// Returns None at the end of the file.
+///
+/// # Errors
+///
+/// Fails when the file cannot be read from or ends in the middle of a record.
+fn read_record(file: &mut impl Read) -> Result<Option<Vec<Data>>, Box<dyn Error>> {
+    let mut len = [0; 4];
+    match file.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(Box::new(err)),
+    }
+
+    let value_count = u32::from_be_bytes(len) as usize;
+    let mut record = Vec::with_capacity(value_count);
+    for _ in 0..value_count {
+        file.read_exact(&mut len)
+            .map_err(|_| DBError::FileFormatError("Sort run ended in the middle of a record."))?;
+        let mut bytes = vec![0; u32::from_be_bytes(len) as usize];
+        file.read_exact(&mut bytes)
+            .map_err(|_| DBError::FileFormatError("Sort run ended in the middle of a record."))?;
+        record.push(Data::from_bytes(&bytes)?);
+    }
+    Ok(Some(record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Integer, Text};
+
+    /// Returns an empty directory for a test to keep its run files in, ending in a '/'.
+    fn test_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("mini_dbms_sort_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        format!("{}/", dir.display())
+    }
+
+    fn file_count(dir: &str) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    /// Returns 200 records of a key with many duplicates and some NULLs, the number
+    /// the record was pushed as, and a Text value.
+    fn records() -> Vec<Vec<Data>> {
+        (0..200)
+            .map(|i| {
+                let key = match i % 11 {
+                    0 => Data::Null,
+                    _ => Data::Integer(Integer::wrap(i * 37 % 10)),
+                };
+                let text = Data::Text(Text::from(&format!("record {i}")).unwrap());
+                vec![key, Data::Integer(Integer::wrap(i)), text]
+            })
+            .collect()
+    }
+
+    /// Pushes every record through a Sorter small enough to write many runs into dir,
+    /// checking that the runs are written and then removed, and returns the sorted records.
+    fn sort_in_runs(dir: &str, sort_keys: Vec<SortKey>) -> Vec<Vec<Data>> {
+        let mut sorter = Sorter::new(sort_keys, 1000, |run| format!("{dir}{run}.sort"));
+        for record in records() {
+            sorter.push(record).unwrap();
+        }
+        assert!(file_count(dir) > 10);
+        let sorted = sorter.finish().unwrap();
+        assert_eq!(file_count(dir), 0);
+        sorted
+    }
+
+    #[test]
+    fn runs_merge_in_order() {
+        let dir = test_dir("ascending");
+        let sorted = sort_in_runs(&dir, vec![(0, false)]);
+        // the sort in memory is stable, so equal keys keep the order they were pushed in
+        let mut expected = records();
+        expected.sort_by(|rec1, rec2| rec1[0].cmp(&rec2[0]));
+        assert_eq!(sorted, expected);
+        assert_eq!(sorted[0][0], Data::Null);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn runs_merge_in_descending_order() {
+        let dir = test_dir("descending");
+        let sorted = sort_in_runs(&dir, vec![(0, true)]);
+        let mut expected = records();
+        expected.sort_by(|rec1, rec2| rec2[0].cmp(&rec1[0]));
+        assert_eq!(sorted, expected);
+        assert_eq!(sorted[sorted.len() - 1][0], Data::Null);
+
+        // a second key breaks the ties of the first
+        let sorted = sort_in_runs(&dir, vec![(0, true), (1, true)]);
+        expected.sort_by(|rec1, rec2| rec2[0].cmp(&rec1[0]).then(rec2[1].cmp(&rec1[1])));
+        assert_eq!(sorted, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn small_sorts_stay_in_memory() {
+        let dir = test_dir("in_memory");
+        let mut sorter = Sorter::new(vec![(1, true)], SORT_MEMORY_LIMIT, |run| {
+            format!("{dir}{run}.sort")
+        });
+        for record in records() {
+            sorter.push(record).unwrap();
+        }
+        assert_eq!(file_count(&dir), 0);
+        let mut expected = records();
+        expected.reverse();
+        assert_eq!(sorter.finish().unwrap(), expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dropped_sorters_remove_their_runs() {
+        let dir = test_dir("dropped");
+        let mut sorter = Sorter::new(vec![(0, false)], 1000, |run| format!("{dir}{run}.sort"));
+        for record in records() {
+            sorter.push(record).unwrap();
+        }
+        assert!(file_count(&dir) > 0);
+        drop(sorter);
+        assert_eq!(file_count(&dir), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+use crate::DBError;
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The name of the log file kept in every database directory
+const WAL_FILE_NAME: &str = "db.wal";
+
+/// A single physical change to a file in the database directory. Every op overwrites
+/// whole byte ranges or lengths, so replaying an op that was already applied is harmless.
+#[derive(Debug)]
+pub enum WalOp {
+    /// Overwrite the bytes at offset, extending (and creating) the file if needed
+    Write {
+        path: String,
+        offset: u64,
+        bytes: Vec<u8>,
+    },
+    /// Truncate or extend the file to len, creating it if needed
+    SetLen { path: String, len: u64 },
+    /// Remove the file if it exists
+    Remove { path: String },
+}
+
+impl WalOp {
+    /// Returns the path of the file this op changes
+    pub fn path(&self) -> &str {
+        match self {
+            WalOp::Write { path, .. } | WalOp::SetLen { path, .. } | WalOp::Remove { path } => path,
+        }
+    }
+
+    /// Returns a copy of this op that changes the file at path instead
+    pub fn with_path(&self, path: String) -> WalOp {
+        match self {
+            WalOp::Write { offset, bytes, .. } => WalOp::Write {
+                path,
+                offset: *offset,
+                bytes: bytes.clone(),
+            },
+            WalOp::SetLen { len, .. } => WalOp::SetLen { path, len: *len },
+            WalOp::Remove { .. } => WalOp::Remove { path },
+        }
+    }
+
+    /// Appends the byte representation of this op to buf.
+    /// The format is a one byte tag, a u16 length prefixed path, and then the op's payload.
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let (tag, path) = match self {
+            WalOp::Write { path, .. } => (0_u8, path),
+            WalOp::SetLen { path, .. } => (1_u8, path),
+            WalOp::Remove { path } => (2_u8, path),
+        };
+        buf.push(tag);
+        buf.extend_from_slice(&(path.len() as u16).to_be_bytes());
+        buf.extend_from_slice(path.as_bytes());
+        match self {
+            WalOp::Write { offset, bytes, .. } => {
+                buf.extend_from_slice(&offset.to_be_bytes());
+                buf.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+                buf.extend_from_slice(bytes);
+            }
+            WalOp::SetLen { len, .. } => buf.extend_from_slice(&len.to_be_bytes()),
+            WalOp::Remove { .. } => (),
+        }
+    }
+
+    /// Reads an op back from the front of bytes. Returns the op and the number of bytes consumed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when bytes does not start with a complete op.
+    fn decode(bytes: &[u8]) -> Result<(Self, usize), DBError> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        let tag = reader.take(1)?[0];
+        let path_len = u16::from_be_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+        let path = String::from_utf8(reader.take(path_len)?.to_vec())
+            .map_err(|_| DBError::FileFormatError("Write-ahead log path is not valid utf-8."))?;
+        let op = match tag {
+            0 => {
+                let offset = reader.take_u64()?;
+                let len = reader.take_u64()? as usize;
+                let bytes = reader.take(len)?.to_vec();
+                WalOp::Write {
+                    path,
+                    offset,
+                    bytes,
+                }
+            }
+            1 => WalOp::SetLen {
+                path,
+                len: reader.take_u64()?,
+            },
+            2 => WalOp::Remove { path },
+            _ => {
+                return Err(DBError::FileFormatError(
+                    "Unknown op type in write-ahead log.",
+                ))
+            }
+        };
+        Ok((op, reader.pos))
+    }
+
+    /// Carries out the op against the filesystem without logging it.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be opened, written, or removed.
+    pub fn apply(&self) -> Result<(), io::Error> {
+        match self {
+            WalOp::Write {
+                path,
+                offset,
+                bytes,
+            } => {
+                let mut file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(path)?;
+                file.seek(SeekFrom::Start(*offset))?;
+                file.write_all(bytes)
+            }
+            WalOp::SetLen { path, len } => OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?
+                .set_len(*len),
+            WalOp::Remove { path } => {
+                if Path::new(path).exists() {
+                    fs::remove_file(path)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A small cursor over a byte slice that reports truncated input as an error
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Returns the next len bytes and moves past them.
+    ///
+    /// # Errors
+    ///
+    /// Fails when fewer than len bytes remain.
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DBError> {
+        let end = self.pos.saturating_add(len);
+        match self.bytes.get(self.pos..end) {
+            Some(slice) => {
+                self.pos += len;
+                Ok(slice)
+            }
+            None => Err(DBError::FileFormatError(
+                "Write-ahead log entry ended early.",
+            )),
+        }
+    }
+
+    /// Returns the next 8 bytes read as a big endian u64.
+    ///
+    /// # Errors
+    ///
+    /// Fails when fewer than 8 bytes remain.
+    fn take_u64(&mut self) -> Result<u64, DBError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// A handle to the write-ahead log of one database directory.
+/// Every change to a .dat file is appended here and flushed to disk before the
+/// .dat file itself is touched, so a crash can always be repaired by replaying the log.
+///
+/// Entries are written as an 8 byte payload length, an 8 byte checksum of the payload,
+/// and then the payload itself. The payload is an 8 byte op count followed by each op.
+#[derive(Debug, Clone)]
+pub struct Wal {
+    path: String,
+}
+
+impl Wal {
+    /// Returns a handle to the log kept in the database directory dir.
+    /// The file itself is only created once the first entry is written.
+    pub fn open(dir: &str) -> Self {
+        Wal {
+            path: format!("{dir}{WAL_FILE_NAME}"),
+        }
+    }
+
+    /// Appends all ops as a single entry, flushes the log to disk, and then applies the ops.
+    /// Either every op in the list survives a crash or none of them do.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the log cannot be written or when an op cannot be applied.
+    pub fn commit(&self, ops: &[WalOp]) -> Result<(), Box<dyn Error>> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&(ops.len() as u64).to_be_bytes());
+        for op in ops {
+            op.encode(&mut payload);
+        }
+
+        let mut entry = Vec::with_capacity(payload.len() + 16);
+        entry.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        entry.extend_from_slice(&checksum(&payload).to_be_bytes());
+        entry.append(&mut payload);
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        file.write_all(&entry)?;
+        file.sync_data()?; // the entry must be on disk before any table file changes
+
+        for op in ops {
+            op.apply()?;
+        }
+        Ok(())
+    }
+
+    /// Re-applies every complete entry in the log. A torn entry at the end of the log
+    /// (from a crash in the middle of a commit) is ignored since none of its ops were applied.
+    /// Returns true if there was anything to replay.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the log cannot be read or when an op cannot be applied.
+    pub fn replay(&self) -> Result<bool, Box<dyn Error>> {
+        let Some(buf) = self.read()? else {
+            return Ok(false);
+        };
+        let (entries, valid_len) = decode_entries(&buf)?;
+        for op in entries.iter().flatten() {
+            op.apply()?;
+        }
+
+        // drop a torn tail so entries appended later are not hidden behind it
+        if valid_len < buf.len() {
+            let file = OpenOptions::new().write(true).open(&self.path)?;
+            file.set_len(valid_len as u64)?;
+            file.sync_data()?;
+        }
+
+        Ok(!entries.is_empty())
+    }
+
+    /// Empties the log. This must only be called once everything the log protects
+    /// (record counts and .index files) has been written out by the caller. Every file
+    /// the logged ops changed is flushed to disk first, along with the directories that
+    /// hold them so that created and removed files are too, since the log can no longer
+    /// redo those changes once it is empty.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the log cannot be read or truncated, or when a file cannot be flushed.
+    pub fn checkpoint(&self) -> Result<(), Box<dyn Error>> {
+        let Some(buf) = self.read()? else {
+            return Ok(());
+        };
+        let (entries, _) = decode_entries(&buf)?;
+
+        let mut paths: Vec<&str> = entries.iter().flatten().map(WalOp::path).collect();
+        paths.sort_unstable();
+        paths.dedup();
+        let mut dirs = Vec::new();
+        for path in paths {
+            // a file which was removed afterwards has nothing left to flush
+            if Path::new(path).exists() {
+                fs::File::open(path)?.sync_all()?;
+            }
+            if let Some(dir) = Path::new(path).parent() {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+        for dir in dirs {
+            sync_dir(dir)?;
+        }
+
+        let file = OpenOptions::new().write(true).open(&self.path)?;
+        file.set_len(0)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Attempts to read the whole log. Returns None when there is no log file.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the log exists but cannot be read.
+    fn read(&self) -> Result<Option<Vec<u8>>, io::Error> {
+        let mut buf = Vec::new();
+        match fs::File::open(&self.path) {
+            Ok(mut file) => file.read_to_end(&mut buf)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(Some(buf))
+    }
+}
+
+/// Reads the ops of every complete entry out of the bytes of a log, stopping at the first entry
+/// which is cut short or does not match its checksum. Returns the ops of each entry in order,
+/// along with the number of bytes the complete entries take.
+///
+/// # Errors
+///
+/// Fails when an entry matches its checksum but its ops cannot be read.
+fn decode_entries(buf: &[u8]) -> Result<(Vec<Vec<WalOp>>, usize), DBError> {
+    let mut entries = Vec::new();
+    let mut valid_len = 0; // end of the last complete entry
+    let mut reader = ByteReader { bytes: buf, pos: 0 };
+    while let (Ok(len), Ok(sum)) = (reader.take_u64(), reader.take_u64()) {
+        let payload = match reader.take(len as usize) {
+            Ok(payload) if checksum(payload) == sum => payload,
+            _ => break, // torn write at the tail of the log
+        };
+        valid_len = reader.pos;
+
+        let mut ops_reader = ByteReader {
+            bytes: payload,
+            pos: 0,
+        };
+        let mut ops = Vec::new();
+        for _ in 0..ops_reader.take_u64()? {
+            let (op, used) = WalOp::decode(&payload[ops_reader.pos..])?;
+            ops_reader.pos += used;
+            ops.push(op);
+        }
+        entries.push(ops);
+    }
+    Ok((entries, valid_len))
+}
+
+/// Flushes the list of files in dir to disk, so that files created in or removed from it
+/// stay that way after a power loss.
+///
+/// # Errors
+///
+/// Fails when the directory cannot be opened or flushed.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<(), io::Error> {
+    let dir = match dir.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => dir,
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened as files to flush them on other platforms, so only the
+/// files in them are flushed there.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<(), io::Error> {
+    Ok(())
+}
+
+/// Returns a 64-bit FNV-1a hash of bytes. Used to detect torn log entries.
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory for a test to keep its log and files in, ending in a '/'
+    /// the way database paths do.
+    fn test_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("mini_dbms_wal_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        format!("{}/", dir.display())
+    }
+
+    /// Returns the bytes of a log entry holding ops, the way Wal::commit writes it.
+    fn entry(ops: &[WalOp]) -> Vec<u8> {
+        let mut payload = (ops.len() as u64).to_be_bytes().to_vec();
+        for op in ops {
+            op.encode(&mut payload);
+        }
+        let mut entry = (payload.len() as u64).to_be_bytes().to_vec();
+        entry.extend_from_slice(&checksum(&payload).to_be_bytes());
+        entry.append(&mut payload);
+        entry
+    }
+
+    fn write_op(path: &str, offset: u64, bytes: &[u8]) -> WalOp {
+        WalOp::Write {
+            path: path.to_string(),
+            offset,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn replay_skips_and_drops_a_torn_tail() {
+        let dir = test_dir("torn");
+        let data = format!("{dir}t.dat");
+        let first = entry(&[write_op(&data, 0, b"abc")]);
+        let torn = entry(&[write_op(&data, 0, b"xyz")]);
+        let mut log = first.clone();
+        log.extend_from_slice(&torn[..torn.len() - 2]);
+        fs::write(format!("{dir}{WAL_FILE_NAME}"), &log).unwrap();
+
+        assert!(Wal::open(&dir).replay().unwrap());
+        assert_eq!(fs::read(&data).unwrap(), b"abc");
+        let log_len = fs::metadata(format!("{dir}{WAL_FILE_NAME}")).unwrap().len();
+        assert_eq!(log_len, first.len() as u64);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replay_stops_at_a_bad_checksum() {
+        let dir = test_dir("checksum");
+        let data = format!("{dir}t.dat");
+        let mut log = entry(&[write_op(&data, 0, b"abc")]);
+        let last = log.len() - 1;
+        log[last] ^= 0xFF; // corrupt the payload so it no longer matches its checksum
+        fs::write(format!("{dir}{WAL_FILE_NAME}"), &log).unwrap();
+
+        assert!(!Wal::open(&dir).replay().unwrap());
+        assert!(!Path::new(&data).exists());
+        let log_len = fs::metadata(format!("{dir}{WAL_FILE_NAME}")).unwrap().len();
+        assert_eq!(log_len, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replaying_an_entry_twice_gives_the_same_files() {
+        let dir = test_dir("twice");
+        let data = format!("{dir}t.dat");
+        let gone = format!("{dir}gone.dat");
+        fs::write(&gone, b"old").unwrap();
+        let wal = Wal::open(&dir);
+        wal.commit(&[
+            write_op(&data, 0, b"abcdef"),
+            WalOp::SetLen {
+                path: data.clone(),
+                len: 4,
+            },
+            write_op(&data, 2, b"XY"),
+            WalOp::Remove { path: gone.clone() },
+        ])
+        .unwrap();
+        assert_eq!(fs::read(&data).unwrap(), b"abXY");
+
+        assert!(wal.replay().unwrap());
+        assert!(wal.replay().unwrap());
+        assert_eq!(fs::read(&data).unwrap(), b"abXY");
+        assert!(!Path::new(&gone).exists());
+
+        wal.checkpoint().unwrap();
+        assert!(!wal.replay().unwrap());
+        assert_eq!(fs::read(&data).unwrap(), b"abXY");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}